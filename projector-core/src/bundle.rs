@@ -0,0 +1,302 @@
+//! 別のマシンへ持っていける状態のまとめ（`projector state export` / `import`）
+//!
+//! 設定ファイルのエイリアスとタグ、状態ファイルの最近開いたプロジェクト・開いた回数・作業時間の記録を
+//! 1つの JSON にする。取り込むときは上書きせずに手元のものと合わせるので、同じものを何度取り込んでも
+//! 結果は変わらない。ホームディレクトリの下のパスは `~/` で書き、取り込むマシンのホームに読み替える。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use toml_edit::{Array, DocumentMut, Item, Table};
+use tracing::debug;
+
+use crate::config::Config;
+use crate::location::Location;
+use crate::paths;
+use crate::state::{State, Visits, MAX_RECENT};
+use crate::timelog::Span;
+use crate::{Error, Result};
+
+/// 書き出す形式のバージョン。読めない新しい形式を取り込まないよう、変えたら上げる
+const VERSION: u32 = 1;
+
+/// 持ち運ぶ状態
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bundle {
+    /// 形式のバージョン
+    pub version: u32,
+    /// 設定のエイリアス（設定ファイルと同じ形式）
+    pub aliases: BTreeMap<String, String>,
+    /// 設定のタグ（設定ファイルと同じ形式）
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// 最近開いたプロジェクト。新しい順
+    pub recent: Vec<String>,
+    /// プロジェクトごとの開いた回数
+    pub visits: BTreeMap<String, Visits>,
+    /// 作業時間の記録
+    pub spans: Vec<Span>,
+}
+
+/// 取り込んで増えたものの数
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Merged {
+    /// 加えたエイリアス
+    pub aliases: usize,
+    /// タグに加えたプロジェクト
+    pub tags: usize,
+    /// 加えたか回数の増えたプロジェクト
+    pub visits: usize,
+    /// 加えた作業時間の記録
+    pub spans: usize,
+    /// 手元と行き先が違うので手元のままにしたエイリアス（名前、手元の行き先、取り込もうとした行き先）
+    pub conflicts: Vec<(String, String, String)>,
+}
+
+impl Bundle {
+    /// 今の設定と状態をまとめる
+    pub fn collect(config: &Config, state: &State) -> Self {
+        Bundle {
+            version: VERSION,
+            aliases: config.aliases.clone(),
+            tags: config.tags.clone(),
+            recent: state.recent.iter().map(|spec| portable(spec)).collect(),
+            visits: state
+                .visits
+                .iter()
+                .map(|(spec, visits)| (portable(spec), *visits))
+                .collect(),
+            spans: state
+                .spans
+                .iter()
+                .map(|span| Span {
+                    project: portable(&span.project),
+                    // このマシンのセッションはもう延びないものとして渡す
+                    open: false,
+                    ..span.clone()
+                })
+                .collect(),
+        }
+    }
+
+    /// JSON を読む。このバージョンで読めない新しい形式ならエラー
+    pub fn parse(content: &str) -> Result<Self> {
+        let bundle: Bundle =
+            serde_json::from_str(content).map_err(|e| Error::InvalidBundle(e.to_string()))?;
+        if bundle.version > VERSION {
+            return Err(Error::InvalidBundle(format!(
+                "version {} (this projector reads up to {})",
+                bundle.version, VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// 最近開いたもの・回数・作業時間の記録を状態に合わせる
+    ///
+    /// 回数と最後に開いた時刻は大きいほうを取り、作業時間は同じセッションの同じ開始時刻の記録を1つにする。
+    pub fn merge_state(&self, state: &mut State, merged: &mut Merged) {
+        for spec in &self.recent {
+            let spec = local_spec(spec);
+            if !state.recent.contains(&spec) {
+                state.recent.push(spec);
+            }
+        }
+        state.recent.truncate(MAX_RECENT);
+
+        for (spec, visits) in &self.visits {
+            let entry = state.visits.entry(local_spec(spec)).or_default();
+            let before = *entry;
+            entry.count = entry.count.max(visits.count);
+            entry.last = entry.last.max(visits.last);
+            if *entry != before {
+                merged.visits += 1;
+            }
+        }
+
+        for span in &self.spans {
+            let project = local_spec(&span.project);
+            let same = state.spans.iter_mut().find(|s| {
+                s.project == project && s.session == span.session && s.start == span.start
+            });
+            match same {
+                Some(same) => same.end = same.end.max(span.end),
+                None => {
+                    state.spans.push(Span {
+                        project,
+                        open: false,
+                        ..span.clone()
+                    });
+                    merged.spans += 1;
+                }
+            }
+        }
+        state.spans.sort_by_key(|span| span.start);
+    }
+
+    /// エイリアスとタグを設定ファイルに書き足す。書式やコメントはそのまま残す
+    ///
+    /// 手元に同じ名前のエイリアスがあれば手元のままにし、タグには手元に無いプロジェクトだけを加える。
+    pub fn merge_config(&self, path: &Path, merged: &mut Merged, dry_run: bool) -> Result<()> {
+        if self.aliases.is_empty() && self.tags.is_empty() {
+            return Ok(());
+        }
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(source) => {
+                return Err(Error::ConfigRead {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        // 壊れた設定ファイルには書き足さない
+        if let Err(source) = toml::from_str::<Config>(&content) {
+            return Err(Error::ConfigParse {
+                path: path.to_path_buf(),
+                source,
+            });
+        }
+        let write_error = |source| Error::ConfigWrite {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut document: DocumentMut = content
+            .parse()
+            .map_err(|e| write_error(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+        if !self.aliases.is_empty() {
+            let aliases = table(&mut document, "aliases");
+            for (name, target) in &self.aliases {
+                match aliases.get(name).and_then(Item::as_str) {
+                    None => {
+                        aliases.insert(name, toml_edit::value(target.as_str()));
+                        merged.aliases += 1;
+                    }
+                    Some(local) if local != target => {
+                        merged
+                            .conflicts
+                            .push((name.clone(), local.to_string(), target.clone()));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        if !self.tags.is_empty() {
+            let tags = table(&mut document, "tags");
+            for (tag, targets) in &self.tags {
+                let item = tags
+                    .entry(tag)
+                    .or_insert_with(|| toml_edit::value(Array::new()));
+                let Some(array) = item.as_array_mut() else {
+                    continue;
+                };
+                for target in targets {
+                    if !array.iter().any(|value| value.as_str() == Some(target)) {
+                        array.push(target.as_str());
+                        merged.tags += 1;
+                    }
+                }
+            }
+        }
+
+        if dry_run || merged.aliases + merged.tags == 0 {
+            return Ok(());
+        }
+        let write = || {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, document.to_string())
+        };
+        write().map_err(write_error)?;
+        debug!(path = %path.display(), "merged aliases and tags into the config");
+        Ok(())
+    }
+}
+
+/// 設定ファイルの表。無ければ末尾に作る
+fn table<'a>(document: &'a mut DocumentMut, key: &str) -> &'a mut Table {
+    let item = document.entry(key).or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(false);
+        Item::Table(table)
+    });
+    // `aliases = { ... }` のような書き方も受け付ける
+    if let Some(inline) = item.as_inline_table().cloned() {
+        *item = Item::Table(inline.into_table());
+    }
+    item.as_table_mut()
+        .expect("aliases and tags are tables in a valid config")
+}
+
+/// ホームディレクトリの下なら `~/` で書いた場所（[`Location::spec`] の形式）
+fn portable(spec: &str) -> String {
+    match Location::parse(spec) {
+        Location::Local(path) => paths::shorten_path(&path),
+        remote => remote.spec(),
+    }
+}
+
+/// 持ってきた場所をこのマシンの [`Location::spec`] にする
+fn local_spec(spec: &str) -> String {
+    Location::parse(spec).spec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_twice_changes_nothing() {
+        let mut state = State {
+            recent: vec!["/a".to_string()],
+            visits: BTreeMap::from([(
+                "/a".to_string(),
+                Visits {
+                    count: 5,
+                    last: 100,
+                },
+            )]),
+            ..State::default()
+        };
+        let bundle = Bundle {
+            recent: vec!["/b".to_string(), "/a".to_string()],
+            visits: BTreeMap::from([
+                (
+                    "/a".to_string(),
+                    Visits {
+                        count: 2,
+                        last: 300,
+                    },
+                ),
+                ("/b".to_string(), Visits { count: 1, last: 50 }),
+            ]),
+            spans: vec![Span::start("/b".to_string(), "b".to_string(), 10)],
+            ..Bundle::default()
+        };
+
+        let mut merged = Merged::default();
+        bundle.merge_state(&mut state, &mut merged);
+        assert_eq!(state.recent, ["/a", "/b"]);
+        assert_eq!(
+            state.visits["/a"],
+            Visits {
+                count: 5,
+                last: 300
+            }
+        );
+        assert!(!state.spans[0].open);
+        assert_eq!((merged.visits, merged.spans), (2, 1));
+
+        let mut again = Merged::default();
+        bundle.merge_state(&mut state, &mut again);
+        assert_eq!(again, Merged::default());
+        assert_eq!(state.spans.len(), 1);
+    }
+}
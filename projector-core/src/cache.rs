@@ -0,0 +1,77 @@
+//! ディスク上の TTL 付きキャッシュ
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    value: Value,
+}
+
+/// キャッシュディレクトリに JSON で保存する TTL 付きのキャッシュ
+pub struct DiskCache {
+    path: Option<PathBuf>,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl DiskCache {
+    /// `dir` の中の `name`（拡張子なし）のファイルを開く。`dir` が無ければメモリ上だけに持つ
+    pub fn open(dir: Option<&Path>, name: &str) -> Self {
+        let path = dir.map(|dir| dir.join(format!("{}.json", name)));
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        DiskCache { path, entries }
+    }
+
+    /// `ttl` 以内に保存された値を返す
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<&Value> {
+        let entry = self.entries.get(key)?;
+        if now().saturating_sub(entry.fetched_at) > ttl.as_secs() {
+            return None;
+        }
+        Some(&entry.value)
+    }
+
+    /// 値を保存してファイルに書き出す
+    pub fn put(&mut self, key: &str, value: Value) {
+        self.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                fetched_at: now(),
+                value,
+            },
+        );
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        // キャッシュの書き込みに失敗しても動作には影響しないので無視する
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(content) = serde_json::to_string(&self.entries) {
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+/// 現在の UNIX 時刻（秒）
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,98 @@
+//! docker compose との連携
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yml",
+    "docker-compose.yaml",
+];
+
+/// 稼働中のサービス数と定義されているサービス数
+#[derive(Clone, Copy)]
+pub struct ComposeStatus {
+    /// 稼働中のサービス数
+    pub running: usize,
+    /// compose ファイルに定義されたサービス数
+    pub total: usize,
+}
+
+/// compose ファイルを探す
+pub fn compose_file(project: &Path) -> Option<PathBuf> {
+    COMPOSE_FILES
+        .iter()
+        .map(|name| project.join(name))
+        .find(|path| path.is_file())
+}
+
+/// docker が使えなければ None
+pub fn status(runner: &dyn CommandRunner, project: &Path) -> Option<ComposeStatus> {
+    let services = runner
+        .output(&compose(project, &["config", "--services"]).read_only())
+        .ok()?;
+    if !services.success {
+        return None;
+    }
+    let total = services
+        .stdout_str()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count();
+
+    let ps = runner
+        .output(&compose(project, &["ps", "--format", "json"]).read_only())
+        .ok()?;
+    if !ps.success {
+        return None;
+    }
+    // バージョンによって JSON 配列か1行1オブジェクトかが異なる
+    let stdout = ps.stdout_str();
+    let containers: Vec<Value> = match serde_json::from_str::<Value>(&stdout) {
+        Ok(Value::Array(containers)) => containers,
+        _ => stdout
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect(),
+    };
+    let running = containers
+        .iter()
+        .filter(|c| c["State"].as_str() == Some("running"))
+        .count();
+
+    Some(ComposeStatus { running, total })
+}
+
+/// `docker compose up -d` でスタックを起動する
+pub fn up(runner: &dyn CommandRunner, project: &Path) -> Result<()> {
+    let success = runner
+        .interactive(&compose(project, &["up", "-d"]))
+        .map_err(|e| Error::spawn("docker", e))?;
+    if !success {
+        return Err(Error::CommandFailed("docker compose up".to_string()));
+    }
+    Ok(())
+}
+
+/// `docker compose down` でスタックを停止する
+pub fn down(runner: &dyn CommandRunner, project: &Path) -> Result<()> {
+    let success = runner
+        .interactive(&compose(project, &["down"]))
+        .map_err(|e| Error::spawn("docker", e))?;
+    if !success {
+        return Err(Error::CommandFailed("docker compose down".to_string()));
+    }
+    Ok(())
+}
+
+fn compose(project: &Path, args: &[&str]) -> Cmd {
+    Cmd::new("docker")
+        .arg("compose")
+        .args(args)
+        .current_dir(project)
+}
@@ -0,0 +1,832 @@
+//! 設定ファイルの読み込み
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::fallback;
+use crate::ghostty;
+use crate::location::Location;
+use crate::migrate::{self, Outcome};
+use crate::paths;
+use crate::scan::ScanOptions;
+use crate::shell;
+use crate::{Error, Result};
+
+/// プロジェクトとみなすディレクトリにあるものの既定値（バージョン管理と主な言語のマニフェスト）
+pub const DEFAULT_MARKERS: &[&str] = &[
+    ".git",
+    ".hg",
+    ".jj",
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "Gemfile",
+    "pom.xml",
+    "build.gradle",
+    "mix.exs",
+];
+
+/// 設定ファイル全体
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// ブラウズするルート。`ssh://host/~/dir` 形式でリモートも指定できる
+    pub roots: Vec<String>,
+    /// プロジェクトの別名。値はルートからの相対パス（`work/acme/api`）か、
+    /// `/` `~/` `ssh://` で始まる場所
+    pub aliases: BTreeMap<String, String>,
+    /// プロジェクトに付けるタグ。値は別名と同じ形式の場所の一覧で、TUI の T で絞り込める
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// TUI で入れる階層の深さ。ルート直下を 1 とし、これより深い一覧には入らない（未設定なら無制限）。
+    /// 全ルートのプロジェクトを探す深さにもなる。: で直接飛ぶ先は制限しない
+    pub max_depth: Option<usize>,
+    /// シンボリックリンクのディレクトリの扱い
+    pub symlinks: SymlinkPolicy,
+    /// 一覧に出さないディレクトリ名のパターン（`*.bak` `archive` のような glob）
+    pub ignore: Vec<String>,
+    /// 一覧の並び順
+    pub sort: SortOrder,
+    /// プロジェクトとみなすディレクトリにあるファイル・ディレクトリ名。
+    /// TUI の Space ではプロジェクトの中に入らず、全ルートの一覧ではその下を辿らない
+    ///
+    /// 未設定なら [`DEFAULT_MARKERS`]。
+    pub markers: Option<Vec<String>>,
+    /// TUI の絞り込み
+    pub filter: FilterConfig,
+    /// TUI を前回終了したときのディレクトリと選択から始める
+    pub resume: bool,
+    /// TUI の印や罫線を ASCII だけで描く（`❯` や `│` が崩れるフォント・端末向け）
+    pub ascii: bool,
+    /// TUI のヘッダーの操作説明の行（`false` で出さない、文字列ならそれに置き換える）
+    pub hint: HintConfig,
+    /// TUI の詳細ペインの「健全性」の行に出す項目
+    pub health: HealthConfig,
+    /// TUI のリーダーキーと、その後に続けて押すキーの割り当て
+    pub keys: KeysConfig,
+    /// TUI の a でプロジェクトを移す場所。相対パスならプロジェクトのあるルートからの位置
+    /// （未設定なら `.archive`）
+    pub archive: Option<String>,
+    /// 表示言語（`ja` / `en` / `auto`）。未設定ならロケールから決める
+    pub language: Option<String>,
+    /// プロジェクトの選び方
+    pub picker: Picker,
+    /// プロジェクトを開く端末の仕組み
+    pub backend: Backend,
+    /// tmux などが無いときに起動するシェル
+    pub fallback: FallbackConfig,
+    /// Enter で開くときの動作
+    pub open: OpenConfig,
+    /// tmux セッションのペイン構成
+    pub layout: LayoutConfig,
+    /// 操作から起動する外部ツール
+    pub tools: ToolsConfig,
+    /// TUI の一覧のリポジトリをバックグラウンドで fetch する
+    pub fetch: FetchConfig,
+    /// GitHub 連携
+    pub github: GithubConfig,
+    /// GitLab 連携
+    pub gitlab: GitlabConfig,
+    /// Gitea / Codeberg 連携
+    pub gitea: GiteaConfig,
+    /// docker compose 連携
+    pub compose: ComposeConfig,
+    /// Nix 連携
+    pub nix: NixConfig,
+    /// ウィンドウマネージャーとの連携
+    pub wm: WmConfig,
+    /// macOS の端末アプリとの連携
+    pub macos: MacosConfig,
+}
+
+impl Config {
+    /// ルートの一覧。未設定なら `~/Developer`
+    ///
+    /// 環境変数 `PROJECTOR_ROOT` があればそのルートに置き換える。先頭が `+` なら設定のルートに追加する。
+    /// 複数のルートは PATH と同じ区切り文字（Windows は `;`、それ以外は `:`）で並べる。
+    pub fn roots(&self) -> Vec<String> {
+        let mut roots = if self.roots.is_empty() {
+            vec!["~/Developer".to_string()]
+        } else {
+            self.roots.clone()
+        };
+        if let Some(value) = std::env::var("PROJECTOR_ROOT")
+            .ok()
+            .filter(|value| !value.is_empty())
+        {
+            match value.strip_prefix('+') {
+                Some(extra) => roots.extend(split_roots(extra)),
+                None => roots = split_roots(&value),
+            }
+        }
+        roots
+    }
+
+    /// ディレクトリの走査のしかた
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            symlinks: self.symlinks,
+            ignore: self.ignore.clone(),
+            sort: self.sort,
+            hidden: false,
+            markers: self
+                .markers
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MARKERS.iter().map(|m| m.to_string()).collect()),
+            roots: self
+                .roots()
+                .iter()
+                .filter_map(|root| Location::parse(root).local_path().map(Path::to_path_buf))
+                .collect(),
+        }
+    }
+
+    /// プロジェクトを開くときのペイン構成。`.projector/config.toml` に layout があればそちらを使う
+    pub fn layout_for(&self, location: &Location) -> LayoutConfig {
+        let Some(path) = location.local_path() else {
+            return self.layout.clone();
+        };
+        local_configs(path, &self.scan_options().roots)
+            .into_iter()
+            .rev()
+            .find_map(|local| local.layout)
+            .unwrap_or_else(|| self.layout.clone())
+    }
+}
+
+/// `PROJECTOR_ROOT` の値をルートごとに分ける。`ssh://` の `:` では区切らない
+fn split_roots(value: &str) -> Vec<String> {
+    let separator = if cfg!(windows) { ';' } else { ':' };
+    let mut roots: Vec<String> = vec![];
+    for part in value.split(separator) {
+        match roots.last_mut() {
+            Some(last) if last == "ssh" && part.starts_with("//") => {
+                last.push(separator);
+                last.push_str(part);
+            }
+            _ => roots.push(part.to_string()),
+        }
+    }
+    roots.retain(|root| !root.is_empty());
+    roots
+}
+
+/// GitHub 連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GithubConfig {
+    /// デフォルトブランチの CI ステータスを一覧に表示する
+    pub ci_status: bool,
+    /// 詳細ペインにオープンな PR / Issue の数を表示する
+    pub open_counts: bool,
+    /// API トークン。未設定なら GITHUB_TOKEN / GH_TOKEN 環境変数、`gh auth token`、
+    /// キーチェーン（git の credential helper）の順に探す（公開リポジトリなら不要）
+    pub token: Option<String>,
+}
+
+/// GitLab 連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GitlabConfig {
+    /// デフォルトブランチのパイプラインの結果を一覧に表示する
+    pub ci_status: bool,
+    /// 詳細ペインにオープンな MR / Issue の数を表示する
+    pub open_counts: bool,
+    /// gitlab.com の API トークン。未設定なら GITLAB_TOKEN 環境変数、glab の設定、キーチェーンの順に探す
+    /// （公開プロジェクトなら不要）
+    pub token: Option<String>,
+    /// セルフホストの GitLab。キーはリモート URL のホスト名（`[gitlab.hosts."gitlab.example.com"]`）
+    pub hosts: BTreeMap<String, ForgeHost>,
+}
+
+impl GitlabConfig {
+    /// gitlab.com とセルフホストの GitLab のホスト名と設定
+    pub fn instances(&self) -> Vec<(String, ForgeHost)> {
+        let mut gitlab_com = self.hosts.get(GITLAB_COM).cloned().unwrap_or_default();
+        gitlab_com.token = gitlab_com.token.or_else(|| self.token.clone());
+        instances(GITLAB_COM, gitlab_com, &self.hosts)
+    }
+}
+
+/// gitlab.com のホスト名
+pub const GITLAB_COM: &str = "gitlab.com";
+
+/// Gitea 互換のサービス（Codeberg やセルフホストの Gitea / Forgejo）との連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GiteaConfig {
+    /// デフォルトブランチのコミットのステータスを一覧に表示する
+    pub ci_status: bool,
+    /// 詳細ペインにオープンな PR / Issue の数を表示する
+    pub open_counts: bool,
+    /// セルフホストの Gitea。キーはリモート URL のホスト名（`[gitea.hosts."git.example.com"]`）。
+    /// codeberg.org は設定しなくても使える
+    pub hosts: BTreeMap<String, ForgeHost>,
+}
+
+impl GiteaConfig {
+    /// codeberg.org とセルフホストの Gitea のホスト名と設定
+    pub fn instances(&self) -> Vec<(String, ForgeHost)> {
+        let codeberg = self.hosts.get(CODEBERG).cloned().unwrap_or_default();
+        instances(CODEBERG, codeberg, &self.hosts)
+    }
+}
+
+/// codeberg.org のホスト名
+pub const CODEBERG: &str = "codeberg.org";
+
+/// 公開サービスを先にしたホストの一覧
+fn instances(
+    public: &str,
+    settings: ForgeHost,
+    hosts: &BTreeMap<String, ForgeHost>,
+) -> Vec<(String, ForgeHost)> {
+    let mut instances = vec![(public.to_string(), settings)];
+    instances.extend(
+        hosts
+            .iter()
+            .filter(|(host, _)| host.as_str() != public)
+            .map(|(host, settings)| (host.clone(), settings.clone())),
+    );
+    instances
+}
+
+/// セルフホストのサービスの設定
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ForgeHost {
+    /// API トークン。未設定ならサービスの CLI の設定、キーチェーンの順に探す
+    pub token: Option<String>,
+    /// API の URL。未設定なら `https://<ホスト名>/api/v4`（Gitea は `/api/v1`）
+    pub api: Option<String>,
+}
+
+/// Enter で開くときの動作の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct OpenConfig {
+    /// tmux の代わりに実行するコマンド（例: `code {path}`）。ローカルのプロジェクトにだけ使う
+    ///
+    /// 使える変数は [`LayoutConfig`] と同じ。
+    pub command: Option<String>,
+    /// TUI の Enter でプロジェクトの目印が無いディレクトリを開く前に確認する
+    /// （途中のディレクトリからうっかりセッションを作らないように）
+    pub confirm_plain_dirs: bool,
+}
+
+/// tmux セッションのペイン構成の設定
+///
+/// コマンドでは `{path}` `{name}` `{session}` `{root}` `{branch}` `{host}` が使える。
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// 左から順に並べるペインで実行するコマンド。空文字列ならシェル（Nix の開発環境があればその中）
+    pub panes: Vec<String>,
+    /// ウィンドウマネージャーのワークスペース名。未設定ならプロジェクト名
+    pub workspace: Option<String>,
+    /// 作ったセッションに `set-option` する tmux のオプション（`mouse = true` `history-limit = 50000` など）。
+    /// 値は変数を展開せずにそのまま渡すので、tmux の `#{...}` をそのまま書ける
+    pub options: BTreeMap<String, OptionValue>,
+    /// ペインへのキー入力をすべてのペインに送る（tmux の `synchronize-panes`）。
+    /// 複数のホストやサービスで同じコマンドを打つレイアウト向け
+    pub synchronize: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            panes: vec![String::new(), String::new()],
+            workspace: None,
+            options: BTreeMap::new(),
+            synchronize: false,
+        }
+    }
+}
+
+/// tmux のオプションの値
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    /// `on` / `off`
+    Flag(bool),
+    /// 数値
+    Number(i64),
+    /// そのまま渡す文字列
+    Text(String),
+}
+
+impl OptionValue {
+    /// `set-option` に渡す値
+    pub fn to_tmux(&self) -> String {
+        match self {
+            OptionValue::Flag(true) => "on".to_string(),
+            OptionValue::Flag(false) => "off".to_string(),
+            OptionValue::Number(n) => n.to_string(),
+            OptionValue::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// TUI のヘッダーの操作説明の行
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum HintConfig {
+    /// `true` なら既定の説明、`false` なら行ごと出さない
+    Show(bool),
+    /// この文字列を出す
+    Text(String),
+}
+
+impl Default for HintConfig {
+    fn default() -> Self {
+        HintConfig::Show(true)
+    }
+}
+
+/// TUI の詳細ペインの「健全性」の行に出す項目。すべて `false` なら行ごと出さない
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// 最後のコミットからの経過時間
+    pub last_commit: bool,
+    /// コミットしていない変更があるか
+    pub dirty: bool,
+    /// デフォルトブランチの CI の結果（CI ステータスの連携が有効なとき）
+    pub ci: bool,
+    /// オープンな PR / MR の数（オープンな数の連携が有効なとき）
+    pub pull_requests: bool,
+    /// TODO / FIXME の数
+    pub todos: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            last_commit: true,
+            dirty: true,
+            ci: true,
+            pull_requests: true,
+            todos: true,
+        }
+    }
+}
+
+/// TUI のリーダーキーの設定
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// 続けて押すキーの操作を始めるキー
+    pub leader: char,
+    /// リーダーキーの後に押すキー（空白区切り、空白キーは `space`）と操作の名前。
+    /// 既定の割り当てに足し、同じキーなら置き換える（例: `"o l" = "git"`）
+    pub chords: BTreeMap<String, String>,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        KeysConfig {
+            leader: ',',
+            chords: BTreeMap::new(),
+        }
+    }
+}
+
+/// TUI の絞り込みの設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    /// 大文字と小文字を区別するか
+    pub case: CaseMode,
+}
+
+/// 絞り込みで大文字と小文字を区別するか
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseMode {
+    /// 検索語に大文字があるときだけ区別する（ripgrep / fzf と同じ）
+    #[default]
+    Smart,
+    /// 常に区別する
+    Sensitive,
+    /// 常に区別しない
+    Insensitive,
+}
+
+/// 操作から起動する外部ツールの設定。変数は [`LayoutConfig`] と同じものが使える
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// `g` で2つ目のペインに起動する git の UI
+    pub git: String,
+    /// `e` で開くファイルマネージャー。未設定なら yazi / lf / ranger のうち見つかったもの
+    pub files: Option<String>,
+    /// `--picker dmenu` で使う dmenu 互換のメニュー（`wofi --dmenu` や `fuzzel --dmenu` など）
+    pub dmenu: String,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        ToolsConfig {
+            git: "lazygit".to_string(),
+            files: None,
+            dmenu: "dmenu -i -p projector".to_string(),
+        }
+    }
+}
+
+impl ToolsConfig {
+    /// 起動するファイルマネージャーのコマンド
+    pub fn file_manager(&self) -> String {
+        if let Some(files) = &self.files {
+            return files.clone();
+        }
+        ["yazi", "lf", "ranger"]
+            .into_iter()
+            .find(|program| shell::command_exists(program))
+            .unwrap_or("yazi")
+            .to_string()
+    }
+}
+
+/// バックグラウンドの fetch の設定
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FetchConfig {
+    /// TUI の一覧に出たリポジトリを `git fetch` し、upstream との差を最新にする
+    pub enabled: bool,
+    /// 同時に fetch するリポジトリの数
+    pub jobs: usize,
+    /// 同じリポジトリを fetch し直すまでの分数
+    pub interval: u64,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            enabled: false,
+            jobs: 4,
+            interval: 15,
+        }
+    }
+}
+
+/// docker compose 連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ComposeConfig {
+    /// compose で開いたセッションが終了していたら docker compose down する
+    pub down_on_exit: bool,
+}
+
+/// Nix 連携の設定
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct NixConfig {
+    /// flake.nix / shell.nix があるプロジェクトでは各ペインを Nix の開発環境で起動する
+    pub enabled: bool,
+    /// flake.nix があるときのペインのコマンド（変数は [`LayoutConfig`] と同じ）
+    pub flake_command: String,
+    /// shell.nix があるときのペインのコマンド
+    pub shell_command: String,
+}
+
+impl Default for NixConfig {
+    fn default() -> Self {
+        NixConfig {
+            enabled: true,
+            flake_command: "nix develop --command \"$SHELL\"".to_string(),
+            shell_command: "nix-shell --run \"$SHELL\"".to_string(),
+        }
+    }
+}
+
+/// ウィンドウマネージャー（Hyprland / i3 / sway）との連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct WmConfig {
+    /// 開くときにプロジェクト名のワークスペースを用意する。未設定なら何もしない
+    pub workspace: Option<WorkspaceMode>,
+    /// `w` でワークスペースに開く端末。末尾に `-e` などで実行させるコマンドを付けて起動する
+    ///
+    /// 未設定なら `$TERMINAL -e`（`TERMINAL` も無ければ `kitty -e`）。
+    pub terminal: Option<String>,
+}
+
+impl WmConfig {
+    /// ワークスペースに開く端末のコマンド
+    pub fn terminal(&self) -> String {
+        if let Some(terminal) = &self.terminal {
+            return terminal.clone();
+        }
+        let program = std::env::var("TERMINAL")
+            .ok()
+            .filter(|program| !program.is_empty())
+            .unwrap_or_else(|| "kitty".to_string());
+        format!("{} -e", program)
+    }
+}
+
+/// macOS の端末アプリとの連携の設定
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MacosConfig {
+    /// `n` で新しいウィンドウを開く端末アプリ
+    pub terminal: MacTerminal,
+}
+
+/// 新しいウィンドウを開く macOS の端末アプリ
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MacTerminal {
+    /// iTerm2 の中で実行していれば iTerm2、それ以外は Terminal.app
+    #[default]
+    Auto,
+    /// Terminal.app
+    Terminal,
+    /// iTerm2
+    Iterm,
+}
+
+impl MacTerminal {
+    /// `auto` を `TERM_PROGRAM` から決める
+    pub fn resolve(self) -> MacTerminal {
+        match self {
+            MacTerminal::Auto if in_iterm() => MacTerminal::Iterm,
+            MacTerminal::Auto => MacTerminal::Terminal,
+            other => other,
+        }
+    }
+}
+
+/// プロジェクトのワークスペースの用意のしかた
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceMode {
+    /// 今のワークスペースの名前をプロジェクト名に変える
+    Rename,
+    /// 端末のウィンドウをプロジェクト名のワークスペース（無ければ作られる）に移して、そこに切り替える
+    Move,
+}
+
+/// プロジェクトを選ぶ UI
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Picker {
+    /// 組み込みの TUI
+    #[default]
+    Tui,
+    /// fzf に候補を渡して選ぶ
+    Fzf,
+    /// rofi のメニューで選ぶ
+    Rofi,
+    /// dmenu 互換のメニュー（`tools.dmenu`）で選ぶ
+    Dmenu,
+}
+
+impl Picker {
+    /// `tui` / `fzf` / `rofi` / `dmenu` を解釈する
+    pub fn parse(s: &str) -> Option<Picker> {
+        match s {
+            "tui" => Some(Picker::Tui),
+            "fzf" => Some(Picker::Fzf),
+            "rofi" => Some(Picker::Rofi),
+            "dmenu" => Some(Picker::Dmenu),
+            _ => None,
+        }
+    }
+
+    /// 端末を使わないメニューか（ウィンドウマネージャーのキーから起動できる）
+    pub fn is_graphical(self) -> bool {
+        matches!(self, Picker::Rofi | Picker::Dmenu)
+    }
+}
+
+/// プロジェクトを開く端末の仕組み
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Windows なら Windows Terminal、それ以外は tmux
+    #[default]
+    Auto,
+    /// tmux のセッション
+    Tmux,
+    /// Windows Terminal のタブ
+    #[serde(rename = "wt")]
+    WindowsTerminal,
+    /// iTerm2 のタブ
+    Iterm,
+    /// Ghostty のウィンドウ
+    Ghostty,
+    /// プロジェクトのディレクトリでシェルを起動するだけ
+    Shell,
+}
+
+impl Backend {
+    /// `auto` / `tmux` / `wt` / `iterm` / `ghostty` / `shell` を解釈する
+    pub fn parse(s: &str) -> Option<Backend> {
+        match s {
+            "auto" => Some(Backend::Auto),
+            "tmux" => Some(Backend::Tmux),
+            "wt" => Some(Backend::WindowsTerminal),
+            "iterm" => Some(Backend::Iterm),
+            "ghostty" => Some(Backend::Ghostty),
+            "shell" => Some(Backend::Shell),
+            _ => None,
+        }
+    }
+
+    /// 設定ファイルに書く名前
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Backend::Auto => "auto",
+            Backend::Tmux => "tmux",
+            Backend::WindowsTerminal => "wt",
+            Backend::Iterm => "iterm",
+            Backend::Ghostty => "ghostty",
+            Backend::Shell => "shell",
+        }
+    }
+
+    /// `auto` を実行中のプラットフォームに合わせて決める。
+    /// tmux や Windows Terminal が見つからなければシェルにする（iTerm2 や Ghostty の中ならその端末）
+    pub fn resolve(self) -> Backend {
+        let backend = match self {
+            Backend::Auto if cfg!(windows) => Backend::WindowsTerminal,
+            Backend::Auto if !shell::command_exists("tmux") && in_iterm() => Backend::Iterm,
+            Backend::Auto if !shell::command_exists("tmux") && ghostty::detect() => {
+                Backend::Ghostty
+            }
+            Backend::Auto => Backend::Tmux,
+            backend => backend,
+        };
+        let program = match backend {
+            Backend::Tmux => "tmux",
+            Backend::WindowsTerminal => "wt",
+            Backend::Iterm => "osascript",
+            Backend::Ghostty => ghostty::program(),
+            _ => return backend,
+        };
+        if shell::command_exists(program) {
+            backend
+        } else {
+            Backend::Shell
+        }
+    }
+}
+
+/// iTerm2 の中で実行しているか
+fn in_iterm() -> bool {
+    std::env::var("TERM_PROGRAM").is_ok_and(|program| program == "iTerm.app")
+}
+
+/// tmux などが無いときに起動するシェルの設定。未設定ならプラットフォームの標準のシェル
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FallbackConfig {
+    /// Linux / macOS などで起動するシェル（既定は `$SHELL`）
+    pub unix: Option<String>,
+    /// Windows で起動するシェル（既定は pwsh、無ければ cmd）
+    pub windows: Option<String>,
+}
+
+impl FallbackConfig {
+    /// 実行中のプラットフォームで起動するシェル
+    pub fn shell(&self) -> String {
+        let configured = if cfg!(windows) {
+            &self.windows
+        } else {
+            &self.unix
+        };
+        configured.clone().unwrap_or_else(fallback::default_shell)
+    }
+}
+
+/// シンボリックリンクのディレクトリの扱い
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// 普通のディレクトリと同じように並べる
+    #[default]
+    Follow,
+    /// 一覧に出さない
+    Skip,
+    /// 並べて `➜` の印を付ける
+    Mark,
+}
+
+/// 一覧の並び順
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// 名前順
+    #[default]
+    Name,
+    /// 更新日時の新しい順
+    Modified,
+}
+
+/// ルートや途中のディレクトリに置く `.projector/config.toml`。そのディレクトリ以下すべてに適用する
+///
+/// 書かなかった項目は上の階層（無ければ設定ファイル）のものを使う。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LocalConfig {
+    /// 一覧に出さないディレクトリ名のパターン。上の階層のものを置き換える
+    pub ignore: Option<Vec<String>>,
+    /// 一覧の並び順
+    pub sort: Option<SortOrder>,
+    /// tmux セッションのペイン構成
+    pub layout: Option<LayoutConfig>,
+}
+
+/// `dir` に適用される `.projector/config.toml` を上の階層から順に返す
+///
+/// `dir` を含むルートまで遡る。どのルートにも含まれなければ `dir` 自身のものだけを読む。
+/// 読めない・形式が不正なファイルは警告して無視する。
+pub fn local_configs(dir: &Path, roots: &[PathBuf]) -> Vec<LocalConfig> {
+    let top = roots
+        .iter()
+        .filter(|root| dir.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .map_or(dir, PathBuf::as_path);
+
+    let mut dirs: Vec<&Path> = dir.ancestors().take_while(|d| d.starts_with(top)).collect();
+    dirs.reverse();
+    dirs.into_iter()
+        .filter_map(|d| {
+            let path = d.join(".projector").join("config.toml");
+            let content = fs::read_to_string(&path).ok()?;
+            match toml::from_str(&content) {
+                Ok(local) => {
+                    debug!(path = %path.display(), "loading directory config");
+                    Some(local)
+                }
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "invalid directory config");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// 設定ファイルを読み込む。ファイルが無ければデフォルト設定を返す
+pub fn load() -> Result<Config> {
+    let Some(path) = paths::config_file() else {
+        return Ok(Config::default());
+    };
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(source) => return Err(Error::ConfigRead { path, source }),
+    };
+    debug!(path = %path.display(), "loading config");
+    let parse_error = |source| Error::ConfigParse {
+        path: path.clone(),
+        source,
+    };
+    let mut table: toml::Table = toml::from_str(&content).map_err(parse_error)?;
+    match migrate::config(&mut table) {
+        Outcome::Migrated => table.try_into().map_err(parse_error),
+        // 誤りの位置を示せるよう、移さなかったものはファイルの内容から読む
+        Outcome::Current | Outcome::Newer(_) => toml::from_str(&content).map_err(parse_error),
+    }
+}
+
+/// ルートと端末の仕組みだけを書いた最初の設定ファイルを作る
+pub fn write_initial(path: &Path, roots: &[String], backend: Backend) -> Result<()> {
+    let mut table = toml::Table::new();
+    table.insert(
+        "version".to_string(),
+        toml::Value::Integer(i64::from(migrate::CONFIG_VERSION)),
+    );
+    table.insert(
+        "roots".to_string(),
+        toml::Value::Array(roots.iter().cloned().map(toml::Value::String).collect()),
+    );
+    table.insert(
+        "backend".to_string(),
+        toml::Value::String(backend.as_str().to_string()),
+    );
+    let content = toml::to_string(&table).expect("a table of strings always serializes");
+
+    let write = || {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, content)
+    };
+    write().map_err(|source| Error::ConfigWrite {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    debug!(path = %path.display(), "wrote initial config");
+    Ok(())
+}
@@ -0,0 +1,343 @@
+//! 常駐してプロジェクトの一覧を温めておくデーモン
+//!
+//! `projector daemon` はルートの一覧をメモリに持ち、Unix ソケットで1行の JSON の問い合わせに
+//! 1行の JSON で答える。CLI や TUI はまずデーモンに問い合わせ、動いていなければ自分で走査する。
+//! 走査のしかた（隠しディレクトリ・並び順など）は問い合わせ側が送り、それごとに一覧を持つ。
+//! 一覧は一定の間隔で作り直すので、デーモンの答えは少し古いことがある。
+//! 作り直すたびに、tmux のセッションで作業した時間の記録（[`crate::timelog`]）も延ばす。
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::index;
+use crate::location::{Entry, Location};
+use crate::paths;
+use crate::runner::SystemRunner;
+use crate::scan::ScanOptions;
+use crate::timelog;
+use crate::{Error, Result};
+
+/// 一覧を作り直す間隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 応答を待つ時間。これより遅ければ自分で走査したほうが早い
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// デーモンへの問い合わせ
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum Request {
+    /// ルート直下のプロジェクト（[`index::projects`]）
+    Projects {
+        /// ルート（[`Location::spec`] の形式）
+        roots: Vec<String>,
+        /// 走査のしかた
+        options: ScanOptions,
+    },
+    /// 全ルートの下のプロジェクト（[`index::walk`]）
+    Walk {
+        /// ルート（[`Location::spec`] の形式）
+        roots: Vec<String>,
+        /// 走査のしかた
+        options: ScanOptions,
+        /// 辿る深さ
+        depth: usize,
+    },
+    /// 持っている一覧をすべて作り直す
+    Refresh,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Response {
+    entries: Vec<WireEntry>,
+}
+
+/// ソケットでやり取りする [`Entry`]
+#[derive(Debug, Serialize, Deserialize)]
+struct WireEntry {
+    name: String,
+    location: String,
+    alias: bool,
+    symlink: bool,
+}
+
+impl From<&Entry> for WireEntry {
+    fn from(entry: &Entry) -> Self {
+        WireEntry {
+            name: entry.name.clone(),
+            location: entry.location.spec(),
+            alias: entry.alias,
+            symlink: entry.symlink,
+        }
+    }
+}
+
+impl From<WireEntry> for Entry {
+    fn from(entry: WireEntry) -> Self {
+        Entry {
+            name: entry.name,
+            location: Location::parse(&entry.location),
+            alias: entry.alias,
+            symlink: entry.symlink,
+        }
+    }
+}
+
+/// デーモンが動いていればルート直下のプロジェクトを問い合わせる
+pub fn projects(roots: &[Location], options: &ScanOptions) -> Option<Vec<Entry>> {
+    query(&Request::Projects {
+        roots: roots.iter().map(Location::spec).collect(),
+        options: options.clone(),
+    })
+}
+
+/// デーモンが動いていれば全ルートの下のプロジェクトを問い合わせる
+pub fn walk(roots: &[Location], options: &ScanOptions, depth: usize) -> Option<Vec<Entry>> {
+    query(&Request::Walk {
+        roots: roots.iter().map(Location::spec).collect(),
+        options: options.clone(),
+        depth,
+    })
+}
+
+/// デーモンに問い合わせる。動いていないか答えられなければ None
+pub fn query(request: &Request) -> Option<Vec<Entry>> {
+    let path = paths::socket_file().filter(|path| path.exists())?;
+    match ask(&path, request) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+            debug!(socket = %path.display(), error = %e, "daemon did not answer");
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn ask(path: &Path, request: &Request) -> io::Result<Vec<Entry>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: Response = serde_json::from_str(&line)?;
+    Ok(response.entries.into_iter().map(Entry::from).collect())
+}
+
+#[cfg(not(unix))]
+fn ask(_path: &Path, _request: &Request) -> io::Result<Vec<Entry>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// 問い合わせ（ルート・走査のしかた・深さ）ごとに作った一覧
+struct Daemon {
+    cache: Mutex<HashMap<Request, Vec<Entry>>>,
+}
+
+impl Daemon {
+    fn answer(&self, request: Request) -> Vec<Entry> {
+        if request == Request::Refresh {
+            self.refresh();
+            return vec![];
+        }
+        if let Some(entries) = lock(&self.cache).get(&request) {
+            return entries.clone();
+        }
+        let entries = self.build(&request);
+        lock(&self.cache).insert(request, entries.clone());
+        entries
+    }
+
+    fn build(&self, request: &Request) -> Vec<Entry> {
+        match request {
+            Request::Projects { roots, options } => {
+                index::projects(&SystemRunner, &parse(roots), options)
+            }
+            Request::Walk {
+                roots,
+                options,
+                depth,
+            } => index::walk(&parse(roots), options, *depth),
+            Request::Refresh => vec![],
+        }
+    }
+
+    /// これまでに問い合わせのあった一覧を作り直す
+    fn refresh(&self) {
+        let requests: Vec<Request> = lock(&self.cache).keys().cloned().collect();
+        for request in requests {
+            let entries = self.build(&request);
+            lock(&self.cache).insert(request, entries);
+        }
+        debug!("refreshed the daemon cache");
+    }
+}
+
+fn parse(roots: &[String]) -> Vec<Location> {
+    roots.iter().map(|root| Location::parse(root)).collect()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // 一覧を作る途中で panic しても、残っている一覧はそのまま使える
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// デーモンとして問い合わせに答え続ける。`roots` の一覧は `options` で起動時に作っておく
+///
+/// ソケットで待ち受け始めたら、そのパスを `listening` に渡す。
+#[cfg(unix)]
+pub fn serve(
+    roots: &[Location],
+    options: ScanOptions,
+    depth: usize,
+    listening: impl FnOnce(&Path),
+) -> Result<()> {
+    use std::fs;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
+    use std::thread;
+
+    let path = paths::socket_file().ok_or(Error::NoHome)?;
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(Error::DaemonRunning(path));
+        }
+        // 前回のデーモンが残したソケット
+        fs::remove_file(&path)?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    info!(socket = %path.display(), "daemon listening");
+    listening(&path);
+
+    let daemon = Arc::new(Daemon {
+        cache: Mutex::new(HashMap::new()),
+    });
+    let specs: Vec<String> = roots.iter().map(Location::spec).collect();
+    daemon.answer(Request::Projects {
+        roots: specs.clone(),
+        options: options.clone(),
+    });
+    daemon.answer(Request::Walk {
+        roots: specs,
+        options,
+        depth,
+    });
+
+    {
+        let daemon = Arc::clone(&daemon);
+        thread::spawn(move || loop {
+            thread::sleep(REFRESH_INTERVAL);
+            daemon.refresh();
+            timelog::poll(&SystemRunner);
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(error = %e, "failed to accept a connection");
+                continue;
+            }
+        };
+        let daemon = Arc::clone(&daemon);
+        thread::spawn(move || {
+            if let Err(e) = handle(&daemon, stream) {
+                debug!(error = %e, "failed to answer a request");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// 1つの接続で1つの問い合わせに答える
+#[cfg(unix)]
+fn handle(daemon: &Daemon, mut stream: std::os::unix::net::UnixStream) -> io::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line)?;
+    let entries = daemon.answer(request);
+    let response = Response {
+        entries: entries.iter().map(WireEntry::from).collect(),
+    };
+    serde_json::to_writer(&mut stream, &response)?;
+    stream.write_all(b"\n")
+}
+
+/// Unix ソケットが無いのでデーモンは使えない
+#[cfg(not(unix))]
+pub fn serve(
+    _roots: &[Location],
+    _options: ScanOptions,
+    _depth: usize,
+    _listening: impl FnOnce(&Path),
+) -> Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::config::SortOrder;
+
+    #[test]
+    fn keeps_a_listing_per_scan_options() {
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(root.path().join("app/.git")).unwrap();
+        fs::create_dir_all(root.path().join(".dotfiles/.git")).unwrap();
+        let daemon = Daemon {
+            cache: Mutex::new(HashMap::new()),
+        };
+        let walk = |hidden| {
+            let request = Request::Walk {
+                roots: vec![Location::Local(root.path().to_path_buf()).spec()],
+                options: ScanOptions {
+                    hidden,
+                    markers: vec![".git".to_string()],
+                    ..ScanOptions::default()
+                },
+                depth: 1,
+            };
+            let entries = daemon.answer(request);
+            entries.into_iter().map(|e| e.name).collect::<Vec<_>>()
+        };
+        assert_eq!(walk(false), ["app"]);
+        assert_eq!(walk(true), [".dotfiles", "app"]);
+        assert_eq!(lock(&daemon.cache).len(), 2);
+    }
+
+    #[test]
+    fn sends_the_scan_options_with_the_request() {
+        let request = Request::Projects {
+            roots: vec!["ssh://devbox/~/src".to_string()],
+            options: ScanOptions {
+                hidden: true,
+                sort: SortOrder::Modified,
+                ..ScanOptions::default()
+            },
+        };
+        let line = serde_json::to_string(&request).unwrap();
+        assert_eq!(serde_json::from_str::<Request>(&line).unwrap(), request);
+    }
+}
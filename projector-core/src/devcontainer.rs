@@ -0,0 +1,129 @@
+//! devcontainer の検出と起動
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::shell;
+use crate::{Error, Result};
+
+/// 起動したコンテナ
+pub struct Container {
+    /// コンテナ ID
+    pub id: String,
+    /// コンテナ内のワークスペースのパス
+    pub workspace: String,
+    /// コンテナ内で使うユーザー
+    pub user: Option<String>,
+}
+
+impl Container {
+    /// コンテナ内でシェルを開くコマンド（bash が無いイメージでは sh を使う）
+    pub fn shell_command(&self) -> String {
+        let mut args = vec!["docker", "exec", "-it", "-w", &self.workspace];
+        if let Some(user) = &self.user {
+            args.extend(["-u", user]);
+        }
+        args.extend([
+            &self.id,
+            "sh",
+            "-c",
+            "command -v bash >/dev/null && exec bash || exec sh",
+        ]);
+        args.iter()
+            .map(|a| shell::quote(a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// devcontainer の設定ファイルを探す
+pub fn config_path(project: &Path) -> Option<PathBuf> {
+    [".devcontainer/devcontainer.json", ".devcontainer.json"]
+        .iter()
+        .map(|name| project.join(name))
+        .find(|path| path.is_file())
+}
+
+/// コンテナを起動する。devcontainer CLI があればそれを使い、無ければ既存のコンテナを docker で探す
+pub fn up(runner: &dyn CommandRunner, project: &Path) -> Result<Container> {
+    match devcontainer_up(runner, project) {
+        Some(result) => result,
+        None => docker_start(runner, project),
+    }
+}
+
+/// devcontainer CLI が無ければ None
+fn devcontainer_up(runner: &dyn CommandRunner, project: &Path) -> Option<Result<Container>> {
+    // ビルドのログは stderr に出るのでそのまま見せる
+    let cmd = Cmd::new("devcontainer")
+        .arg("up")
+        .arg("--workspace-folder")
+        .arg(project)
+        .inherit_stderr();
+    let output = runner.output(&cmd).ok()?;
+
+    let stdout = output.stdout_str();
+    let result: Option<Value> = stdout
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str(line).ok());
+    let Some(result) = result.filter(|r| r["outcome"] == "success") else {
+        return Some(Err(Error::CommandFailed("devcontainer up".to_string())));
+    };
+
+    let id = result["containerId"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let workspace = result["remoteWorkspaceFolder"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| default_workspace(project));
+    Some(Ok(Container {
+        id,
+        workspace,
+        user: result["remoteUser"].as_str().map(str::to_string),
+    }))
+}
+
+/// VS Code や devcontainer CLI が付けるラベルから既存のコンテナを探して起動する
+fn docker_start(runner: &dyn CommandRunner, project: &Path) -> Result<Container> {
+    let filter = format!("label=devcontainer.local_folder={}", project.display());
+    let output = runner
+        .output(
+            &Cmd::new("docker")
+                .args(["ps", "-a", "-q", "--filter", &filter])
+                .read_only(),
+        )
+        .map_err(|e| Error::spawn("docker", e))?;
+
+    let id = output
+        .stdout_str()
+        .lines()
+        .next()
+        .map(str::to_string)
+        .ok_or(Error::NoContainer)?;
+
+    let output = runner
+        .output(&Cmd::new("docker").args(["start", &id]))
+        .map_err(|e| Error::spawn("docker", e))?;
+    if !output.success {
+        return Err(Error::CommandFailed("docker start".to_string()));
+    }
+
+    Ok(Container {
+        id,
+        workspace: default_workspace(project),
+        user: None,
+    })
+}
+
+fn default_workspace(project: &Path) -> String {
+    let name = project
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("/workspaces/{}", name)
+}
@@ -0,0 +1,368 @@
+//! projector 全体で使うエラー型
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::i18n::{self, Lang};
+
+/// projector のエラー
+#[derive(Debug, Error)]
+pub enum Error {
+    /// 解釈できないコマンドライン引数
+    UnknownArgument(String),
+    /// 必要な引数が無い
+    MissingArgument(&'static str),
+    /// 指定した名前のプロジェクトが無い
+    NoMatch(String),
+    /// 指定した名前のプロジェクトが複数あって1つに決まらない
+    Ambiguous {
+        /// 指定した名前
+        name: String,
+        /// 当てはまったプロジェクトの表示名
+        matches: Vec<String>,
+    },
+    /// まだプロジェクトを開いたことが無い
+    NoRecentProject,
+    /// 端末が無いのに対話的な操作が必要
+    NotATerminal,
+    /// ホームディレクトリが分からない
+    NoHome,
+    /// 設定ファイルを読めない
+    ConfigRead {
+        /// 設定ファイルのパス
+        path: PathBuf,
+        /// 元のエラー
+        source: io::Error,
+    },
+    /// 設定ファイルの形式が不正
+    ConfigParse {
+        /// 設定ファイルのパス
+        path: PathBuf,
+        /// 元のエラー
+        source: toml::de::Error,
+    },
+    /// 設定ファイルを書き込めない
+    ConfigWrite {
+        /// 設定ファイルのパス
+        path: PathBuf,
+        /// 元のエラー
+        source: io::Error,
+    },
+    /// 存在するルートディレクトリが1つも無い
+    NoRoots(Vec<String>),
+    /// 外部コマンドを起動できない
+    Spawn {
+        /// コマンド名
+        program: String,
+        /// 元のエラー
+        source: io::Error,
+    },
+    /// 外部コマンドが失敗した
+    CommandFailed(String),
+    /// tmux のサブコマンドが失敗した
+    Tmux(String),
+    /// tmux が PATH に無い
+    TmuxMissing,
+    /// tmux が古くて、セッションの組み立てに要る機能が無い
+    TmuxTooOld {
+        /// 要る機能
+        feature: &'static str,
+        /// 機能が使えるようになったバージョン
+        required: String,
+        /// インストールされているバージョン
+        found: String,
+    },
+    /// ssh 先でセッションを起動できない
+    RemoteSession(String),
+    /// リモートのプロジェクトでは使えない操作
+    RemoteUnsupported(&'static str),
+    /// 起動できる devcontainer が無い
+    NoContainer,
+    /// デーモンが既に動いている
+    DaemonRunning(PathBuf),
+    /// 対応しているウィンドウマネージャーの中で実行していない
+    NoWindowManager,
+    /// ディレクトリを移せない
+    Move {
+        /// 移そうとしたディレクトリ
+        path: PathBuf,
+        /// 元のエラー
+        source: io::Error,
+    },
+    /// 別のファイルシステムへは移さない（コピーになるため）
+    CrossDevice {
+        /// 移そうとしたディレクトリ
+        path: PathBuf,
+        /// 移動先
+        to: PathBuf,
+    },
+    /// 移動先が既にある
+    AlreadyExists(PathBuf),
+    /// `projector state import` で読めない内容
+    InvalidBundle(String),
+    /// ディレクトリを作れない
+    CreateDir {
+        /// 作ろうとしたディレクトリ
+        path: PathBuf,
+        /// 元のエラー
+        source: io::Error,
+    },
+    /// HTTP リクエストの失敗
+    Http(String),
+    /// HTTP レスポンスを解釈できない
+    InvalidResponse,
+    /// 端末の入出力エラー
+    Terminal(#[from] io::Error),
+}
+
+/// projector の Result
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// プロセスの終了コード。シェルスクリプトから結果を判別できるよう固定している
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// その他のエラー
+    Failure = 1,
+    /// コマンドライン引数の誤り
+    Usage = 2,
+    /// プロジェクトを選ばずに終了した
+    Cancelled = 3,
+    /// 指定した名前のプロジェクトが無い
+    NoMatch = 4,
+    /// tmux の起動・アタッチに失敗した
+    Tmux = 5,
+    /// 設定ファイルやルートの誤り
+    Config = 6,
+}
+
+impl Error {
+    /// 外部コマンドの起動エラーを作る
+    pub fn spawn(program: impl Into<String>, source: io::Error) -> Self {
+        let program = program.into();
+        // どう直せばよいかが分かるよう、OS のエラーではなく専用のメッセージにする
+        if program == "tmux" && source.kind() == io::ErrorKind::NotFound {
+            return Error::TmuxMissing;
+        }
+        Error::Spawn { program, source }
+    }
+
+    /// ディレクトリの移動のエラーを作る
+    pub fn move_failed(path: &Path, source: io::Error) -> Self {
+        Error::Move {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// ディレクトリの作成のエラーを作る
+    pub fn create_dir_failed(path: &Path, source: io::Error) -> Self {
+        Error::CreateDir {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// このエラーで終了するときの終了コード
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Error::UnknownArgument(_) | Error::MissingArgument(_) | Error::Ambiguous { .. } => {
+                ExitCode::Usage
+            }
+            Error::NoMatch(_) | Error::NoRecentProject => ExitCode::NoMatch,
+            Error::ConfigRead { .. }
+            | Error::ConfigParse { .. }
+            | Error::ConfigWrite { .. }
+            | Error::NoRoots(_) => ExitCode::Config,
+            Error::Tmux(_)
+            | Error::TmuxMissing
+            | Error::TmuxTooOld { .. }
+            | Error::RemoteSession(_) => ExitCode::Tmux,
+            Error::Spawn { program, .. } if program == "tmux" || program == "wt.exe" => {
+                ExitCode::Tmux
+            }
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match i18n::lang() {
+            Lang::Ja => self.ja(f),
+            Lang::En => self.en(f),
+        }
+    }
+}
+
+impl Error {
+    fn ja(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownArgument(arg) => {
+                write!(f, "不明な引数です: {} (--help で使い方を表示)", arg)
+            }
+            Error::MissingArgument(name) => write!(f, "{} を指定してください", name),
+            Error::NoMatch(name) => write!(f, "プロジェクト '{}' が見つかりません", name),
+            Error::Ambiguous { name, matches } => write!(
+                f,
+                "'{}' に当てはまるプロジェクトが複数あります: {} (org/name の形で絞るか、--first か --all を指定してください)",
+                name,
+                matches.join(", ")
+            ),
+            Error::NoRecentProject => write!(f, "最近開いたプロジェクトがありません"),
+            Error::NotATerminal => write!(
+                f,
+                "tmux にアタッチするには端末が必要です (--dry-run で実行するコマンドを表示できます)"
+            ),
+            Error::NoHome => write!(f, "ホームディレクトリを取得できませんでした"),
+            Error::ConfigRead { path, source } => {
+                write!(f, "{} を読み込めませんでした: {}", path.display(), source)
+            }
+            Error::ConfigParse { path, source } => {
+                write!(f, "{} の形式が不正です: {}", path.display(), source)
+            }
+            Error::ConfigWrite { path, source } => {
+                write!(f, "{} に書き込めませんでした: {}", path.display(), source)
+            }
+            Error::NoRoots(roots) => write!(f, "{} ディレクトリが存在しません", roots.join(", ")),
+            Error::Spawn { program, source } => {
+                write!(f, "{} を実行できませんでした: {}", program, source)
+            }
+            Error::CommandFailed(command) => write!(f, "{} に失敗しました", command),
+            Error::Tmux(command) => write!(f, "tmux {} に失敗しました", command),
+            Error::TmuxMissing => write!(
+                f,
+                "tmux が見つかりません。インストールするか、設定の backend を shell などに変えてください"
+            ),
+            Error::TmuxTooOld {
+                feature,
+                required,
+                found,
+            } => write!(
+                f,
+                "{} には tmux {} 以降が必要です (インストールされているのは {})",
+                feature, required, found
+            ),
+            Error::RemoteSession(host) => {
+                write!(f, "{} でのリモートセッションの起動に失敗しました", host)
+            }
+            Error::RemoteUnsupported(feature) => {
+                write!(f, "リモートの {} には対応していません", feature)
+            }
+            Error::NoContainer => write!(
+                f,
+                "devcontainer CLI が見つからず、既存のコンテナもありません"
+            ),
+            Error::DaemonRunning(socket) => write!(
+                f,
+                "projector daemon は既に動いています ({})",
+                socket.display()
+            ),
+            Error::NoWindowManager => {
+                write!(f, "Hyprland / sway / i3 の中で実行してください")
+            }
+            Error::Move { path, source } => {
+                write!(f, "{} を移動できませんでした: {}", path.display(), source)
+            }
+            Error::CrossDevice { path, to } => write!(
+                f,
+                "{} と {} は別のファイルシステムにあるため移動できません (コピーはしません)",
+                path.display(),
+                to.display()
+            ),
+            Error::AlreadyExists(path) => write!(f, "{} は既にあります", path.display()),
+            Error::InvalidBundle(reason) => {
+                write!(f, "取り込めない状態のファイルです: {}", reason)
+            }
+            Error::CreateDir { path, source } => {
+                write!(f, "{} を作成できませんでした: {}", path.display(), source)
+            }
+            Error::Http(message) => write!(f, "HTTP リクエストに失敗しました: {}", message),
+            Error::InvalidResponse => write!(f, "不正な HTTP レスポンス"),
+            Error::Terminal(e) => write!(f, "{}", e),
+        }
+    }
+
+    fn en(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownArgument(arg) => {
+                write!(f, "unknown argument: {} (see --help)", arg)
+            }
+            Error::MissingArgument(name) => write!(f, "missing argument: {}", name),
+            Error::NoMatch(name) => write!(f, "no project named '{}'", name),
+            Error::Ambiguous { name, matches } => write!(
+                f,
+                "'{}' matches several projects: {} (narrow it down as org/name, or pass --first or --all)",
+                name,
+                matches.join(", ")
+            ),
+            Error::NoRecentProject => write!(f, "no project has been opened yet"),
+            Error::NotATerminal => write!(
+                f,
+                "attaching to tmux requires a terminal (use --dry-run to print the commands instead)"
+            ),
+            Error::NoHome => write!(f, "could not determine the home directory"),
+            Error::ConfigRead { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            Error::ConfigParse { path, source } => {
+                write!(f, "invalid format in {}: {}", path.display(), source)
+            }
+            Error::ConfigWrite { path, source } => {
+                write!(f, "could not write {}: {}", path.display(), source)
+            }
+            Error::NoRoots(roots) => write!(f, "directory does not exist: {}", roots.join(", ")),
+            Error::Spawn { program, source } => {
+                write!(f, "could not run {}: {}", program, source)
+            }
+            Error::CommandFailed(command) => write!(f, "{} failed", command),
+            Error::Tmux(command) => write!(f, "tmux {} failed", command),
+            Error::TmuxMissing => write!(
+                f,
+                "tmux not found; install it or set backend to shell (or another terminal) in the config"
+            ),
+            Error::TmuxTooOld {
+                feature,
+                required,
+                found,
+            } => write!(
+                f,
+                "{} requires tmux {} or later (installed: {})",
+                feature, required, found
+            ),
+            Error::RemoteSession(host) => write!(f, "failed to start a remote session on {}", host),
+            Error::RemoteUnsupported(feature) => {
+                write!(f, "{} is not supported for remote projects", feature)
+            }
+            Error::NoContainer => write!(
+                f,
+                "devcontainer CLI not found and no existing container"
+            ),
+            Error::DaemonRunning(socket) => {
+                write!(f, "projector daemon is already running ({})", socket.display())
+            }
+            Error::NoWindowManager => {
+                write!(f, "requires a running Hyprland, sway or i3 session")
+            }
+            Error::Move { path, source } => {
+                write!(f, "could not move {}: {}", path.display(), source)
+            }
+            Error::CrossDevice { path, to } => write!(
+                f,
+                "cannot move {} to {}: they are on different filesystems (projects are never copied)",
+                path.display(),
+                to.display()
+            ),
+            Error::AlreadyExists(path) => write!(f, "{} already exists", path.display()),
+            Error::InvalidBundle(reason) => write!(f, "cannot import this state file: {}", reason),
+            Error::CreateDir { path, source } => {
+                write!(f, "could not create {}: {}", path.display(), source)
+            }
+            Error::Http(message) => write!(f, "HTTP request failed: {}", message),
+            Error::InvalidResponse => write!(f, "invalid HTTP response"),
+            Error::Terminal(e) => write!(f, "{}", e),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! tmux などが無い環境でプロジェクトのディレクトリにシェルを開く
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::session::SessionSpec;
+use crate::shell;
+use crate::{Error, Result};
+
+/// プラットフォームの標準のシェル。Windows は pwsh があればそれ、無ければ cmd
+pub fn default_shell() -> String {
+    if cfg!(windows) {
+        if shell::command_exists("pwsh") {
+            "pwsh".to_string()
+        } else {
+            "cmd".to_string()
+        }
+    } else {
+        std::env::var("SHELL")
+            .ok()
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| "/bin/sh".to_string())
+    }
+}
+
+/// セッションの代わりにシェルを起動し、終了するまで待つ。
+/// 最初のペインにコマンドがあれば（Nix の開発環境など）それを実行する
+pub fn open(runner: &dyn CommandRunner, shell: &str, spec: &SessionSpec) -> Result<()> {
+    let cmd = match spec.panes.first().cloned().flatten() {
+        Some(command) if cfg!(windows) => Cmd::new("cmd").args(["/C", &command]),
+        Some(command) => Cmd::new("sh").args(["-c", &command]),
+        None => {
+            // `pwsh -NoLogo` のような引数付きの指定も受け付ける
+            let mut words = shell.split_whitespace();
+            Cmd::new(words.next().unwrap_or("sh")).args(words)
+        }
+    }
+    .current_dir(&spec.dir);
+
+    // シェルの終了コードは最後に実行したコマンドのものなので失敗扱いにしない
+    runner
+        .interactive(&cmd)
+        .map_err(|e| Error::spawn(cmd.program.to_string_lossy(), e))?;
+    Ok(())
+}
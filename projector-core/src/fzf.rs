@@ -0,0 +1,31 @@
+//! fzf を使ったプロジェクトの選択
+
+use crate::location::Entry;
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+// 何も選ばずに終了したとき（Esc / Ctrl-C）と、一致する候補が無かったとき
+const EXIT_NO_MATCH: i32 = 1;
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// 候補を fzf に渡し、選ばれたエントリの位置を返す。キャンセルされたら None
+pub fn pick(runner: &dyn CommandRunner, entries: &[Entry]) -> Result<Option<usize>> {
+    let candidates: Vec<String> = entries.iter().map(|e| e.location.display()).collect();
+    // fzf は画面を /dev/tty に描くので、候補と結果だけをパイプでやり取りする
+    let cmd = Cmd::new("fzf")
+        .args(["--prompt", "projector> ", "--no-multi"])
+        .stdin(candidates.join("\n"))
+        .inherit_stderr()
+        .read_only();
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("fzf", e))?;
+
+    if !output.success {
+        return match output.code {
+            Some(EXIT_NO_MATCH | EXIT_INTERRUPTED) => Ok(None),
+            _ => Err(Error::CommandFailed("fzf".to_string())),
+        };
+    }
+    let selected = output.stdout_str();
+    let selected = selected.trim_end_matches('\n');
+    Ok(candidates.iter().position(|c| c == selected))
+}
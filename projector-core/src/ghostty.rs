@@ -0,0 +1,78 @@
+//! Ghostty でプロジェクトを開く
+//!
+//! tmux を使わない Ghostty では、セッションの代わりにレイアウトのペインを Ghostty の
+//! ウィンドウとして開く。Ghostty の CLI ではタブや分割を外から作れないので、ペインごとに
+//! ウィンドウを1つずつ起動する（起動中の Ghostty があればそのウィンドウとして開く）。
+//! macOS では `open -na Ghostty.app` で起動する。リモートのプロジェクトはウィンドウの中で
+//! `ssh -t` してリモートの tmux にアタッチする。
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::session::SessionSpec;
+use crate::shell;
+use crate::{Error, Result};
+
+/// Ghostty の中で実行しているか
+pub fn detect() -> bool {
+    std::env::var("TERM_PROGRAM").is_ok_and(|program| program == "ghostty")
+}
+
+/// Ghostty を起動するコマンド。この名前が PATH に無ければ使えない
+pub fn program() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "ghostty"
+    }
+}
+
+/// ペインごとに Ghostty のウィンドウを開く呼び出しを組み立てる
+pub fn open_commands(host: Option<&str>, spec: &SessionSpec) -> Vec<Cmd> {
+    let dir = format!("--working-directory={}", spec.dir.to_string_lossy());
+    let title = format!("--title={}", spec.name);
+    let commands: Vec<Option<Vec<String>>> = match host {
+        Some(host) => vec![Some(vec![
+            "ssh".to_string(),
+            "-t".to_string(),
+            host.to_string(),
+            format!("sh -c {}", shell::quote(&spec.remote_script())),
+        ])],
+        None => spec
+            .panes
+            .iter()
+            .map(|command| {
+                command
+                    .as_ref()
+                    .map(|command| vec!["sh".to_string(), "-c".to_string(), command.clone()])
+            })
+            .collect(),
+    };
+
+    commands
+        .into_iter()
+        .map(|command| {
+            let cmd = if cfg!(target_os = "macos") {
+                Cmd::new("open").args(["-na", "Ghostty.app", "--args"])
+            } else {
+                Cmd::new("ghostty")
+            };
+            // リモートの作業ディレクトリはリモートのスクリプトで移る
+            let cmd = match host {
+                Some(_) => cmd.arg(&title),
+                None => cmd.args([&dir, &title]),
+            };
+            // `-e` より後ろはすべて実行するコマンドになる
+            match command {
+                Some(command) => cmd.arg("-e").args(command),
+                None => cmd,
+            }
+        })
+        .collect()
+}
+
+/// Ghostty のウィンドウでプロジェクトを開く
+pub fn open(runner: &dyn CommandRunner, host: Option<&str>, spec: &SessionSpec) -> Result<()> {
+    for cmd in open_commands(host, spec) {
+        runner.spawn(&cmd).map_err(|e| Error::spawn(program(), e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+//! git リポジトリの情報
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::profile;
+use crate::runner::{Cmd, CommandRunner};
+
+/// origin リモートの URL を返す。git リポジトリでなければ None
+pub fn remote_url(runner: &dyn CommandRunner, path: &Path) -> Option<String> {
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["remote", "get-url", "origin"])
+        .read_only();
+    let output = profile::time("git", || runner.output(&cmd)).ok()?;
+
+    if !output.success {
+        return None;
+    }
+    let url = output.stdout_str().trim().to_string();
+    (!url.is_empty()).then_some(url)
+}
+
+/// 現在のブランチ名。git リポジトリでなければ None（detached HEAD なら `HEAD`）
+pub fn current_branch(runner: &dyn CommandRunner, path: &Path) -> Option<String> {
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .read_only();
+    let output = profile::time("git", || runner.output(&cmd)).ok()?;
+
+    if !output.success {
+        return None;
+    }
+    let branch = output.stdout_str().trim().to_string();
+    (!branch.is_empty()).then_some(branch)
+}
+
+/// 現在のブランチが upstream より進んでいるコミット数と遅れているコミット数。
+/// upstream が無い・git リポジトリでなければ None
+///
+/// 手元にある upstream の参照と比べるだけなので、最後に fetch してからのリモートの変化は数えない。
+pub fn ahead_behind(runner: &dyn CommandRunner, path: &Path) -> Option<(usize, usize)> {
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])
+        .read_only();
+    let output = profile::time("git", || runner.output(&cmd)).ok()?;
+
+    if !output.success {
+        return None;
+    }
+    let stdout = output.stdout_str();
+    let (ahead, behind) = stdout.trim().split_once('\t')?;
+    Some((ahead.parse().ok()?, behind.parse().ok()?))
+}
+
+/// 最後に fetch してから `interval` 以上経っていれば、リモートの参照を黙って取得する。
+/// fetch したら true
+///
+/// 最後に fetch した時刻は `FETCH_HEAD` の更新時刻で見るので、手で fetch した分や他の projector の
+/// 分も数える。認証を尋ねられると止まってしまうので、git にも ssh にも入力させない。
+pub fn fetch(runner: &dyn CommandRunner, path: &Path, interval: Duration) -> bool {
+    if runner.dry_run() {
+        return false;
+    }
+    let Some(git_dir) = git_dir(path) else {
+        return false;
+    };
+    let fetched = fs::metadata(git_dir.join("FETCH_HEAD")).and_then(|meta| meta.modified());
+    if let Ok(fetched) = fetched {
+        let elapsed = SystemTime::now()
+            .duration_since(fetched)
+            .unwrap_or_default();
+        if elapsed < interval {
+            return false;
+        }
+    }
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["fetch", "--quiet"]);
+    let cmd = without_prompts(cmd);
+    profile::time("git", || runner.output(&cmd)).is_ok_and(|output| output.success)
+}
+
+/// 認証やホスト鍵の確認で止まらないよう、git と ssh のパスワード入力をすべて止める。
+/// 空の `GIT_ASKPASS` は `core.askPass` や `SSH_ASKPASS` へのフォールバックも止める
+pub(crate) fn without_prompts(cmd: Cmd) -> Cmd {
+    cmd.env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .env("SSH_ASKPASS", "")
+        .env(
+            "GIT_SSH_COMMAND",
+            "ssh -o BatchMode=yes -o ConnectTimeout=5",
+        )
+}
+
+/// HEAD のコミットの UNIX 時刻。コミットが無い・git リポジトリでなければ None
+pub fn last_commit_time(runner: &dyn CommandRunner, path: &Path) -> Option<u64> {
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["log", "-1", "--format=%ct"])
+        .read_only();
+    let output = profile::time("git", || runner.output(&cmd)).ok()?;
+
+    if !output.success {
+        return None;
+    }
+    output.stdout_str().trim().parse().ok()
+}
+
+/// コミットしていない変更（追跡していないファイルを含む）があるか。git リポジトリでなければ None
+pub fn is_dirty(runner: &dyn CommandRunner, path: &Path) -> Option<bool> {
+    let cmd = Cmd::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["status", "--porcelain"])
+        .read_only();
+    let output = profile::time("git", || runner.output(&cmd)).ok()?;
+
+    if !output.success {
+        return None;
+    }
+    Some(!output.stdout.is_empty())
+}
+
+/// スタッシュの数。git を起動せず、stash の reflog の行数を数える（git リポジトリでなければ 0）
+pub fn stash_count(path: &Path) -> usize {
+    let Some(git_dir) = git_dir(path) else {
+        return 0;
+    };
+    // worktree のスタッシュは本体のリポジトリにある
+    let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir,
+    };
+    fs::read_to_string(common_dir.join("logs/refs/stash"))
+        .map(|log| log.lines().filter(|line| !line.is_empty()).count())
+        .unwrap_or(0)
+}
+
+/// `.git` ディレクトリ。worktree やサブモジュールの `.git` ファイルは `gitdir:` の先を辿る
+fn git_dir(path: &Path) -> Option<PathBuf> {
+    let dot_git = path.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let target = content.strip_prefix("gitdir:")?.trim();
+    Some(path.join(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::OsString;
+
+    use tempfile::TempDir;
+
+    use crate::runner::MockRunner;
+
+    #[test]
+    fn fetches_without_prompting() {
+        let repo = TempDir::new().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        let runner = MockRunner::new();
+        assert!(fetch(&runner, repo.path(), Duration::ZERO));
+
+        let calls = runner.calls();
+        let env = |key: &str| {
+            calls[0]
+                .env
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(env("GIT_TERMINAL_PROMPT"), Some(OsString::from("0")));
+        assert_eq!(env("GIT_ASKPASS"), Some(OsString::new()));
+        assert_eq!(env("SSH_ASKPASS"), Some(OsString::new()));
+        let ssh = env("GIT_SSH_COMMAND").unwrap();
+        assert!(ssh.to_string_lossy().contains("BatchMode=yes"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Gitea 互換の API との連携（Codeberg、セルフホストの Gitea / Forgejo）
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::config::ForgeHost;
+use crate::forge::{self, Api, CiStatus, Forge, OpenCounts, TokenSource};
+use crate::runner::CommandRunner;
+
+/// `host` の Gitea のリモート URL から `owner/repo` を取り出す
+pub fn parse_remote(url: &str, host: &str) -> Option<String> {
+    forge::remote_path(url, host).filter(|path| path.matches('/').count() == 1)
+}
+
+/// Gitea REST API クライアント。トークンが無くても公開リポジトリは参照できる
+pub struct GiteaClient {
+    host: String,
+    api: Api,
+}
+
+impl GiteaClient {
+    /// `host` の Gitea のクライアントを作る。トークンが設定に無ければキーチェーンから探す
+    pub fn new(
+        runner: Arc<dyn CommandRunner>,
+        cache_dir: Option<&Path>,
+        host: &str,
+        settings: ForgeHost,
+    ) -> Self {
+        let token_source = TokenSource {
+            configured: settings.token,
+            env: &[],
+            cli: None,
+            host: host.to_string(),
+        };
+        let base = settings
+            .api
+            .map(|api| api.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| format!("https://{}/api/v1", host));
+        let api = Api::new(
+            runner,
+            cache_dir,
+            &format!("gitea-{}", host),
+            base,
+            token_source,
+            |token| format!("Authorization: token {}", token),
+        )
+        .header("Accept: application/json");
+        GiteaClient {
+            host: host.to_string(),
+            api,
+        }
+    }
+}
+
+impl Forge for GiteaClient {
+    fn parse_remote(&self, url: &str) -> Option<String> {
+        parse_remote(url, &self.host)
+    }
+
+    /// デフォルトブランチの最新コミットのステータスをまとめたもの
+    fn ci_status(&self, repo: &str) -> Option<CiStatus> {
+        if let Some(status) = self.api.cached_ci(repo) {
+            return status;
+        }
+
+        let branch = self.api.default_branch(repo, &format!("/repos/{}", repo))?;
+        let combined = self.api.json(&format!(
+            "/repos/{}/commits/{}/status",
+            repo,
+            forge::encode(&branch)
+        ))?;
+        let status = combined_status(&combined);
+
+        self.api.store_ci(repo, status);
+        status
+    }
+
+    /// Gitea の open_issues_count は PR を含まない
+    fn open_counts(&self, repo: &str) -> Option<OpenCounts> {
+        if let Some(counts) = self.api.cached_counts(repo) {
+            return Some(counts);
+        }
+
+        let info = self.api.json(&format!("/repos/{}", repo))?;
+        let counts = OpenCounts {
+            pull_requests: info["open_pr_counter"].as_u64()?,
+            issues: info["open_issues_count"].as_u64()?,
+        };
+        self.api.store_counts(repo, counts);
+        Some(counts)
+    }
+}
+
+/// コミットのステータスをまとめた結果。ステータスが1つも無ければ None
+fn combined_status(combined: &Value) -> Option<CiStatus> {
+    if combined["statuses"].as_array().is_none_or(Vec::is_empty) {
+        return None;
+    }
+    match combined["state"].as_str()? {
+        "success" | "warning" => Some(CiStatus::Success),
+        "failure" | "error" => Some(CiStatus::Failure),
+        "pending" => Some(CiStatus::Pending),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::forge::tests::respond;
+    use crate::runner::MockRunner;
+
+    use super::*;
+
+    #[test]
+    fn encodes_the_branch_in_the_status_path() {
+        let runner = Arc::new(MockRunner::new());
+        let repo = "https://codeberg.org/api/v1/repos/owner/app";
+        respond(&runner, repo, r#"{"default_branch": "release/1.0+x"}"#);
+        respond(
+            &runner,
+            &format!("{}/commits/release%2F1.0%2Bx/status", repo),
+            r#"{"state": "pending", "statuses": [{"status": "pending"}]}"#,
+        );
+        let settings = ForgeHost {
+            token: Some("token".to_string()),
+            api: None,
+        };
+
+        let cache = TempDir::new().unwrap();
+        let client = GiteaClient::new(runner.clone(), Some(cache.path()), "codeberg.org", settings);
+        assert!(matches!(
+            client.ci_status("owner/app"),
+            Some(CiStatus::Pending)
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+//! GitHub API との連携
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::forge::{self, Api, CiStatus, Forge, OpenCounts, TokenSource};
+use crate::runner::{Cmd, CommandRunner};
+
+const API_BASE: &str = "https://api.github.com";
+
+/// github.com のリモート URL から (owner, repo) を取り出す
+pub fn parse_remote(url: &str) -> Option<(String, String)> {
+    let path = forge::remote_path(url, "github.com")?;
+    let (owner, repo) = path.split_once('/')?;
+    if repo.contains('/') {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// GitHub REST API クライアント。トークンが無くても公開リポジトリは参照できる
+pub struct GithubClient {
+    api: Api,
+}
+
+impl GithubClient {
+    /// クライアントを作る。`token` が無ければ GITHUB_TOKEN / GH_TOKEN 環境変数、`gh auth token`、
+    /// キーチェーンの順に探す
+    pub fn new(
+        runner: Arc<dyn CommandRunner>,
+        cache_dir: Option<&Path>,
+        token: Option<String>,
+    ) -> Self {
+        let token_source = TokenSource {
+            configured: token,
+            env: &["GITHUB_TOKEN", "GH_TOKEN"],
+            cli: Some(
+                Cmd::new("gh")
+                    .args(["auth", "token", "--hostname", "github.com"])
+                    .read_only(),
+            ),
+            host: "github.com".to_string(),
+        };
+        let api = Api::new(
+            runner,
+            cache_dir,
+            "github",
+            API_BASE,
+            token_source,
+            |token| format!("Authorization: Bearer {}", token),
+        )
+        .header("Accept: application/vnd.github+json");
+        GithubClient { api }
+    }
+}
+
+impl Forge for GithubClient {
+    fn parse_remote(&self, url: &str) -> Option<String> {
+        let (owner, repo) = parse_remote(url)?;
+        Some(format!("{}/{}", owner, repo))
+    }
+
+    /// デフォルトブランチの最新コミットに対するチェック結果をまとめる
+    fn ci_status(&self, repo: &str) -> Option<CiStatus> {
+        if let Some(status) = self.api.cached_ci(repo) {
+            return status;
+        }
+
+        let branch = self.api.default_branch(repo, &format!("/repos/{}", repo))?;
+        let runs = self.api.json(&format!(
+            "/repos/{}/commits/{}/check-runs?per_page=100",
+            repo,
+            forge::encode(&branch)
+        ))?;
+        let status = summarize_check_runs(&runs);
+
+        self.api.store_ci(repo, status);
+        status
+    }
+
+    /// Issue の数は open_issues_count から PR を差し引いて求める
+    fn open_counts(&self, repo: &str) -> Option<OpenCounts> {
+        if let Some(counts) = self.api.cached_counts(repo) {
+            return Some(counts);
+        }
+
+        let info = self.api.json(&format!("/repos/{}", repo))?;
+        let open_issues = info["open_issues_count"].as_u64()?;
+        let search = self.api.json(&format!(
+            "/search/issues?q=repo:{}+is:pr+is:open&per_page=1",
+            repo
+        ))?;
+        let pull_requests = search["total_count"].as_u64()?;
+
+        let counts = OpenCounts {
+            pull_requests,
+            issues: open_issues.saturating_sub(pull_requests),
+        };
+        self.api.store_counts(repo, counts);
+        Some(counts)
+    }
+}
+
+fn summarize_check_runs(runs: &Value) -> Option<CiStatus> {
+    let runs = runs["check_runs"].as_array()?;
+    if runs.is_empty() {
+        return None;
+    }
+
+    let mut status = CiStatus::Success;
+    for run in runs {
+        if run["status"].as_str() != Some("completed") {
+            status = CiStatus::Pending;
+            continue;
+        }
+        if matches!(
+            run["conclusion"].as_str(),
+            Some("failure" | "timed_out" | "cancelled" | "action_required")
+        ) {
+            return Some(CiStatus::Failure);
+        }
+    }
+    Some(status)
+}
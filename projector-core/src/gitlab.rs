@@ -0,0 +1,179 @@
+//! GitLab API との連携（gitlab.com とセルフホストの GitLab）
+
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::config::{ForgeHost, GITLAB_COM};
+use crate::forge::{self, Api, CiStatus, Forge, OpenCounts, TokenSource};
+use crate::runner::{Cmd, CommandRunner};
+
+/// `host` の GitLab のリモート URL からプロジェクトのパス（サブグループを含む）を取り出す
+pub fn parse_remote(url: &str, host: &str) -> Option<String> {
+    forge::remote_path(url, host).filter(|path| path.contains('/'))
+}
+
+/// GitLab REST API クライアント。トークンが無くても公開プロジェクトは参照できる
+pub struct GitlabClient {
+    host: String,
+    api: Api,
+}
+
+impl GitlabClient {
+    /// `host` の GitLab のクライアントを作る。トークンが設定に無ければ、gitlab.com なら
+    /// GITLAB_TOKEN 環境変数、それから glab の設定、キーチェーンの順に探す
+    pub fn new(
+        runner: Arc<dyn CommandRunner>,
+        cache_dir: Option<&Path>,
+        host: &str,
+        settings: ForgeHost,
+    ) -> Self {
+        let token_source = TokenSource {
+            configured: settings.token,
+            // 環境変数のトークンを別のホストに送らないよう、gitlab.com だけに使う
+            env: if host == GITLAB_COM {
+                &["GITLAB_TOKEN"]
+            } else {
+                &[]
+            },
+            cli: Some(
+                Cmd::new("glab")
+                    .args(["config", "get", "token", "--host", host])
+                    .read_only(),
+            ),
+            host: host.to_string(),
+        };
+        let base = settings
+            .api
+            .map(|api| api.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| format!("https://{}/api/v4", host));
+        // gitlab.com のキャッシュは以前と同じファイルを使う
+        let cache = if host == GITLAB_COM {
+            "gitlab".to_string()
+        } else {
+            format!("gitlab-{}", host)
+        };
+        let api = Api::new(runner, cache_dir, &cache, base, token_source, |token| {
+            format!("PRIVATE-TOKEN: {}", token)
+        });
+        GitlabClient {
+            host: host.to_string(),
+            api,
+        }
+    }
+
+    /// JSON 本体と X-Total ヘッダーの値を返す
+    fn get(&self, path: &str) -> Option<(Value, Option<u64>)> {
+        let response = self.api.get(path)?;
+        let total = response.header("x-total").and_then(|t| t.parse().ok());
+        Some((serde_json::from_str(&response.body).ok()?, total))
+    }
+}
+
+impl Forge for GitlabClient {
+    fn parse_remote(&self, url: &str) -> Option<String> {
+        parse_remote(url, &self.host)
+    }
+
+    /// デフォルトブランチの最新のパイプラインの結果
+    fn ci_status(&self, project: &str) -> Option<CiStatus> {
+        if let Some(status) = self.api.cached_ci(project) {
+            return status;
+        }
+
+        let id = project_id(project);
+        let branch = self.api.default_branch(&id, &format!("/projects/{}", id))?;
+        let pipelines = self.api.json(&format!(
+            "/projects/{}/pipelines?ref={}&per_page=1",
+            id,
+            forge::encode(&branch)
+        ))?;
+        let status = pipelines
+            .as_array()?
+            .first()
+            .and_then(|pipeline| pipeline["status"].as_str())
+            .and_then(pipeline_status);
+
+        self.api.store_ci(project, status);
+        status
+    }
+
+    /// オープンな MR と Issue の数
+    fn open_counts(&self, project: &str) -> Option<OpenCounts> {
+        if let Some(counts) = self.api.cached_counts(project) {
+            return Some(counts);
+        }
+
+        let id = project_id(project);
+        let (info, _) = self.get(&format!("/projects/{}", id))?;
+        let issues = info["open_issues_count"].as_u64().unwrap_or(0);
+        // MR の総数は X-Total ヘッダーで返ってくる
+        let (_, total) = self.get(&format!(
+            "/projects/{}/merge_requests?state=opened&per_page=1",
+            id
+        ))?;
+
+        let counts = OpenCounts {
+            pull_requests: total?,
+            issues,
+        };
+        self.api.store_counts(project, counts);
+        Some(counts)
+    }
+}
+
+/// API の URL に埋め込むプロジェクトの ID（パスの `/` もエスケープしたもの）
+fn project_id(project: &str) -> String {
+    forge::encode(project)
+}
+
+/// パイプラインの状態。手動実行待ちやスキップは CI の結果として扱わない
+fn pipeline_status(status: &str) -> Option<CiStatus> {
+    match status {
+        "success" => Some(CiStatus::Success),
+        "failed" | "canceled" => Some(CiStatus::Failure),
+        "created" | "waiting_for_resource" | "preparing" | "pending" | "running" | "scheduled" => {
+            Some(CiStatus::Pending)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::forge::tests::respond;
+    use crate::runner::MockRunner;
+
+    use super::*;
+
+    #[test]
+    fn encodes_the_branch_in_the_pipeline_query() {
+        let runner = Arc::new(MockRunner::new());
+        let project = "https://gitlab.example.com/api/v4/projects/group%2Fapp";
+        respond(&runner, project, r#"{"default_branch": "release/1.0+x"}"#);
+        respond(
+            &runner,
+            &format!("{}/pipelines?ref=release%2F1.0%2Bx&per_page=1", project),
+            r#"[{"status": "failed"}]"#,
+        );
+        let settings = ForgeHost {
+            token: Some("token".to_string()),
+            api: None,
+        };
+
+        let cache = TempDir::new().unwrap();
+        let client = GitlabClient::new(
+            runner.clone(),
+            Some(cache.path()),
+            "gitlab.example.com",
+            settings,
+        );
+        assert!(matches!(
+            client.ci_status("group/app"),
+            Some(CiStatus::Failure)
+        ));
+    }
+}
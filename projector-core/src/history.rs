@@ -0,0 +1,147 @@
+//! シェル（zsh / bash）の履歴から、よく cd したプロジェクトを拾う
+//!
+//! 初めて使うときから一覧をよく使う順に並べられるよう、開いた回数（[`Visits`]）の初期値にする。
+//! 履歴のコマンドは解釈せず、`cd` と `pushd` の引数を読むだけなので、変数や補完に頼った移動は拾えない。
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::location::expand_tilde;
+use crate::scan::ScanOptions;
+use crate::state::Visits;
+
+/// 履歴にあった1回の移動
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cd {
+    /// 移動先（`~` は展開済み）
+    pub dir: PathBuf,
+    /// 実行した UNIX 時刻（秒）。履歴に時刻が無ければ None
+    pub time: Option<u64>,
+}
+
+/// 既定で読む履歴ファイル（`$HISTFILE`・`~/.zsh_history`・`~/.bash_history` のうちあるもの）
+pub fn default_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = env::var_os("HISTFILE")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    if let Some(home) = dirs::home_dir() {
+        files.extend([".zsh_history", ".bash_history"].map(|name| home.join(name)));
+    }
+    let mut found: Vec<PathBuf> = vec![];
+    for file in files {
+        if file.is_file() && !found.contains(&file) {
+            found.push(file);
+        }
+    }
+    found
+}
+
+/// 履歴の内容から `cd` と `pushd` の移動先を読む
+///
+/// zsh の拡張形式（`: 1700000000:0;cd dir`）と、bash の `HISTTIMEFORMAT` 付きの形式
+/// （コマンドの前の行に `#1700000000`）の時刻を読む。相対パスは、端末を開いたばかりの
+/// ホームディレクトリからの移動とみなす。
+pub fn parse(content: &str) -> Vec<Cd> {
+    let mut cds = vec![];
+    let mut time = None;
+    for line in content.lines() {
+        if let Some(stamp) = line.strip_prefix('#').and_then(|s| s.parse().ok()) {
+            time = Some(stamp);
+            continue;
+        }
+        let (command, line_time) = match zsh_extended(line) {
+            Some((stamp, command)) => (command, Some(stamp)),
+            None => (line, time.take()),
+        };
+        for part in command.split(['&', ';', '|']) {
+            if let Some(dir) = cd_target(part) {
+                cds.push(Cd {
+                    dir,
+                    time: line_time,
+                });
+            }
+        }
+    }
+    cds
+}
+
+/// zsh の拡張形式の行を時刻とコマンドに分ける
+fn zsh_extended(line: &str) -> Option<(u64, &str)> {
+    let rest = line.strip_prefix(": ")?;
+    let (meta, command) = rest.split_once(';')?;
+    let (stamp, _duration) = meta.split_once(':')?;
+    Some((stamp.parse().ok()?, command))
+}
+
+/// `cd dir` や `pushd dir` の移動先。引数が無い・`-` などは None
+fn cd_target(command: &str) -> Option<PathBuf> {
+    let mut words = command.trim().splitn(2, char::is_whitespace);
+    if !matches!(words.next()?, "cd" | "pushd") {
+        return None;
+    }
+    let arg = words.next()?.trim();
+    let arg = arg
+        .strip_prefix('"')
+        .and_then(|a| a.strip_suffix('"'))
+        .or_else(|| arg.strip_prefix('\'').and_then(|a| a.strip_suffix('\'')))
+        .unwrap_or(arg);
+    if arg.is_empty() || arg.starts_with(['-', '+', '$']) {
+        return None;
+    }
+    let arg = arg.replace("\\ ", " ");
+    // 末尾の `/` などを除いて、開いたときの場所と同じ形にする
+    let dir: PathBuf = expand_tilde(&arg).components().collect();
+    if dir.is_absolute() {
+        Some(dir)
+    } else {
+        dirs::home_dir().map(|home| home.join(dir))
+    }
+}
+
+/// `dir` を含むプロジェクト（`dir` 自身か、ルートより下で一番近いプロジェクトの祖先）
+pub fn project_of(dir: &Path, roots: &[PathBuf], options: &ScanOptions) -> Option<PathBuf> {
+    let root = roots.iter().find(|root| dir.starts_with(root))?;
+    dir.ancestors()
+        .take_while(|ancestor| *ancestor != root.as_path())
+        .find(|ancestor| options.is_project(ancestor))
+        .map(Path::to_path_buf)
+}
+
+/// 履歴ファイルを読み、ルートの下のプロジェクトごとに cd した回数と最後の時刻をまとめる
+///
+/// 時刻の無い履歴は、ファイルの更新日時を最後に cd した時刻とみなす。
+pub fn import(
+    files: &[PathBuf],
+    roots: &[PathBuf],
+    options: &ScanOptions,
+) -> BTreeMap<PathBuf, Visits> {
+    let mut visits: BTreeMap<PathBuf, Visits> = BTreeMap::new();
+    // 同じディレクトリへの移動が多いので、プロジェクトかどうかは1度だけ調べる
+    let mut projects: BTreeMap<PathBuf, Option<PathBuf>> = BTreeMap::new();
+    for file in files {
+        // zsh は UTF-8 でない文字を独自の形式で書くので、読めない部分は置き換える
+        let Ok(bytes) = fs::read(file) else {
+            continue;
+        };
+        let modified = fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+        for cd in parse(&String::from_utf8_lossy(&bytes)) {
+            let project = projects
+                .entry(cd.dir.clone())
+                .or_insert_with(|| project_of(&cd.dir, roots, options));
+            if let Some(project) = project {
+                let entry = visits.entry(project.clone()).or_default();
+                entry.count = entry.count.saturating_add(1);
+                entry.last = entry.last.max(cd.time.unwrap_or(modified));
+            }
+        }
+    }
+    visits
+}
@@ -0,0 +1,65 @@
+//! curl を使った HTTP クライアント
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+/// curl で取得した HTTP レスポンス
+pub struct Response {
+    /// ステータスコード
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    /// レスポンス本文
+    pub body: String,
+}
+
+impl Response {
+    /// ヘッダーの値（大文字小文字を区別しない）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// curl で GET する。ヘッダーはプロセス一覧にトークンが出ないよう標準入力から渡す
+pub fn get(runner: &dyn CommandRunner, url: &str, headers: &[String]) -> Result<Response> {
+    let cmd = Cmd::new("curl")
+        .args(["-sS", "-i", "--max-time", "10", "-H", "@-", url])
+        .stdin(headers.join("\n"))
+        .read_only();
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("curl", e))?;
+    if !output.success {
+        return Err(Error::Http(output.stderr_str().trim().to_string()));
+    }
+
+    parse_response(&output.stdout_str())
+}
+
+pub(crate) fn parse_response(raw: &str) -> Result<Response> {
+    // リダイレクトや 100 Continue で複数のヘッダーブロックが続くことがあるので最後のものを使う
+    let mut rest = raw;
+    let mut head = "";
+    while rest.starts_with("HTTP/") {
+        let (block, body) = rest.split_once("\r\n\r\n").ok_or(Error::InvalidResponse)?;
+        head = block;
+        rest = body;
+    }
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or(Error::InvalidResponse)?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Ok(Response {
+        status,
+        headers,
+        body: rest.to_string(),
+    })
+}
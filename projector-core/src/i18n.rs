@@ -0,0 +1,1203 @@
+//! 表示メッセージの多言語対応
+//!
+//! 言語は設定ファイルの `language` か、環境変数 `LC_ALL` / `LC_MESSAGES` / `LANG` から決める。
+//! 日本語のロケール以外では英語で表示する。
+
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::config::SortOrder;
+use crate::profile::Timing;
+
+// 0 は未設定。設定を読み込み直したときに変えられるよう OnceLock にはしない
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+/// 表示言語
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    /// 日本語
+    Ja,
+    /// 英語
+    En,
+}
+
+impl Lang {
+    /// `ja` / `en` や `ja_JP.UTF-8` のようなロケール名から言語を決める
+    pub fn parse(name: &str) -> Option<Lang> {
+        let name = name.to_ascii_lowercase();
+        if name.starts_with("ja") {
+            Some(Lang::Ja)
+        } else if name.starts_with("en") {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    /// 設定値（`auto` か未設定なら環境変数）から言語を決める
+    pub fn detect(config: Option<&str>) -> Lang {
+        if let Some(lang) = config.filter(|c| *c != "auto").and_then(Lang::parse) {
+            return lang;
+        }
+        // 最初に空でない変数がロケールを決める（POSIX の優先順位）
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::parse(&value))
+            .unwrap_or(Lang::En)
+    }
+}
+
+/// 表示言語を設定する。設定を読み込み直したときにも呼ぶ
+pub fn set_lang(lang: Lang) {
+    let value = match lang {
+        Lang::Ja => 1,
+        Lang::En => 2,
+    };
+    LANG.store(value, Ordering::Relaxed);
+}
+
+/// 現在の表示言語。未設定なら環境変数から決める
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Ja,
+        2 => Lang::En,
+        _ => {
+            let lang = Lang::detect(None);
+            set_lang(lang);
+            lang
+        }
+    }
+}
+
+/// 画面や端末に出すメッセージ。`Display` で現在の言語の文言になる
+pub enum Msg<'a> {
+    /// tmux セッションを開く直前
+    Opening(&'a str),
+    /// 設定したコマンドで開く直前
+    OpeningWith {
+        /// プロジェクトの場所
+        name: &'a str,
+        /// 実行するコマンド
+        command: &'a str,
+    },
+    /// devcontainer を起動する直前
+    OpeningDevcontainer(&'a str),
+    /// docker compose up する直前
+    OpeningCompose(&'a str),
+    /// git の UI のペインを付けて開くとき
+    OpeningGit(&'a str),
+    /// ファイルマネージャーで開くとき
+    OpeningFiles(&'a str),
+    /// ワークスペースに端末を開くとき
+    OpeningWorkspace(&'a str),
+    /// 端末アプリの新しいウィンドウで開くとき
+    OpeningNewWindow(&'a str),
+    /// 今の tmux セッションの新しいウィンドウで開くとき
+    OpeningWindow(&'a str),
+    /// タスクを実行するとき
+    RunningTask {
+        /// プロジェクト
+        name: &'a str,
+        /// 実行するコマンド
+        command: &'a str,
+    },
+    /// セッション終了後に docker compose down する
+    ComposeDown,
+    /// tmux などが無いのでシェルを起動する
+    NoMultiplexer,
+    /// TUI の起動時に tmux が無いと分かった
+    TmuxMissing(&'a str),
+    /// 同名のセッションにアタッチする
+    SessionExists(&'a str),
+    /// `projector resurrect`: セッションを作り直した
+    Resurrected(&'a str),
+    /// `projector resurrect`: セッションが既にある
+    AlreadyRunning(&'a str),
+    /// `projector resurrect`: プロジェクトのディレクトリが無くなっている
+    ResurrectMissing {
+        /// セッション名
+        name: &'a str,
+        /// ディレクトリ
+        dir: &'a str,
+    },
+    /// `projector resurrect`: 覚えているセッションが無い
+    NoSavedSessions,
+    /// projector が作ったセッションが1つも動いていない
+    NoRunningSessions,
+    /// デーモンが問い合わせを待ち始めた
+    DaemonListening(&'a str),
+    /// `--profile`: 1つの区間にかかった時間
+    Profile(&'a Timing),
+    /// tmux-resurrect の保存から先に復元するかの確認
+    TmuxResurrectPrompt(&'a str),
+    /// tmux-resurrect で復元している
+    RestoringTmuxResurrect,
+    /// tmux-resurrect の保存にあるセッションは作らない
+    LeftToTmuxResurrect(&'a str),
+    /// `open` の名前に当てはまるプロジェクトが複数ある
+    AmbiguousName(&'a str),
+    /// 当てはまったプロジェクトから番号で選ぶ（番号の最大）
+    ChooseMatch(usize),
+    /// `doctor`: 設定ファイルがまだ無い
+    DoctorNoConfig(&'a str),
+    /// `doctor`: tmux のバージョンを読めない
+    DoctorUnknownVersion,
+    /// `doctor`: ディレクトリを読める
+    DoctorReadable,
+    /// `doctor`: ディレクトリが無い
+    DoctorMissing,
+    /// `doctor`: ディレクトリを読めない
+    DoctorUnreadable(&'a str),
+    /// `doctor`: ssh で接続できる
+    DoctorReachable,
+    /// `doctor`: ssh で接続できない
+    DoctorUnreachable(&'a str),
+    /// `doctor`: ディレクトリに書き込めない
+    DoctorUnwritable {
+        /// ディレクトリ
+        path: &'a str,
+        /// 元のエラー
+        error: &'a str,
+    },
+    /// `doctor`: JSON のファイルが壊れている
+    DoctorBroken(&'a str),
+    /// `doctor`: クリップボードのコマンドが無い
+    DoctorNoClipboard,
+    /// `doctor`: 問題が無かった
+    DoctorAllGood,
+    /// `doctor`: 問題と警告の数
+    DoctorSummary {
+        /// 直さないと動かない問題
+        failures: usize,
+        /// 使えない機能がある警告
+        warnings: usize,
+    },
+    /// `import-history`: 履歴で cd した回数
+    HistoryVisits {
+        /// プロジェクト
+        name: &'a str,
+        /// 回数
+        count: u32,
+    },
+    /// `import-history`: 取り込んだプロジェクトの数
+    HistoryImported {
+        /// 回数を覚えたプロジェクト
+        imported: usize,
+        /// 既に回数があるので変えなかったプロジェクト
+        skipped: usize,
+    },
+    /// `import-history`: 読める履歴ファイルが無い
+    NoHistoryFiles,
+    /// `export`: Markdown の表の見出しの行
+    ExportHeader,
+    /// `state export`: 書き出したファイル
+    StateExported(&'a str),
+    /// `state import`: 手元と行き先が違うので手元のままにしたエイリアス
+    AliasConflict {
+        /// エイリアスの名前
+        name: &'a str,
+        /// 手元の行き先
+        local: &'a str,
+        /// 取り込もうとした行き先
+        imported: &'a str,
+    },
+    /// `state import`: 取り込んで増えたものの数
+    StateImported {
+        /// 加えたエイリアス
+        aliases: usize,
+        /// タグに加えたプロジェクト
+        tags: usize,
+        /// 加えたか回数の増えたプロジェクト
+        visits: usize,
+        /// 加えた作業時間の記録
+        spans: usize,
+    },
+    /// `stats`: 週の見出し（週の始まりの日付）
+    StatsWeek(&'a str),
+    /// `stats`: 週の合計の行
+    StatsTotal,
+    /// `stats`: 作業時間の記録が無い
+    NoTimeTracked,
+    /// 何も選ばずに終了した
+    Cancelled,
+    /// `--help` の内容
+    Usage,
+    /// エラーの表示
+    Error(&'a dyn fmt::Display),
+    /// 初回設定の案内
+    WizardIntro(&'a str),
+    /// 初回設定: ルートの入力
+    WizardRoots,
+    /// 初回設定: 存在しないルートを作るか
+    WizardCreate(&'a str),
+    /// 初回設定: 端末の仕組みの入力
+    WizardBackend,
+    /// 初回設定: 解釈できない入力
+    WizardInvalid,
+    /// 初回設定: 設定ファイルを書いた
+    WizardSaved(&'a str),
+    /// 設定ファイルを読み込み直した
+    ConfigReloaded,
+    /// 並び順を切り替えた
+    SortedBy(SortOrder),
+    /// 隠しディレクトリを並べるかを切り替えた
+    HiddenShown(bool),
+    /// キー操作のヒントのうち、一覧の中を移動するキー
+    NavigationHint,
+    /// キー操作のヒントのうち、リーダーキー
+    LeaderHint(&'a str),
+    /// サブディレクトリが無い
+    NoSubdirectories,
+    /// Space でプロジェクトの中に入ろうとした
+    ProjectBoundary(&'a str),
+    /// 設定の `max_depth` より深い一覧に入ろうとした
+    MaxDepth(usize),
+    /// モノレポのパッケージを並べているときのヘッダー
+    Packages(&'a str),
+    /// 絞り込みに一致するものが無い
+    NoMatches,
+    /// 絞り込み中の操作説明
+    FilterHint,
+    /// 検索語が正規表現として不正
+    InvalidRegex,
+    /// 移動先の入力中の操作説明
+    JumpHint,
+    /// 移動先がディレクトリでない
+    NotADirectory(&'a str),
+    /// 新しい名前の入力中の操作説明
+    RenameHint,
+    /// 新しいディレクトリの名前の入力中の操作説明
+    NewDirHint,
+    /// ディレクトリを作った
+    Created(&'a str),
+    /// 名前に使えない文字（`/` など）が入っている
+    InvalidName(&'a str),
+    /// 名前を変えた
+    Renamed {
+        /// 元の名前
+        from: &'a str,
+        /// 新しい名前
+        to: &'a str,
+    },
+    /// ゴミ箱に移した
+    Trashed(&'a str),
+    /// アーカイブに移した
+    Archived {
+        /// プロジェクト名
+        name: &'a str,
+        /// 移した先
+        to: &'a str,
+    },
+    /// 直前の操作を取り消した
+    Undone(&'a str),
+    /// 取り消せる操作が無い
+    NothingToUndo,
+    /// ゴミ箱に移すかの確認
+    ConfirmTrash(&'a str),
+    /// tmux セッションを終了するかの確認
+    ConfirmKillSession(&'a str),
+    /// プロジェクトの目印が無いディレクトリを開くか尋ねる
+    ConfirmOpenPlain(&'a str),
+    /// 移動先にあるものを置き換えるかの確認
+    ConfirmOverwrite(&'a str),
+    /// 確認ダイアログの「はい」
+    Yes,
+    /// 確認ダイアログの「いいえ」
+    No,
+    /// 確認ダイアログの操作説明
+    ConfirmHint,
+    /// エラーの表示の見出し
+    ErrorTitle,
+    /// エラーの表示の操作説明
+    ErrorHint,
+    /// tmux セッションを終了した
+    SessionKilled(&'a str),
+    /// プロジェクトの tmux セッションが無い
+    NoSession(&'a str),
+    /// `--stdin` のときのヘッダー
+    StdinCandidates,
+    /// 全ルートの検索のときのヘッダー
+    AllProjects,
+    /// 全ルートのプロジェクトの一覧を作成中
+    Indexing,
+    /// 全ルートを辿っている途中の、読み終えたディレクトリの数
+    Scanning(usize),
+    /// 内容を検索中
+    Searching,
+    /// タスクの選択のヘッダー
+    TasksTitle(&'a str),
+    /// タスクの選択中の操作説明
+    TasksHint,
+    /// タスクが見つからない
+    NoTasks,
+    /// 種類・タグの選択のヘッダー
+    TypesTitle,
+    /// 種類・タグの選択中の操作説明
+    TypesHint,
+    /// 種類・タグで絞り込まない選択肢
+    AllTypes,
+    /// 一覧に種類の分かるプロジェクトもタグも無い
+    NoProjectTypes,
+    /// 内容の検索結果のヘッダー
+    SearchResults(&'a str),
+    /// 検索語の入力中の操作説明
+    SearchHint,
+    /// 詳細ペインの見出し
+    DetailsTitle,
+    /// リモートのプロジェクトの場所
+    Remote(&'a str),
+    /// 計算中
+    Computing,
+    /// 問い合わせ中
+    Fetching,
+    /// ディスク使用量
+    Size(&'a str),
+    /// TODO / FIXME の数
+    Markers(&'a str),
+    /// git のスタッシュの数
+    Stashes(usize),
+    /// 詳細ペインの健全性の行の見出し
+    HealthTitle,
+    /// 最後のコミットからの経過時間（秒）
+    CommitAge(u64),
+    /// コミットしていない変更がある
+    Dirty,
+    /// コミットしていない変更が無い
+    Clean,
+    /// 健全性の行のオープンな PR / MR の数
+    HealthPullRequests(u64),
+    /// git の upstream より進んでいる・遅れているコミット数
+    Upstream {
+        /// upstream に無いコミット数
+        ahead: usize,
+        /// upstream にしか無いコミット数
+        behind: usize,
+    },
+    /// キーの割り当ての一覧に出す操作の説明（操作の名前を渡す）
+    CommandName(&'a str),
+    /// キーの割り当ての一覧に出す、続きのキーがあるまとまりの名前
+    ChordGroup(&'a str),
+    /// devcontainer の定義がある
+    DevcontainerAvailable,
+    /// docker compose の状態
+    Compose(&'a str),
+    /// docker が使えず状態が分からない
+    ComposeUnknown,
+    /// コンテナが1つも動いていない
+    ComposeStopped,
+    /// 動いているコンテナの数
+    ComposeRunning {
+        /// 稼働中のサービス数
+        running: usize,
+        /// サービスの総数
+        total: usize,
+    },
+    /// オープンな PR と Issue の数
+    OpenCounts {
+        /// PR / MR の数
+        pull_requests: u64,
+        /// Issue の数
+        issues: u64,
+    },
+    /// オープンな PR と Issue を取得中
+    OpenCountsFetching,
+    /// 言語別の行数を計算中
+    LanguagesComputing,
+    /// ソースファイルが無い
+    LanguagesNone,
+    /// 全言語の合計行数
+    LanguagesTotal(usize),
+    /// 言語ごとの行数
+    LanguageRow {
+        /// 言語名
+        name: &'a str,
+        /// 行数
+        lines: usize,
+        /// ファイル数
+        files: usize,
+    },
+}
+
+impl Msg<'_> {
+    fn ja(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Msg::Opening(name) => write!(f, "選択: {} -> TMUXを起動します...", name),
+            Msg::OpeningWith { name, command } => {
+                write!(f, "選択: {} -> {} を実行します...", name, command)
+            }
+            Msg::OpeningDevcontainer(name) => {
+                write!(f, "選択: {} -> Devcontainer を起動します...", name)
+            }
+            Msg::OpeningCompose(name) => {
+                write!(f, "選択: {} -> docker compose up してから TMUXを起動します...", name)
+            }
+            Msg::OpeningGit(name) => {
+                write!(f, "選択: {} -> git の UI を並べて TMUXを起動します...", name)
+            }
+            Msg::OpeningFiles(name) => {
+                write!(f, "選択: {} -> ファイルマネージャーを開きます...", name)
+            }
+            Msg::OpeningWorkspace(name) => {
+                write!(f, "選択: {} -> プロジェクトのワークスペースに端末を開きます...", name)
+            }
+            Msg::OpeningNewWindow(name) => {
+                write!(f, "選択: {} -> 新しい端末のウィンドウで TMUXを起動します...", name)
+            }
+            Msg::OpeningWindow(name) => {
+                write!(f, "選択: {} -> 今のセッションに新しいウィンドウを開きます...", name)
+            }
+            Msg::RunningTask { name, command } => {
+                write!(f, "選択: {} -> 新しいウィンドウで {} を実行します...", name, command)
+            }
+            Msg::ComposeDown => write!(f, "セッションが終了したので docker compose down します..."),
+            Msg::NoMultiplexer => write!(f, "tmux や Windows Terminal が見つからないので、シェルを起動します"),
+            Msg::TmuxMissing(shell) => write!(
+                f,
+                "tmux が見つかりません。Enter では {} を起動します ([g] [t] [K] は使えません)",
+                shell
+            ),
+            Msg::SessionExists(name) => {
+                write!(f, "セッション '{}' は既に存在します。アタッチします...", name)
+            }
+            Msg::Resurrected(name) => write!(f, "セッション '{}' を作り直しました", name),
+            Msg::AlreadyRunning(name) => write!(f, "セッション '{}' は既に存在します", name),
+            Msg::ResurrectMissing { name, dir } => {
+                write!(f, "{} が無いのでセッション '{}' を作り直せません", dir, name)
+            }
+            Msg::NoSavedSessions => write!(f, "覚えているセッションがありません"),
+            Msg::NoRunningSessions => write!(f, "projector が作ったセッションは動いていません"),
+            Msg::TmuxResurrectPrompt(path) => write!(
+                f,
+                "tmux-resurrect の保存 ({}) があります。先に復元しますか？ [y/N] ",
+                path
+            ),
+            Msg::RestoringTmuxResurrect => write!(f, "tmux-resurrect で復元しています..."),
+            Msg::DaemonListening(socket) => {
+                write!(f, "{} で問い合わせを待っています (Ctrl-C で終了)", socket)
+            }
+            Msg::Profile(timing) => write!(
+                f,
+                "[profile] {}: {:.1}ms ({}回)",
+                timing.label,
+                timing.total.as_secs_f64() * 1000.0,
+                timing.count
+            ),
+            Msg::LeftToTmuxResurrect(name) => write!(
+                f,
+                "セッション '{}' は tmux-resurrect の保存にあるので作りません",
+                name
+            ),
+            Msg::AmbiguousName(name) => {
+                write!(f, "'{}' に当てはまるプロジェクトが複数あります:", name)
+            }
+            Msg::ChooseMatch(count) => write!(f, "開くものの番号 [1-{}]: ", count),
+            Msg::DoctorNoConfig(path) => write!(f, "{} はまだありません (既定の設定を使います)", path),
+            Msg::DoctorUnknownVersion => write!(f, "バージョンを読めませんでした"),
+            Msg::DoctorReadable => write!(f, "読めます"),
+            Msg::DoctorMissing => write!(f, "存在しません。作るか、設定の roots から外してください"),
+            Msg::DoctorUnreadable(error) => write!(f, "読めません: {}", error),
+            Msg::DoctorReachable => write!(f, "ssh で接続できます"),
+            Msg::DoctorUnreachable(host) => write!(
+                f,
+                "ssh で接続できません。ssh {} がパスワードなしで通るか確かめてください",
+                host
+            ),
+            Msg::DoctorUnwritable { path, error } => {
+                write!(f, "{} に書き込めません: {}", path, error)
+            }
+            Msg::DoctorBroken(path) => write!(f, "{} が壊れています。消せば作り直します", path),
+            Msg::DoctorNoClipboard => write!(
+                f,
+                "クリップボードのコマンドがありません (pbcopy / wl-copy / xclip / xsel)"
+            ),
+            Msg::DoctorAllGood => write!(f, "問題は見つかりませんでした"),
+            Msg::DoctorSummary { failures, warnings } => {
+                write!(f, "問題 {} 件、警告 {} 件", failures, warnings)
+            }
+            Msg::HistoryVisits { name, count } => write!(f, "{:>5} 回  {}", count, name),
+            Msg::HistoryImported { imported, skipped } => write!(
+                f,
+                "{} 件のプロジェクトの回数を取り込みました（既に回数のある {} 件はそのまま）",
+                imported, skipped
+            ),
+            Msg::NoHistoryFiles => write!(
+                f,
+                "シェルの履歴ファイルが見つかりません (読むファイルを指定してください)"
+            ),
+            Msg::ExportHeader => write!(f, "| パス | 種類 | 最後に開いた日 | リモート | タグ |"),
+            Msg::StateExported(path) => write!(f, "{} に書き出しました", path),
+            Msg::AliasConflict {
+                name,
+                local,
+                imported,
+            } => write!(
+                f,
+                "エイリアス {} は手元の {} のままにしました（取り込み元は {}）",
+                name, local, imported
+            ),
+            Msg::StateImported {
+                aliases,
+                tags,
+                visits,
+                spans,
+            } => write!(
+                f,
+                "エイリアス {} 件、タグ付け {} 件、開いた回数 {} 件、作業時間 {} 件を取り込みました",
+                aliases, tags, visits, spans
+            ),
+            Msg::StatsWeek(date) => write!(f, "{} からの週", date),
+            Msg::StatsTotal => write!(f, "合計"),
+            Msg::NoTimeTracked => write!(
+                f,
+                "この期間の記録はありません (projector で tmux のセッションを開くと記録します)"
+            ),
+            Msg::Cancelled => write!(f, "キャンセルされました"),
+            Msg::Usage => write!(
+                f,
+                "使い方: projector [--picker tui|fzf|rofi|dmenu] [オプション]
+       projector list [--format text|json|raycast|alfred]
+       projector open <名前> [--detach | --print-attach] [--first | --all] [--dry-run]
+       projector last [--dry-run]
+       projector resurrect [<セッション名>...] [--dry-run]
+       projector kill <名前> | --all [--dry-run]
+       projector doctor
+       projector import-history [<ファイル>...] [--dry-run]
+       projector export [--format md|csv]
+       projector state export [<ファイル>] | import <ファイル> [--dry-run]
+       projector stats [<週の数>] [--format text|json]
+       projector daemon
+       projector serve --stdio [--msgpack]
+
+コマンド:
+  list           プロジェクトを出力する（端末でも TUI を開かない）
+  open <名前>    ルート直下のプロジェクトかエイリアスを名前で開く（name/sub で下の階層も指定できる）。
+                 パスも指定できる。--detach ならセッションを作るだけでアタッチしない。
+                 --print-attach ならセッションを作り、アタッチするコマンドを出力する。
+                 ルート直下に無い名前は下の階層から探す（org/name で絞れる）。複数あれば番号で選ぶか、
+                 --first で最初のものを、--all ですべてをアタッチせずに開く
+  last           1つ前に開いたプロジェクトをもう一度開く（cd - のように直前の2つを行き来する）
+  resurrect      projector が作った tmux セッションをバックグラウンドで作り直す（再起動後などに。
+                 セッション名を指定すればそれだけ）。tmux-resurrect の保存にあるセッションは作らず、
+                 端末なら先にその復元を実行するか確認する
+  kill <名前>    プロジェクトの tmux セッション（git UI 付きなどのものも）を終了する。名前は open と
+                 同じように探し、プロジェクトが無ければセッション名とみなす。--all なら projector が
+                 作ったセッションをすべて終了する
+  doctor         tmux・設定ファイル・ルート・キャッシュ・クリップボードを調べ、問題と直し方を表示する
+                 （不具合の報告に添えてください）
+  import-history シェルの履歴（既定は $HISTFILE・~/.zsh_history・~/.bash_history）で cd した
+                 ルートの下のプロジェクトを数え、全ルートの一覧をよく使う順に並べるための開いた回数に
+                 する。既に回数のあるプロジェクト（開いたことがあるものなど）は変えない
+  export         全ルートのプロジェクトのパス・種類・最後に開いた日・origin の URL・タグを
+                 Markdown（既定）か CSV の表にして出力する
+  state export   設定のエイリアスとタグ、開いた履歴（最近・回数・作業時間）を JSON で書き出す
+                 （ファイルを指定しなければ標準出力）。ホームの下のパスは ~/ で書く
+  state import   state export のファイルを手元のものと合わせる（- なら標準入力）。上書きはせず、
+                 同じ名前で行き先の違うエイリアスは手元のまま、回数は大きいほうを残す
+  stats          直近の週（既定は4週）ごとに、プロジェクトの tmux セッションで作業した時間を表示する。
+                 projector が開いたセッションの開始から最後のキー入力までを数え、週は UTC の月曜から。
+                 記録は状態ファイルに残すだけで、デーモンが動いていると終わりの時刻が正確になる
+  daemon         プロジェクトの一覧をメモリに持って常駐する。動いている間は一覧を走査せずに
+                 デーモンに問い合わせる（ソケットは $XDG_RUNTIME_DIR/projector/daemon.sock）
+  serve --stdio  エディタのプラグイン向けに、標準入出力で JSON-RPC 2.0 に答える
+                 （projects / query / open / metadata。1行1メッセージか Content-Length ヘッダー付き）。
+                 --msgpack なら Neovim 向けの MessagePack-RPC で答え、open を通知で送ると
+                 作り終えたところで User ProjectorOpened の自動コマンドを実行させる
+
+オプション:
+      --stdin    ルートの代わりに標準入力から1行1パスで候補を読む
+      --picker <tui|fzf|rofi|dmenu>
+                 プロジェクトの選び方（設定ファイルの picker を上書きする）。rofi / dmenu は端末が
+                 無くても使え、そのときはセッションを作るだけでアタッチしない
+      --dry-run  tmux などを実行せず、実行するコマンドを表示する
+      --format <text|json|raycast|alfred|md|csv>
+                 json なら起動せず、選んだプロジェクトを JSON で出力する（画面は標準エラー出力に描く）。
+                 raycast / alfred は一覧をランチャーの形式で出力する（arg は open --detach に渡せる）
+                 md / csv は export の表の形式
+      --profile  ディレクトリの走査・git の情報の取得・最初の描画にかかった時間を標準エラー出力に表示する
+      --color <auto|always|never>
+                 TUI の色と太字。auto なら NO_COLOR / CLICOLOR / CLICOLOR_FORCE と TERM から決める
+      --ascii    TUI の印や罫線を ASCII だけで描く（設定ファイルの ascii と同じ）
+  -v, --verbose  ログを詳しく記録する（-vv でさらに詳しく）
+  -h, --help     このヘルプを表示する
+
+終了コード:
+  0  成功
+  1  その他のエラー
+  2  引数の誤り
+  3  キャンセルされた
+  4  プロジェクトが見つからない
+  5  tmux や Windows Terminal の起動に失敗した
+  6  設定ファイルやルートの誤り
+
+環境変数:
+  PROJECTOR_CONFIG  設定ファイルのパス（既定は $XDG_CONFIG_HOME/projector/config.toml）
+  PROJECTOR_ROOT    設定のルートの代わりに使うルート（: 区切り、Windows は ;。先頭を + にすると追加）
+  XDG_CONFIG_HOME / XDG_CACHE_HOME / XDG_STATE_HOME
+                    設定・キャッシュ・ログの置き場所（既定は ~/.config ~/.cache ~/.local/state）
+
+標準出力が端末でなければ TUI を開かず、プロジェクトのパスを1行ずつ出力します。
+ログは $XDG_STATE_HOME/projector/log（既定は ~/.local/state/projector/log）に書き出されます。"
+            ),
+            Msg::Error(e) => write!(f, "エラー: {}", e),
+            Msg::WizardIntro(path) => write!(
+                f,
+                "設定ファイルが無く、~/Developer もありません。{} を作成します。",
+                path
+            ),
+            Msg::WizardRoots => write!(f, "プロジェクトを置くディレクトリ（カンマ区切り）[~/Developer]: "),
+            Msg::WizardCreate(root) => write!(f, "{} はありません。作成しますか？ [y/N]: ", root),
+            Msg::WizardBackend => write!(f, "開き方 (auto/tmux/wt/iterm/ghostty/shell) [auto]: "),
+            Msg::WizardInvalid => write!(f, "解釈できない値です"),
+            Msg::WizardSaved(path) => write!(f, "{} に保存しました", path),
+            Msg::ConfigReloaded => write!(f, "設定を読み込み直しました"),
+            Msg::SortedBy(SortOrder::Name) => write!(f, "名前順に並べます"),
+            Msg::SortedBy(SortOrder::Modified) => write!(f, "更新日時の新しい順に並べます"),
+            Msg::HiddenShown(true) => write!(f, "隠しディレクトリも表示します"),
+            Msg::HiddenShown(false) => write!(f, "隠しディレクトリを表示しません"),
+            Msg::NavigationHint => write!(
+                f,
+                "[↑↓] 移動  [Space] 入る  [←/BS] 戻る  [H/L] 履歴  [M-Enter] ここを開く"
+            ),
+            Msg::LeaderHint(key) => write!(f, "[{}] その他のキー", key),
+            Msg::NoSubdirectories => write!(f, "(サブディレクトリなし)"),
+            Msg::ProjectBoundary(name) => {
+                write!(f, "{} はプロジェクトです。Enter で開き、> で中に入ります", name)
+            }
+            Msg::MaxDepth(max) => write!(f, "これより深くは入れません (max_depth = {})", max),
+            Msg::Packages(kind) => write!(f, "({} のパッケージ)", kind),
+            Msg::NoMatches => write!(f, "(一致なし)"),
+            Msg::FilterHint => write!(f, "[Ctrl-R] 正規表現 (re:)  [Esc] 解除"),
+            Msg::InvalidRegex => write!(f, "(正規表現が不正です)"),
+            Msg::JumpHint => write!(f, "[Tab] 補完  [Enter] 移動  [Esc] 取り消し"),
+            Msg::NotADirectory(path) => {
+                write!(f, "{} はディレクトリではありません（絶対パスか ~/ で指定）", path)
+            }
+            Msg::RenameHint => write!(f, "[Enter] 名前を変える  [Esc] 取り消し"),
+            Msg::NewDirHint => write!(f, "[Enter] 作成  [Esc] 取り消し"),
+            Msg::Created(path) => write!(f, "{} を作成しました", path),
+            Msg::InvalidName(name) => write!(f, "{} は名前に使えません", name),
+            Msg::Renamed { from, to } => {
+                write!(f, "{} の名前を {} に変えました ([u] で元に戻す)", from, to)
+            }
+            Msg::Trashed(name) => write!(f, "{} をゴミ箱に移しました ([u] で元に戻す)", name),
+            Msg::Archived { name, to } => {
+                write!(f, "{} を {} に移しました ([u] で元に戻す)", name, to)
+            }
+            Msg::Undone(name) => write!(f, "{} を元に戻しました", name),
+            Msg::NothingToUndo => write!(f, "元に戻す操作がありません"),
+            Msg::ConfirmTrash(name) => write!(f, "{} をゴミ箱に移しますか?", name),
+            Msg::ConfirmOpenPlain(name) => write!(
+                f,
+                "{} はプロジェクトではないただのフォルダのようです。開きますか?",
+                name
+            ),
+            Msg::ConfirmKillSession(name) => {
+                write!(f, "tmux セッション {} を終了しますか?", name)
+            }
+            Msg::ConfirmOverwrite(path) => {
+                write!(f, "{} は既にあります。ゴミ箱に移して置き換えますか?", path)
+            }
+            Msg::Yes => write!(f, "はい"),
+            Msg::No => write!(f, "いいえ"),
+            Msg::ConfirmHint => write!(f, "[←→] 選択  [Enter] 決定  [y/n]  [Esc] やめる"),
+            Msg::ErrorTitle => write!(f, "エラー"),
+            Msg::ErrorHint => write!(f, "[Enter/Esc] 閉じる"),
+            Msg::SessionKilled(name) => write!(f, "tmux セッション {} を終了しました", name),
+            Msg::NoSession(name) => write!(f, "tmux セッション {} はありません", name),
+            Msg::StdinCandidates => write!(f, "(標準入力の候補)"),
+            Msg::AllProjects => write!(f, "(全ルートのプロジェクト)"),
+            Msg::Indexing => write!(f, "(プロジェクトを探しています...)"),
+            Msg::Scanning(count) => {
+                write!(f, "プロジェクトを探しています ({} ディレクトリ)", count)
+            }
+            Msg::Searching => write!(f, "(検索しています...)"),
+            Msg::TasksTitle(name) => write!(f, "{} のタスク", name),
+            Msg::TasksHint => write!(f, "[↑↓] 移動  [Enter] 実行  [Esc] 戻る"),
+            Msg::NoTasks => {
+                write!(f, "justfile / Makefile / package.json / Taskfile のタスクがありません")
+            }
+            Msg::TypesTitle => write!(f, "種類・タグで絞り込む"),
+            Msg::TypesHint => write!(f, "[↑↓] 移動  [Enter] 選択  [Esc] 戻る"),
+            Msg::AllTypes => write!(f, "(すべて)"),
+            Msg::NoProjectTypes => write!(f, "種類の分かるプロジェクトもタグもありません"),
+            Msg::SearchResults(query) => write!(f, "(\"{}\" を含むプロジェクト)", query),
+            Msg::SearchHint => write!(f, "[Enter] ripgrep で検索  [Esc] 取り消し"),
+            Msg::DetailsTitle => write!(f, "詳細"),
+            Msg::Remote(location) => write!(f, "リモート: {}", location),
+            Msg::Computing => write!(f, "計算中..."),
+            Msg::Fetching => write!(f, "取得中..."),
+            Msg::Size(size) => write!(f, "サイズ: {}", size),
+            Msg::Markers(markers) => write!(f, "マーカー: {}", markers),
+            Msg::Stashes(count) => write!(f, "スタッシュ: {} 件", count),
+            Msg::HealthTitle => write!(f, "健全性:"),
+            Msg::CommitAge(seconds) => {
+                let (amount, unit) = age(*seconds);
+                let unit = ["分", "時間", "日", "週間", "か月", "年"][unit];
+                write!(f, "{}{}前", amount, unit)
+            }
+            Msg::Dirty => write!(f, "変更あり"),
+            Msg::Clean => write!(f, "変更なし"),
+            Msg::HealthPullRequests(count) => write!(f, "PR {}", count),
+            Msg::Upstream { ahead, behind } => write!(
+                f,
+                "upstream: {} コミット先行 / {} コミット遅れ",
+                ahead, behind
+            ),
+            Msg::CommandName(name) => {
+                let text = match *name {
+                    "open" => "tmux で開く",
+                    "git" => "git の UI を開く",
+                    "window" => "新しいウィンドウで開く",
+                    "workspace" => "ワークスペースで開く",
+                    "files" => "ファイルマネージャー",
+                    "devcontainer" => "devcontainer で開く",
+                    "compose" => "compose up して開く",
+                    "new_window" => "端末の新しいウィンドウ",
+                    "tasks" => "タスク",
+                    "previous" => "前のプロジェクト",
+                    "find" => "全ルートから探す",
+                    "search" => "内容を検索",
+                    "filter" => "絞り込み",
+                    "jump" => "パスへ移動",
+                    "types" => "種類で絞り込み",
+                    "sort" => "並び順",
+                    "hidden" => "隠しディレクトリ",
+                    "refresh" => "詳細を更新",
+                    "reload" => "設定を再読み込み",
+                    "rename" => "名前を変更",
+                    "new_dir" => "ディレクトリを作成",
+                    "archive" => "アーカイブ",
+                    "trash" => "ゴミ箱へ",
+                    "kill_session" => "セッションを終了",
+                    "undo" => "元に戻す",
+                    "quit" => "終了",
+                    _ => name,
+                };
+                write!(f, "{}", text)
+            }
+            Msg::ChordGroup(name) => {
+                let text = match *name {
+                    "open" => "開く",
+                    "view" => "表示",
+                    "project" => "プロジェクト",
+                    _ => "…",
+                };
+                write!(f, "+{}", text)
+            }
+            Msg::DevcontainerAvailable => write!(f, "Devcontainer: あり [d]"),
+            Msg::Compose(status) => write!(f, "Compose: {} [c]", status),
+            Msg::ComposeUnknown => write!(f, "不明 (docker が使えません)"),
+            Msg::ComposeStopped => write!(f, "停止中"),
+            Msg::ComposeRunning { running, total } => write!(f, "{}/{} 稼働中", running, total),
+            Msg::OpenCounts {
+                pull_requests,
+                issues,
+            } => write!(f, "オープン: PR {} / Issue {}", pull_requests, issues),
+            Msg::OpenCountsFetching => write!(f, "オープン: 取得中..."),
+            Msg::LanguagesComputing => write!(f, "言語: 計算中..."),
+            Msg::LanguagesNone => write!(f, "言語: -"),
+            Msg::LanguagesTotal(lines) => write!(f, "言語: {} 行", lines),
+            Msg::LanguageRow { name, lines, files } => {
+                write!(f, "  {:<12} {:>8} 行 ({} ファイル)", name, lines, files)
+            }
+        }
+    }
+
+    fn en(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Msg::Opening(name) => write!(f, "Selected: {} -> starting tmux...", name),
+            Msg::OpeningWith { name, command } => {
+                write!(f, "Selected: {} -> running {}...", name, command)
+            }
+            Msg::OpeningDevcontainer(name) => {
+                write!(f, "Selected: {} -> starting devcontainer...", name)
+            }
+            Msg::OpeningCompose(name) => {
+                write!(f, "Selected: {} -> running docker compose up, then starting tmux...", name)
+            }
+            Msg::OpeningGit(name) => {
+                write!(f, "Selected: {} -> starting tmux with a git UI pane...", name)
+            }
+            Msg::OpeningFiles(name) => {
+                write!(f, "Selected: {} -> opening a file manager...", name)
+            }
+            Msg::OpeningWorkspace(name) => {
+                write!(f, "Selected: {} -> opening a terminal on the project workspace...", name)
+            }
+            Msg::OpeningNewWindow(name) => {
+                write!(f, "Selected: {} -> starting tmux in a new terminal window...", name)
+            }
+            Msg::OpeningWindow(name) => {
+                write!(f, "Selected: {} -> opening a new window in the current session...", name)
+            }
+            Msg::RunningTask { name, command } => {
+                write!(f, "Selected: {} -> running {} in a new window...", name, command)
+            }
+            Msg::ComposeDown => write!(f, "Session ended, running docker compose down..."),
+            Msg::NoMultiplexer => write!(f, "tmux or Windows Terminal not found, starting a shell instead"),
+            Msg::TmuxMissing(shell) => write!(
+                f,
+                "tmux not found: Enter starts {} ([g] [t] [K] are unavailable)",
+                shell
+            ),
+            Msg::SessionExists(name) => {
+                write!(f, "Session '{}' already exists. Attaching...", name)
+            }
+            Msg::Resurrected(name) => write!(f, "Recreated session '{}'", name),
+            Msg::AlreadyRunning(name) => write!(f, "Session '{}' already exists", name),
+            Msg::ResurrectMissing { name, dir } => {
+                write!(f, "Cannot recreate session '{}': {} does not exist", name, dir)
+            }
+            Msg::NoSavedSessions => write!(f, "No sessions have been recorded"),
+            Msg::NoRunningSessions => write!(f, "No sessions created by projector are running"),
+            Msg::TmuxResurrectPrompt(path) => {
+                write!(f, "Found a tmux-resurrect save ({}). Restore it first? [y/N] ", path)
+            }
+            Msg::RestoringTmuxResurrect => write!(f, "Restoring with tmux-resurrect..."),
+            Msg::DaemonListening(socket) => {
+                write!(f, "Listening on {} (Ctrl-C to stop)", socket)
+            }
+            Msg::Profile(timing) => write!(
+                f,
+                "[profile] {}: {:.1}ms ({} times)",
+                timing.label,
+                timing.total.as_secs_f64() * 1000.0,
+                timing.count
+            ),
+            Msg::LeftToTmuxResurrect(name) => {
+                write!(f, "Skipping session '{}': it is in the tmux-resurrect save", name)
+            }
+            Msg::AmbiguousName(name) => write!(f, "'{}' matches several projects:", name),
+            Msg::ChooseMatch(count) => write!(f, "Number to open [1-{}]: ", count),
+            Msg::DoctorNoConfig(path) => write!(f, "{} does not exist yet (using the defaults)", path),
+            Msg::DoctorUnknownVersion => write!(f, "could not read the version"),
+            Msg::DoctorReadable => write!(f, "readable"),
+            Msg::DoctorMissing => write!(f, "does not exist; create it or remove it from roots"),
+            Msg::DoctorUnreadable(error) => write!(f, "not readable: {}", error),
+            Msg::DoctorReachable => write!(f, "reachable over ssh"),
+            Msg::DoctorUnreachable(host) => write!(
+                f,
+                "cannot connect over ssh; check that ssh {} works without a password prompt",
+                host
+            ),
+            Msg::DoctorUnwritable { path, error } => {
+                write!(f, "cannot write to {}: {}", path, error)
+            }
+            Msg::DoctorBroken(path) => write!(f, "{} is corrupted; delete it to rebuild", path),
+            Msg::DoctorNoClipboard => {
+                write!(f, "no clipboard command (pbcopy / wl-copy / xclip / xsel)")
+            }
+            Msg::DoctorAllGood => write!(f, "No problems found"),
+            Msg::DoctorSummary { failures, warnings } => {
+                write!(f, "{} problem(s), {} warning(s)", failures, warnings)
+            }
+            Msg::HistoryVisits { name, count } => write!(f, "{:>5} cd  {}", count, name),
+            Msg::HistoryImported { imported, skipped } => write!(
+                f,
+                "Imported visit counts for {} project(s) ({} already had counts and were left as is)",
+                imported, skipped
+            ),
+            Msg::NoHistoryFiles => {
+                write!(f, "no shell history file found (pass the files to read)")
+            }
+            Msg::ExportHeader => write!(f, "| Path | Type | Last opened | Remote | Tags |"),
+            Msg::StateExported(path) => write!(f, "Wrote {}", path),
+            Msg::AliasConflict {
+                name,
+                local,
+                imported,
+            } => write!(
+                f,
+                "Kept alias {} pointing to {} (the import has {})",
+                name, local, imported
+            ),
+            Msg::StateImported {
+                aliases,
+                tags,
+                visits,
+                spans,
+            } => write!(
+                f,
+                "Imported {} alias(es), {} tag assignment(s), {} visit count(s) and {} time record(s)",
+                aliases, tags, visits, spans
+            ),
+            Msg::StatsWeek(date) => write!(f, "Week of {}", date),
+            Msg::StatsTotal => write!(f, "total"),
+            Msg::NoTimeTracked => write!(
+                f,
+                "Nothing recorded for this period (time is tracked for tmux sessions opened by projector)"
+            ),
+            Msg::Cancelled => write!(f, "Cancelled"),
+            Msg::Usage => write!(
+                f,
+                "Usage: projector [--picker tui|fzf|rofi|dmenu] [OPTIONS]
+       projector list [--format text|json|raycast|alfred]
+       projector open <NAME> [--detach | --print-attach] [--first | --all] [--dry-run]
+       projector last [--dry-run]
+       projector resurrect [<SESSION>...] [--dry-run]
+       projector kill <NAME> | --all [--dry-run]
+       projector doctor
+       projector import-history [<FILE>...] [--dry-run]
+       projector export [--format md|csv]
+       projector state export [<FILE>] | import <FILE> [--dry-run]
+       projector stats [<WEEKS>] [--format text|json]
+       projector daemon
+       projector serve --stdio [--msgpack]
+
+Commands:
+  list           Print the projects (never opens the TUI, even on a terminal)
+  open <NAME>    Open a project under a root or an alias by name (name/sub for nested directories)
+                 or by path; with --detach, create the session without attaching;
+                 with --print-attach, create it and print the command that attaches to it.
+                 Names not directly under a root are looked up deeper (org/name narrows it down);
+                 several matches are listed to choose from, or --first opens the first one
+                 and --all opens all of them without attaching
+  last           Re-open the previously opened project (toggles between the last two, like cd -)
+  resurrect      Recreate the tmux sessions projector has created, detached (e.g. after a reboot;
+                 only the named ones if given). Sessions in a tmux-resurrect save are left to it;
+                 on a terminal, offers to run its restore first
+  kill <NAME>    Kill the tmux sessions of a project (including the git UI one and the like). The name
+                 is looked up as in open, or taken as a session name if no project matches. With --all,
+                 kill every session projector has created
+  doctor         Check tmux, the config file, roots, caches and clipboard support, and print what
+                 to fix (handy to attach to bug reports)
+  import-history Count the projects under the roots that were cd-ed into in shell history
+                 ($HISTFILE, ~/.zsh_history and ~/.bash_history by default) and seed the visit
+                 counts that rank the all-projects list; projects that already have counts
+                 (e.g. ones opened before) are left as is
+  export         Print every project under the roots with its path, type, last opened date,
+                 origin URL and tags as a Markdown (default) or CSV table
+  state export   Write the aliases and tags from the config and the history (recent projects,
+                 visit counts, time records) as JSON (to stdout unless a file is given); paths
+                 under the home directory are written with ~/
+  state import   Merge a state export into the local one (- reads stdin). Nothing is overwritten:
+                 aliases that point elsewhere locally are kept, and the larger visit counts win
+  stats          Show the time spent in each project's tmux sessions per week (the last 4 weeks
+                 by default), counted from when projector opened a session to its last input;
+                 weeks start on Monday (UTC). Kept in the local state file only; end times are
+                 more accurate while the daemon runs
+  daemon         Stay resident and keep the project list in memory; while it runs, listings are
+                 answered by the daemon instead of scanning (socket: $XDG_RUNTIME_DIR/projector/daemon.sock)
+  serve --stdio  Answer JSON-RPC 2.0 on stdin/stdout for editor plugins (projects / query / open /
+                 metadata; one message per line or with Content-Length headers). With --msgpack,
+                 speak MessagePack-RPC for Neovim; an `open` notification fires the
+                 User ProjectorOpened autocommand once the session is ready
+
+Options:
+      --stdin    Read candidate paths from stdin (one per line) instead of the roots
+      --picker <tui|fzf|rofi|dmenu>
+                 How to pick a project (overrides `picker` in the config file). rofi / dmenu work
+                 without a terminal; the session is then created without attaching
+      --dry-run  Print the tmux and other commands instead of running them
+      --format <text|json|raycast|alfred|md|csv>
+                 With json, print the selected project as JSON instead of launching it
+                 (the TUI is drawn on stderr). raycast / alfred print the list for those launchers
+                 (each arg can be passed to open --detach)
+                 md / csv are the table formats of export
+      --profile  Print how long directory scanning, git metadata and the first render took (to stderr)
+      --color <auto|always|never>
+                 Colors and bold text in the TUI. auto follows NO_COLOR / CLICOLOR / CLICOLOR_FORCE
+                 and TERM
+      --ascii    Draw TUI markers and lines with ASCII only (same as `ascii` in the config file)
+  -v, --verbose  Write more detailed logs (-vv for even more)
+  -h, --help     Print this help
+
+Exit codes:
+  0  Success
+  1  Other error
+  2  Invalid arguments
+  3  Cancelled
+  4  No matching project
+  5  tmux or Windows Terminal failed
+  6  Invalid config or roots
+
+Environment:
+  PROJECTOR_CONFIG  Path to the config file (default: $XDG_CONFIG_HOME/projector/config.toml)
+  PROJECTOR_ROOT    Roots to use instead of the configured ones (`:`-separated, `;` on Windows; prefix with + to add)
+  XDG_CONFIG_HOME / XDG_CACHE_HOME / XDG_STATE_HOME
+                    Where config, cache and logs live (default: ~/.config ~/.cache ~/.local/state)
+
+When stdout is not a terminal, project paths are printed one per line instead of opening the TUI.
+Logs are written to $XDG_STATE_HOME/projector/log (default: ~/.local/state/projector/log)."
+            ),
+            Msg::Error(e) => write!(f, "error: {}", e),
+            Msg::WizardIntro(path) => write!(
+                f,
+                "No config file and no ~/Developer found. Creating {}.",
+                path
+            ),
+            Msg::WizardRoots => write!(f, "Directories containing your projects (comma-separated) [~/Developer]: "),
+            Msg::WizardCreate(root) => write!(f, "{} does not exist. Create it? [y/N]: ", root),
+            Msg::WizardBackend => write!(f, "How to open projects (auto/tmux/wt/iterm/ghostty/shell) [auto]: "),
+            Msg::WizardInvalid => write!(f, "invalid value"),
+            Msg::WizardSaved(path) => write!(f, "Saved {}", path),
+            Msg::ConfigReloaded => write!(f, "Config reloaded"),
+            Msg::SortedBy(SortOrder::Name) => write!(f, "Sorted by name"),
+            Msg::SortedBy(SortOrder::Modified) => write!(f, "Sorted by last modified"),
+            Msg::HiddenShown(true) => write!(f, "Showing hidden directories"),
+            Msg::HiddenShown(false) => write!(f, "Hiding hidden directories"),
+            Msg::NavigationHint => write!(
+                f,
+                "[↑↓] move  [Space] enter  [←/BS] back  [H/L] history  [M-Enter] open here"
+            ),
+            Msg::LeaderHint(key) => write!(f, "[{}] more keys", key),
+            Msg::NoSubdirectories => write!(f, "(no subdirectories)"),
+            Msg::ProjectBoundary(name) => {
+                write!(f, "{} is a project: Enter opens it, > enters it anyway", name)
+            }
+            Msg::MaxDepth(max) => write!(f, "cannot go any deeper (max_depth = {})", max),
+            Msg::Packages(kind) => write!(f, "({} packages)", kind),
+            Msg::NoMatches => write!(f, "(no matches)"),
+            Msg::FilterHint => write!(f, "[Ctrl-R] regex (re:)  [Esc] clear"),
+            Msg::InvalidRegex => write!(f, "(invalid regex)"),
+            Msg::JumpHint => write!(f, "[Tab] complete  [Enter] go  [Esc] cancel"),
+            Msg::NotADirectory(path) => {
+                write!(f, "not a directory: {} (use an absolute or ~/ path)", path)
+            }
+            Msg::RenameHint => write!(f, "[Enter] rename  [Esc] cancel"),
+            Msg::NewDirHint => write!(f, "[Enter] create  [Esc] cancel"),
+            Msg::Created(path) => write!(f, "Created {}", path),
+            Msg::InvalidName(name) => write!(f, "invalid name: {}", name),
+            Msg::Renamed { from, to } => write!(f, "Renamed {} to {} ([u] undo)", from, to),
+            Msg::Trashed(name) => write!(f, "Moved {} to the trash ([u] undo)", name),
+            Msg::Archived { name, to } => write!(f, "Moved {} to {} ([u] undo)", name, to),
+            Msg::Undone(name) => write!(f, "Restored {}", name),
+            Msg::NothingToUndo => write!(f, "nothing to undo"),
+            Msg::ConfirmTrash(name) => write!(f, "Move {} to the trash?", name),
+            Msg::ConfirmOpenPlain(name) => write!(
+                f,
+                "{} looks like a plain folder, not a project. Open anyway?",
+                name
+            ),
+            Msg::ConfirmKillSession(name) => write!(f, "Kill tmux session {}?", name),
+            Msg::ConfirmOverwrite(path) => {
+                write!(f, "{} already exists. Move it to the trash and replace it?", path)
+            }
+            Msg::Yes => write!(f, "Yes"),
+            Msg::No => write!(f, "No"),
+            Msg::ConfirmHint => write!(f, "[←→] choose  [Enter] confirm  [y/n]  [Esc] cancel"),
+            Msg::ErrorTitle => write!(f, "Error"),
+            Msg::ErrorHint => write!(f, "[Enter/Esc] close"),
+            Msg::SessionKilled(name) => write!(f, "Killed tmux session {}", name),
+            Msg::NoSession(name) => write!(f, "no tmux session named {}", name),
+            Msg::StdinCandidates => write!(f, "(candidates from stdin)"),
+            Msg::AllProjects => write!(f, "(projects in all roots)"),
+            Msg::Indexing => write!(f, "(looking for projects...)"),
+            Msg::Scanning(count) => {
+                write!(f, "looking for projects ({} directories)", count)
+            }
+            Msg::Searching => write!(f, "(searching...)"),
+            Msg::TasksTitle(name) => write!(f, "Tasks in {}", name),
+            Msg::TasksHint => write!(f, "[↑↓] move  [Enter] run  [Esc] back"),
+            Msg::NoTasks => write!(f, "no justfile / Makefile / package.json / Taskfile tasks"),
+            Msg::TypesTitle => write!(f, "Filter by type or tag"),
+            Msg::TypesHint => write!(f, "[↑↓] move  [Enter] select  [Esc] back"),
+            Msg::AllTypes => write!(f, "(all)"),
+            Msg::NoProjectTypes => write!(f, "no projects of a known type and no tags"),
+            Msg::SearchResults(query) => write!(f, "(projects containing \"{}\")", query),
+            Msg::SearchHint => write!(f, "[Enter] search with ripgrep  [Esc] cancel"),
+            Msg::DetailsTitle => write!(f, "Details"),
+            Msg::Remote(location) => write!(f, "Remote: {}", location),
+            Msg::Computing => write!(f, "calculating..."),
+            Msg::Fetching => write!(f, "fetching..."),
+            Msg::Size(size) => write!(f, "Size: {}", size),
+            Msg::Markers(markers) => write!(f, "Markers: {}", markers),
+            Msg::Stashes(count) => write!(f, "Stashes: {}", count),
+            Msg::HealthTitle => write!(f, "Health:"),
+            Msg::CommitAge(seconds) => {
+                let (amount, unit) = age(*seconds);
+                let unit = ["m", "h", "d", "w", "mo", "y"][unit];
+                write!(f, "{}{} ago", amount, unit)
+            }
+            Msg::Dirty => write!(f, "dirty"),
+            Msg::Clean => write!(f, "clean"),
+            Msg::HealthPullRequests(count) => write!(f, "{} PRs", count),
+            Msg::Upstream { ahead, behind } => {
+                write!(f, "Upstream: {} ahead / {} behind", ahead, behind)
+            }
+            Msg::CommandName(name) => {
+                let text = match *name {
+                    "open" => "open in tmux",
+                    "git" => "open git UI",
+                    "window" => "open in new window",
+                    "workspace" => "open in workspace",
+                    "files" => "file manager",
+                    "devcontainer" => "open in devcontainer",
+                    "compose" => "compose up and open",
+                    "new_window" => "new terminal window",
+                    "tasks" => "tasks",
+                    "previous" => "previous project",
+                    "find" => "find in all roots",
+                    "search" => "search contents",
+                    "filter" => "filter",
+                    "jump" => "jump to path",
+                    "types" => "filter by type",
+                    "sort" => "sort order",
+                    "hidden" => "hidden directories",
+                    "refresh" => "refresh details",
+                    "reload" => "reload config",
+                    "rename" => "rename",
+                    "new_dir" => "new directory",
+                    "archive" => "archive",
+                    "trash" => "move to trash",
+                    "kill_session" => "kill session",
+                    "undo" => "undo",
+                    "quit" => "quit",
+                    _ => name,
+                };
+                write!(f, "{}", text)
+            }
+            Msg::ChordGroup(name) => {
+                let text = match *name {
+                    "open" => "open",
+                    "view" => "view",
+                    "project" => "project",
+                    _ => "…",
+                };
+                write!(f, "+{}", text)
+            }
+            Msg::DevcontainerAvailable => write!(f, "Devcontainer: available [d]"),
+            Msg::Compose(status) => write!(f, "Compose: {} [c]", status),
+            Msg::ComposeUnknown => write!(f, "unknown (docker unavailable)"),
+            Msg::ComposeStopped => write!(f, "stopped"),
+            Msg::ComposeRunning { running, total } => write!(f, "{}/{} running", running, total),
+            Msg::OpenCounts {
+                pull_requests,
+                issues,
+            } => write!(f, "Open: PR {} / Issue {}", pull_requests, issues),
+            Msg::OpenCountsFetching => write!(f, "Open: fetching..."),
+            Msg::LanguagesComputing => write!(f, "Languages: calculating..."),
+            Msg::LanguagesNone => write!(f, "Languages: -"),
+            Msg::LanguagesTotal(lines) => write!(f, "Languages: {} lines", lines),
+            Msg::LanguageRow { name, lines, files } => {
+                write!(f, "  {:<12} {:>8} lines ({} files)", name, lines, files)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Msg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match lang() {
+            Lang::Ja => self.ja(f),
+            Lang::En => self.en(f),
+        }
+    }
+}
+
+/// 経過時間を一番大きい単位の数にする。単位は分・時間・日・週・月・年の順の添字
+fn age(seconds: u64) -> (u64, usize) {
+    const UNITS: [u64; 6] = [
+        60,
+        60 * 60,
+        24 * 60 * 60,
+        7 * 24 * 60 * 60,
+        30 * 24 * 60 * 60,
+        365 * 24 * 60 * 60,
+    ];
+    let unit = UNITS.iter().rposition(|&unit| seconds >= unit).unwrap_or(0);
+    (seconds / UNITS[unit], unit)
+}
@@ -0,0 +1,181 @@
+//! 全ルートにまたがるプロジェクトの一覧
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::{self, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tracing::warn;
+
+use crate::location::{self, Entry, Location};
+use crate::runner::CommandRunner;
+use crate::scan::{self, ScanOptions};
+
+/// [`walk`] で辿る深さの既定値（ルート直下が 1）
+pub const DEFAULT_DEPTH: usize = 5;
+
+/// 全ルート直下のディレクトリをルートの順にまとめて返す
+pub fn projects(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    options: &ScanOptions,
+) -> Vec<Entry> {
+    roots
+        .iter()
+        .flat_map(|root| root.entries(runner, options))
+        .collect()
+}
+
+/// 全ルートの下を辿り、ルート直下のディレクトリと、それより深い階層にあるプロジェクトをすべて返す
+///
+/// プロジェクトは [`ScanOptions::markers`] のどれかがあるディレクトリ。
+/// 名前はルートからの相対パス（`work/acme/api`）。プロジェクトの中と `depth` より深い階層には入らない。
+/// リモートのルートは時間がかかるので辿らない。
+pub fn walk(roots: &[Location], options: &ScanOptions, depth: usize) -> Vec<Entry> {
+    walk_counting(roots, options, depth, &AtomicUsize::new(0))
+}
+
+/// [`walk`] と同じ。読んだディレクトリの数を `scanned` に足していく（進み具合の表示用）
+pub fn walk_counting(
+    roots: &[Location],
+    options: &ScanOptions,
+    depth: usize,
+    scanned: &AtomicUsize,
+) -> Vec<Entry> {
+    let mut entries = vec![];
+    for root in roots {
+        if let Some(path) = root.local_path() {
+            walk_dir(root, path, options, 1, depth, scanned, &mut entries);
+        }
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+fn walk_dir(
+    root: &Location,
+    dir: &Path,
+    options: &ScanOptions,
+    level: usize,
+    depth: usize,
+    scanned: &AtomicUsize,
+    entries: &mut Vec<Entry>,
+) {
+    scanned.fetch_add(1, Ordering::Relaxed);
+    for sub in scan::get_directories(dir, options) {
+        let path = dir.join(&sub.name);
+        let project = options.is_project(&path);
+        if level == 1 || project {
+            let name = root
+                .local_path()
+                .and_then(|root| path.strip_prefix(root).ok())
+                .map(|relative| relative.to_string_lossy().to_string())
+                .unwrap_or_else(|| sub.name.to_string_lossy().to_string());
+            entries.push(Entry {
+                name,
+                location: Location::Local(path.clone()),
+                alias: false,
+                symlink: sub.symlink,
+            });
+        }
+        if !project && level < depth {
+            walk_dir(root, &path, options, level + 1, depth, scanned, entries);
+        }
+    }
+}
+
+/// 1行に1つずつ書かれたパス（`ssh://` 形式も可）を候補として読み込む。
+/// 存在しないローカルのディレクトリと重複は除く
+pub fn read_candidates(input: impl BufRead) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    for line in input.split(b'\n').map_while(Result::ok) {
+        let Some(location) = candidate(&line) else {
+            continue;
+        };
+        if entries.iter().any(|e| e.location == location) {
+            continue;
+        }
+        entries.push(Entry {
+            name: location.name(),
+            location,
+            alias: false,
+            symlink: false,
+        });
+    }
+    entries
+}
+
+/// 候補の1行を場所にする。UTF-8 でない行はローカルのパスとして読む
+fn candidate(line: &[u8]) -> Option<Location> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return None;
+    }
+    let location = match std::str::from_utf8(line) {
+        Ok(line) => Location::parse(line),
+        Err(_) => Location::Local(raw_path(line)?),
+    };
+    match location {
+        Location::Local(path) => match path::absolute(&path) {
+            Ok(path) if path.is_dir() => Some(Location::Local(path)),
+            _ => None,
+        },
+        remote => Some(remote),
+    }
+}
+
+#[cfg(unix)]
+fn raw_path(bytes: &[u8]) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(PathBuf::from(std::ffi::OsStr::from_bytes(bytes)))
+}
+
+// Unix 以外ではパスがバイト列とは限らないので読めない
+#[cfg(not(unix))]
+fn raw_path(bytes: &[u8]) -> Option<PathBuf> {
+    warn!(line = %String::from_utf8_lossy(bytes), "ignoring a candidate that is not UTF-8");
+    None
+}
+
+/// 設定のエイリアスを名前順のエントリにする。行き先が見つからないものは除く
+pub fn aliases(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    options: &ScanOptions,
+    aliases: &BTreeMap<String, String>,
+) -> Vec<Entry> {
+    aliases
+        .iter()
+        .filter_map(|(name, target)| {
+            let Some(location) = location::resolve(runner, roots, options, target) else {
+                warn!(alias = %name, target = %target, "alias target not found");
+                return None;
+            };
+            Some(Entry {
+                name: name.clone(),
+                location,
+                alias: true,
+                symlink: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_candidates_after_a_line_that_is_not_utf8() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut input = format!("{}\n", root.display()).into_bytes();
+        input.extend_from_slice(b"/no/such/dir\xff\n");
+        input.extend_from_slice(
+            format!("{}\nssh://host/~/app\n", root.join("src").display()).as_bytes(),
+        );
+
+        let entries = read_candidates(&input[..]);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["projector-core", "src", "app"]);
+    }
+}
@@ -0,0 +1,79 @@
+//! iTerm2 でプロジェクトを開く
+//!
+//! tmux を使わない iTerm2 では、セッションの代わりに新しいタブを開き、レイアウトのペインを
+//! 縦の分割で並べる。iTerm2 の AppleScript でタブとペインを作り、それぞれのシェルに
+//! `cd` とコマンドを入力する。リモートのプロジェクトはタブの中で `ssh -t` してリモートの
+//! tmux にアタッチする。
+
+use crate::macos;
+use crate::runner::{Cmd, CommandRunner};
+use crate::session::SessionSpec;
+use crate::shell;
+use crate::{Error, Result};
+
+const PROGRAM: &str = "osascript";
+
+/// 今のペインを縦に分割し、新しいペインを次の入力先にする
+const SPLIT: &str =
+    "tell projectorSession to set projectorSession to (split vertically with default profile)";
+
+/// 新しいタブを開く `osascript` の呼び出しを組み立てる
+pub fn open_command(host: Option<&str>, spec: &SessionSpec) -> Cmd {
+    let inputs = match host {
+        Some(host) => vec![format!(
+            "ssh -t {} {}",
+            shell::quote(host),
+            shell::quote(&format!("sh -c {}", shell::quote(&spec.remote_script())))
+        )],
+        None => {
+            let cd = format!("cd {}", shell::quote(&spec.dir.to_string_lossy()));
+            spec.panes
+                .iter()
+                .map(|command| match command {
+                    Some(command) => format!("{} && {}", cd, command),
+                    None => cd.clone(),
+                })
+                .collect()
+        }
+    };
+
+    let mut script = vec![
+        "tell application \"iTerm\"".to_string(),
+        "activate".to_string(),
+        // ウィンドウが無ければタブの代わりにウィンドウを作る
+        "if (count of windows) = 0 then".to_string(),
+        "create window with default profile".to_string(),
+        "else".to_string(),
+        "tell current window to create tab with default profile".to_string(),
+        "end if".to_string(),
+        "set projectorSession to current session of current window".to_string(),
+        format!(
+            "tell projectorSession to set name to {}",
+            macos::quote(&spec.name)
+        ),
+    ];
+    for (i, input) in inputs.iter().enumerate() {
+        if i > 0 {
+            script.push(SPLIT.to_string());
+        }
+        script.push(format!(
+            "tell projectorSession to write text {}",
+            macos::quote(input)
+        ));
+    }
+    script.push("end tell".to_string());
+
+    script
+        .into_iter()
+        .fold(Cmd::new(PROGRAM), |cmd, line| cmd.arg("-e").arg(line))
+}
+
+/// iTerm2 の新しいタブでプロジェクトを開く
+pub fn open(runner: &dyn CommandRunner, host: Option<&str>, spec: &SessionSpec) -> Result<()> {
+    let cmd = open_command(host, spec);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn(PROGRAM, e))?;
+    if !output.success {
+        return Err(Error::CommandFailed(PROGRAM.to_string()));
+    }
+    Ok(())
+}
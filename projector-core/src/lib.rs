@@ -0,0 +1,64 @@
+//! projector のコアロジック
+//!
+//! ディレクトリの走査、設定の読み込み、tmux セッションの組み立て、プロジェクトの統計や
+//! GitHub / GitLab / Gitea との連携など、TUI に依存しない部分をまとめたライブラリ。
+//! エディタのプラグインなど別のフロントエンドからも利用できる。
+//!
+//! ```no_run
+//! use projector_core::location::Location;
+//! use projector_core::runner::SystemRunner;
+//! use projector_core::session::{self, SessionSpec};
+//!
+//! let project = Location::parse("~/Developer/projector");
+//! let spec = SessionSpec::new(&project);
+//! session::start_local(&SystemRunner, &spec).unwrap();
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod bundle;
+pub mod cache;
+pub mod compose;
+pub mod config;
+pub mod daemon;
+pub mod devcontainer;
+pub mod error;
+pub mod fallback;
+pub mod forge;
+pub mod fzf;
+pub mod ghostty;
+pub mod git;
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+pub mod history;
+pub mod http;
+pub mod i18n;
+pub mod index;
+pub mod iterm;
+pub mod location;
+pub mod macos;
+pub mod matcher;
+pub mod menu;
+pub mod migrate;
+pub mod monorepo;
+pub mod nix;
+pub mod paths;
+pub mod profile;
+pub mod project_type;
+pub mod runner;
+pub mod scan;
+pub mod search;
+pub mod session;
+pub mod shell;
+pub mod state;
+pub mod stats;
+pub mod tasks;
+pub mod template;
+pub mod timelog;
+pub mod tmux_resurrect;
+pub mod trash;
+pub mod wm;
+pub mod wt;
+
+pub use error::{Error, ExitCode, Result};
@@ -0,0 +1,402 @@
+//! ローカル・リモートのディレクトリの表現
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::SymlinkPolicy;
+use crate::index;
+use crate::paths;
+use crate::runner::{Cmd, CommandRunner};
+use crate::scan::{self, Directory, ScanOptions};
+use crate::shell;
+
+/// ブラウズ対象のディレクトリ。ローカルか、ssh で到達できるホスト上のもの
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// ローカルのディレクトリ
+    Local(PathBuf),
+    /// ssh で到達できるホスト上のディレクトリ
+    Remote {
+        /// ssh の接続先
+        host: String,
+        /// リモートのシェルで解釈するパス（`~` を含んでよい）
+        path: String,
+    },
+}
+
+/// 一覧の1行分
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// 表示名（ディレクトリ名。UTF-8 でない部分は置き換える）
+    pub name: String,
+    /// エントリの場所
+    pub location: Location,
+    /// 設定のエイリアスで、`name` がその別名
+    pub alias: bool,
+    /// シンボリックリンクとして印を付ける
+    pub symlink: bool,
+}
+
+impl Location {
+    /// ルートの指定を解釈する。`ssh://host/~/dir` や `ssh://host/srv/dir` はリモート、それ以外はローカル
+    pub fn parse(spec: &str) -> Location {
+        if let Some(rest) = spec.strip_prefix("ssh://") {
+            let (host, path) = rest.split_once('/').unwrap_or((rest, "~"));
+            let path = if path.is_empty() || path.starts_with('~') {
+                format!("~{}", path.trim_start_matches('~'))
+            } else {
+                format!("/{}", path)
+            };
+            return Location::Remote {
+                host: host.to_string(),
+                path,
+            };
+        }
+        Location::Local(expand_tilde(spec))
+    }
+
+    /// サブディレクトリの場所
+    pub fn join(&self, name: impl AsRef<OsStr>) -> Location {
+        let name = name.as_ref();
+        match self {
+            Location::Local(path) => Location::Local(path.join(name)),
+            Location::Remote { host, path } => Location::Remote {
+                host: host.clone(),
+                path: format!("{}/{}", path.trim_end_matches('/'), name.to_string_lossy()),
+            },
+        }
+    }
+
+    /// 末尾のディレクトリ名
+    pub fn name(&self) -> String {
+        match self {
+            Location::Local(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Location::Remote { path, .. } => {
+                path.rsplit('/').next().unwrap_or_default().to_string()
+            }
+        }
+    }
+
+    /// ディレクトリ名が UTF-8 でなく、表示名では一部を置き換えているか
+    pub fn has_lossy_name(&self) -> bool {
+        match self {
+            Location::Local(path) => path.file_name().is_some_and(|n| n.to_str().is_none()),
+            Location::Remote { .. } => false,
+        }
+    }
+
+    /// 表示用の文字列（ローカルは `~/` 形式、リモートは `host:path`）
+    pub fn display(&self) -> String {
+        match self {
+            Location::Local(path) => paths::shorten_path(path),
+            Location::Remote { host, path } => format!("{}:{}", host, path),
+        }
+    }
+
+    /// [`Location::parse`] で元に戻せる文字列（リモートは `ssh://host/path`）
+    pub fn spec(&self) -> String {
+        match self {
+            Location::Local(path) => path.to_string_lossy().to_string(),
+            Location::Remote { host, path } if path.starts_with('/') => {
+                format!("ssh://{}{}", host, path)
+            }
+            Location::Remote { host, path } => format!("ssh://{}/{}", host, path),
+        }
+    }
+
+    /// パスの文字列（リモートは `~` を含んだまま）
+    pub fn path_str(&self) -> String {
+        match self {
+            Location::Local(path) => path.to_string_lossy().to_string(),
+            Location::Remote { path, .. } => path.clone(),
+        }
+    }
+
+    /// 親ディレクトリの場所
+    pub fn parent(&self) -> Option<Location> {
+        match self {
+            Location::Local(path) => path.parent().map(|p| Location::Local(p.to_path_buf())),
+            Location::Remote { host, path } => {
+                let (parent, _) = path.trim_end_matches('/').rsplit_once('/')?;
+                Some(Location::Remote {
+                    host: host.clone(),
+                    path: if parent.is_empty() {
+                        "/".to_string()
+                    } else {
+                        parent.to_string()
+                    },
+                })
+            }
+        }
+    }
+
+    /// `other` がこの場所かその下にあるか
+    pub fn contains(&self, other: &Location) -> bool {
+        match (self, other) {
+            (Location::Local(root), Location::Local(path)) => path.starts_with(root),
+            (
+                Location::Remote { host, path: root },
+                Location::Remote {
+                    host: other_host,
+                    path,
+                },
+            ) => host == other_host && Path::new(path).starts_with(root),
+            _ => false,
+        }
+    }
+
+    /// ローカルならそのパス
+    pub fn local_path(&self) -> Option<&Path> {
+        match self {
+            Location::Local(path) => Some(path),
+            Location::Remote { .. } => None,
+        }
+    }
+
+    /// リモートならホスト名
+    pub fn host(&self) -> Option<&str> {
+        match self {
+            Location::Local(_) => None,
+            Location::Remote { host, .. } => Some(host),
+        }
+    }
+
+    /// 隠しディレクトリと ignore に当てはまるものを除いたサブディレクトリを返す
+    pub fn list_directories(
+        &self,
+        runner: &dyn CommandRunner,
+        options: &ScanOptions,
+    ) -> Vec<Directory> {
+        match self {
+            Location::Local(path) => scan::get_directories(path, options),
+            Location::Remote { host, path } => list_remote_directories(runner, host, path, options),
+        }
+    }
+
+    /// サブディレクトリを一覧のエントリとして返す
+    pub fn entries(&self, runner: &dyn CommandRunner, options: &ScanOptions) -> Vec<Entry> {
+        self.list_directories(runner, options)
+            .into_iter()
+            .map(|dir| self.entry(dir))
+            .collect()
+    }
+
+    /// 直下のディレクトリを一覧のエントリにする
+    pub fn entry(&self, dir: Directory) -> Entry {
+        Entry {
+            location: self.join(&dir.name),
+            name: dir.name.to_string_lossy().to_string(),
+            alias: false,
+            symlink: dir.symlink,
+        }
+    }
+}
+
+/// 先頭の `~` をホームディレクトリに展開する（Windows の `~\` も受け付ける）
+pub fn expand_tilde(path: &str) -> PathBuf {
+    if let Some(home) = dirs::home_dir() {
+        if path == "~" {
+            return home;
+        }
+        if let Some(rest) = path
+            .strip_prefix("~/")
+            .or_else(|| path.strip_prefix("~\\").filter(|_| cfg!(windows)))
+        {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// 入力途中のローカルのパス（`~/` で始まってもよい）をディレクトリ名で補完する
+///
+/// 最後の `/` より後ろを、前方一致するディレクトリ名に共通する部分まで伸ばす。
+/// 1つに決まれば末尾に `/` を付ける。隠しディレクトリは `.` を入力したときだけ候補にする。
+pub fn complete_path(input: &str) -> String {
+    if input == "~" {
+        return "~/".to_string();
+    }
+    let Some(slash) = input.rfind('/') else {
+        return input.to_string();
+    };
+    let (dir, prefix) = input.split_at(slash + 1);
+    let Ok(entries) = fs::read_dir(expand_tilde(dir)) else {
+        return input.to_string();
+    };
+    let names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.'))
+        })
+        .collect();
+
+    match names.as_slice() {
+        [] => input.to_string(),
+        [name] => format!("{}{}/", dir, name),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.as_str(), |common, name| {
+                let len = common
+                    .char_indices()
+                    .zip(name.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, c), _)| i + c.len_utf8());
+                &common[..len]
+            });
+            format!("{}{}", dir, common)
+        }
+    }
+}
+
+/// ルート直下から名前でプロジェクトを探す。`name/sub` のように下の階層も指定できる
+pub fn find(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    options: &ScanOptions,
+    name: &str,
+) -> Option<Location> {
+    roots
+        .iter()
+        .find_map(|root| find_in(runner, root, options, name))
+}
+
+/// 名前に当てはまるプロジェクトをすべて探す
+///
+/// まずどのルートからも [`find`] と同じように相対パスとして探す。どこにも無ければ全ルートの下を
+/// [`index::walk`] で辿り、ルートからの相対パスの末尾が名前と一致するもの
+/// （`api` や `acme/api` なら `work/acme/api`）を集める。
+pub fn find_all(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    options: &ScanOptions,
+    depth: usize,
+    name: &str,
+) -> Vec<Location> {
+    let found: Vec<Location> = roots
+        .iter()
+        .filter_map(|root| find_in(runner, root, options, name))
+        .collect();
+    if !found.is_empty() {
+        return found;
+    }
+    let name = name.trim_matches('/');
+    if name.is_empty() {
+        return vec![];
+    }
+    let suffix = format!("/{}", name);
+    index::walk(roots, options, depth)
+        .into_iter()
+        .filter(|entry| entry.name == name || entry.name.ends_with(&suffix))
+        .map(|entry| entry.location)
+        .collect()
+}
+
+/// `root` の下から `name/sub` の形の相対パスのディレクトリを探す
+fn find_in(
+    runner: &dyn CommandRunner,
+    root: &Location,
+    options: &ScanOptions,
+    name: &str,
+) -> Option<Location> {
+    let contains = |location: &Location, part: &str| {
+        location
+            .list_directories(runner, options)
+            .iter()
+            .any(|dir| dir.name == part)
+    };
+    let mut parts = name.trim_matches('/').split('/');
+    let first = parts.next().filter(|part| !part.is_empty())?;
+    if !contains(root, first) {
+        return None;
+    }
+    let mut location = root.join(first);
+    for part in parts {
+        if !contains(&location, part) {
+            return None;
+        }
+        location = location.join(part);
+    }
+    Some(location)
+}
+
+/// ルートからの相対パスではなく、場所そのものの指定（`/` `~` `ssh://` で始まる）か
+pub fn is_path(target: &str) -> bool {
+    target.starts_with(['/', '~']) || target.starts_with("ssh://")
+}
+
+/// エイリアスの値を場所に解決する。`/` `~` `ssh://` で始まればその場所、
+/// それ以外はルートからの相対パスとして [`find`] で探す
+pub fn resolve(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    options: &ScanOptions,
+    target: &str,
+) -> Option<Location> {
+    if !is_path(target) {
+        return find(runner, roots, options, target);
+    }
+    match Location::parse(target) {
+        Location::Local(path) if !path.is_dir() => None,
+        location => Some(location),
+    }
+}
+
+/// リモートのサブディレクトリ。リンク先の循環は検出せず、`.projector/config.toml` も読まない
+fn list_remote_directories(
+    runner: &dyn CommandRunner,
+    host: &str,
+    path: &str,
+    options: &ScanOptions,
+) -> Vec<Directory> {
+    // ログインシェルが fish などでも動くよう sh で実行する。
+    // シンボリックリンクは `l`、それ以外は `d` を先頭に付けて返す
+    let pattern = if options.hidden { "*/ .*/" } else { "*/" };
+    let script = format!(
+        "cd {} && for d in {}; do d=\"${{d%/}}\"; [ -d \"$d\" ] || continue; \
+         if [ -L \"$d\" ]; then printf 'l %s\\n' \"$d\"; else printf 'd %s\\n' \"$d\"; fi; done",
+        shell::quote_path(path),
+        pattern
+    );
+    let cmd = Cmd::new("ssh")
+        .args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=5", host])
+        .arg(format!("sh -c {}", shell::quote(&script)))
+        .read_only();
+
+    let Ok(output) = runner.output(&cmd) else {
+        return vec![];
+    };
+    let ignore = options.ignore_set();
+    let mut dirs: Vec<Directory> = output
+        .stdout_str()
+        .lines()
+        .filter_map(|line| {
+            let (kind, name) = line.split_once(' ')?;
+            let symlink = kind == "l";
+            if name.is_empty() || name == "." || name == ".." {
+                return None;
+            }
+            if name.starts_with('.') && !options.hidden {
+                return None;
+            }
+            if scan::is_ignored(&ignore, name.as_ref()) {
+                return None;
+            }
+            if symlink && options.symlinks == SymlinkPolicy::Skip {
+                return None;
+            }
+            Some(Directory {
+                name: name.into(),
+                symlink: symlink && options.symlinks == SymlinkPolicy::Mark,
+                modified: None,
+            })
+        })
+        .collect();
+    options.sort(&mut dirs);
+    dirs
+}
@@ -0,0 +1,51 @@
+//! macOS の端末アプリ（Terminal.app / iTerm2）の新しいウィンドウで開く
+//!
+//! 今の端末を使わずに、AppleScript（`osascript`）で端末アプリに新しいウィンドウを開かせ、
+//! そこでコマンドを実行する。コマンドはウィンドウのログインシェルに入力されたものとして動く。
+
+use crate::config::MacTerminal;
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+const PROGRAM: &str = "osascript";
+
+/// 新しいウィンドウを開いて `command` を実行する `osascript` の呼び出しを組み立てる
+pub fn open_window_command(terminal: MacTerminal, command: &str) -> Cmd {
+    let command = quote(command);
+    let script = match terminal.resolve() {
+        MacTerminal::Iterm => vec![
+            "tell application \"iTerm\"".to_string(),
+            "activate".to_string(),
+            "set projectorWindow to (create window with default profile)".to_string(),
+            format!(
+                "tell current session of projectorWindow to write text {}",
+                command
+            ),
+            "end tell".to_string(),
+        ],
+        MacTerminal::Auto | MacTerminal::Terminal => vec![
+            "tell application \"Terminal\"".to_string(),
+            "activate".to_string(),
+            format!("do script {}", command),
+            "end tell".to_string(),
+        ],
+    };
+    script
+        .into_iter()
+        .fold(Cmd::new(PROGRAM), |cmd, line| cmd.arg("-e").arg(line))
+}
+
+/// 端末アプリの新しいウィンドウで `command` を実行する
+pub fn open_window(runner: &dyn CommandRunner, terminal: MacTerminal, command: &str) -> Result<()> {
+    let cmd = open_window_command(terminal, command);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn(PROGRAM, e))?;
+    if !output.success {
+        return Err(Error::CommandFailed(PROGRAM.to_string()));
+    }
+    Ok(())
+}
+
+/// AppleScript の文字列として引用する
+pub(crate) fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
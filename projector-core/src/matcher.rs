@@ -0,0 +1,157 @@
+//! あいまい検索による絞り込み
+//!
+//! nucleo のスコアで並べるので、`prj` は `projector` が名前順で前にある候補より上に来る。
+//! 検索語が `re:` で始まるときは残りを正規表現として扱い、一致した候補を元の順で返す。
+//! 大文字と小文字は、既定では検索語に大文字があるときだけ区別する（smart case）。
+
+use std::cmp::Reverse;
+
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+use regex::{Regex, RegexBuilder};
+
+use crate::config::CaseMode;
+
+/// 正規表現で絞り込むときの検索語の接頭辞
+pub const REGEX_PREFIX: &str = "re:";
+
+/// 候補を検索語で絞り込んで並べ替える
+pub struct FuzzyMatcher {
+    matcher: Matcher,
+    case: CaseMode,
+    /// 最後にコンパイルした正規表現（キー入力のたびに作り直さない）
+    regex: Option<(String, Option<Regex>)>,
+}
+
+impl FuzzyMatcher {
+    /// マッチャーを作る
+    pub fn new() -> Self {
+        FuzzyMatcher {
+            matcher: Matcher::new(Config::DEFAULT),
+            case: CaseMode::default(),
+            regex: None,
+        }
+    }
+
+    /// 大文字と小文字の区別のしかたを変える
+    pub fn set_case(&mut self, case: CaseMode) {
+        self.case = case;
+        self.regex = None;
+    }
+
+    /// あいまい検索での大文字と小文字の扱い
+    fn case_matching(&self) -> CaseMatching {
+        match self.case {
+            CaseMode::Smart => CaseMatching::Smart,
+            CaseMode::Sensitive => CaseMatching::Respect,
+            CaseMode::Insensitive => CaseMatching::Ignore,
+        }
+    }
+
+    /// 検索語が正規表現で、コンパイルできないか
+    pub fn is_invalid(&mut self, query: &str) -> bool {
+        query
+            .strip_prefix(REGEX_PREFIX)
+            .is_some_and(|pattern| self.compile(pattern).is_none())
+    }
+
+    /// 正規表現をコンパイルする。不正なら None
+    fn compile(&mut self, pattern: &str) -> Option<&Regex> {
+        if self
+            .regex
+            .as_ref()
+            .is_none_or(|(cached, _)| cached != pattern)
+        {
+            let insensitive = match self.case {
+                CaseMode::Smart => !has_uppercase_literal(pattern),
+                CaseMode::Sensitive => false,
+                CaseMode::Insensitive => true,
+            };
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(insensitive)
+                .build()
+                .ok();
+            self.regex = Some((pattern.to_string(), regex));
+        }
+        self.regex.as_ref().and_then(|(_, regex)| regex.as_ref())
+    }
+
+    /// 検索語にマッチする候補の位置をスコアの高い順に返す。同点なら元の順を保つ
+    pub fn rank<S: AsRef<str>>(&mut self, query: &str, candidates: &[S]) -> Vec<usize> {
+        if query.is_empty() {
+            return (0..candidates.len()).collect();
+        }
+        if let Some(pattern) = query.strip_prefix(REGEX_PREFIX) {
+            let Some(regex) = self.compile(pattern) else {
+                return vec![];
+            };
+            return (0..candidates.len())
+                .filter(|&i| regex.is_match(candidates[i].as_ref()))
+                .collect();
+        }
+        let pattern = Pattern::parse(query, self.case_matching(), Normalization::Smart);
+
+        let mut buf = vec![];
+        let mut scored: Vec<(usize, u32)> = candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, candidate)| {
+                let haystack = Utf32Str::new(candidate.as_ref(), &mut buf);
+                pattern
+                    .score(haystack, &mut self.matcher)
+                    .map(|score| (i, score))
+            })
+            .collect();
+        scored.sort_by_key(|&(i, score)| (Reverse(score), i));
+        scored.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// 候補の中で検索語にマッチした文字の位置（文字単位、昇順）。マッチしなければ空
+    pub fn indices(&mut self, query: &str, candidate: &str) -> Vec<usize> {
+        if query.is_empty() {
+            return vec![];
+        }
+        if let Some(pattern) = query.strip_prefix(REGEX_PREFIX) {
+            let Some(regex) = self.compile(pattern) else {
+                return vec![];
+            };
+            // 一致した範囲のバイト位置を文字の位置に直す
+            let mut indices = vec![];
+            for m in regex.find_iter(candidate) {
+                indices.extend(
+                    candidate
+                        .char_indices()
+                        .enumerate()
+                        .filter(|(_, (byte, _))| m.range().contains(byte))
+                        .map(|(i, _)| i),
+                );
+            }
+            return indices;
+        }
+        let pattern = Pattern::parse(query, self.case_matching(), Normalization::Smart);
+        let mut buf = vec![];
+        let mut indices = vec![];
+        let haystack = Utf32Str::new(candidate, &mut buf);
+        pattern.indices(haystack, &mut self.matcher, &mut indices);
+        // 空白区切りの語ごとに位置が追加されるので並べ直す
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|i| i as usize).collect()
+    }
+}
+
+/// 正規表現に大文字の文字があるか。`\W` `\S` のようなエスケープは数えない
+fn has_uppercase_literal(pattern: &str) -> bool {
+    let mut escaped = false;
+    pattern.chars().any(|c| {
+        let literal = !escaped && c.is_uppercase();
+        escaped = !escaped && c == '\\';
+        literal
+    })
+}
+
+impl Default for FuzzyMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,38 @@
+//! rofi や dmenu 互換のメニューで候補を選ぶ
+//!
+//! 候補を1行ずつ標準入力に渡し、選ばれた行を標準出力から受け取る。端末を使わないので、
+//! ウィンドウマネージャーのキーに割り当てて使える。
+
+use crate::location::Entry;
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+/// `--picker rofi` で起動するコマンド
+pub const ROFI: &str = "rofi -dmenu -i -p projector";
+
+// 何も選ばずに閉じたとき（Esc）
+const EXIT_CANCELLED: i32 = 1;
+
+/// 候補をメニューに渡し、選ばれたエントリの位置を返す。キャンセルされたら None
+///
+/// `command` は `wofi --dmenu` のような引数付きの指定も受け付ける。
+pub fn pick(runner: &dyn CommandRunner, command: &str, entries: &[Entry]) -> Result<Option<usize>> {
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("dmenu");
+    let candidates: Vec<String> = entries.iter().map(|e| e.location.display()).collect();
+    let cmd = Cmd::new(program)
+        .args(words)
+        .stdin(candidates.join("\n"))
+        .read_only();
+    let output = runner.output(&cmd).map_err(|e| Error::spawn(program, e))?;
+
+    if !output.success {
+        return match output.code {
+            Some(EXIT_CANCELLED) => Ok(None),
+            _ => Err(Error::CommandFailed(program.to_string())),
+        };
+    }
+    let selected = output.stdout_str();
+    let selected = selected.trim_end_matches('\n');
+    Ok(candidates.iter().position(|c| c == selected))
+}
@@ -0,0 +1,144 @@
+//! 状態ファイルと設定ファイルの形式のバージョンと、古い形式からの移行
+//!
+//! どちらのファイルも先頭の `version` に形式のバージョンを書く（書いていなければ 0）。
+//! 読み込むときは、型に読み込む前の JSON / TOML の値に古い順に移行を当ててから読むので、
+//! キーの名前や形を変えても古いファイルの値を失わない。形式を変えるときは、バージョンを上げて
+//! 移行を1つ足す。このバージョンより新しいファイルは、知らないキーを落とさないよう書き戻さない。
+
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+
+/// 状態ファイル（state.json）の形式のバージョン
+pub const STATE_VERSION: u32 = 1;
+
+/// 設定ファイル（config.toml）の形式のバージョン
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 1つの移行。値を変えたら true
+type Migration<T> = fn(&mut T) -> bool;
+
+/// 状態ファイルの移行。`i` 番目がバージョン `i` から `i + 1` への移行
+const STATE_MIGRATIONS: &[Migration<Map<String, Value>>] = &[
+    // 0: バージョンを書く前の形式。中身は 1 と同じ
+    |_| false,
+];
+
+/// 設定ファイルの移行。`i` 番目がバージョン `i` から `i + 1` への移行
+const CONFIG_MIGRATIONS: &[Migration<toml::Table>] = &[
+    // 0: バージョンを書く前の形式。中身は 1 と同じ
+    |_| false,
+];
+
+/// 移行の結果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// 今のバージョンの形式だった（移行しても値が変わらなかった）
+    Current,
+    /// 古い形式から今の形式に移した
+    Migrated,
+    /// このバージョンより新しい形式。知らない値は読めない
+    Newer(u32),
+}
+
+/// 読み込んだ状態ファイルの値を今の形式にする。`version` は取り除く
+pub fn state(value: &mut Value) -> Outcome {
+    let Some(map) = value.as_object_mut() else {
+        return Outcome::Current;
+    };
+    let version = map
+        .remove("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0) as u32;
+    run("state", version, STATE_VERSION, STATE_MIGRATIONS, map)
+}
+
+/// 書き出す状態ファイルの値に今のバージョンを書く
+pub fn stamp_state(value: &mut Value) {
+    if let Some(map) = value.as_object_mut() {
+        map.insert("version".to_string(), Value::from(STATE_VERSION));
+    }
+}
+
+/// 読み込んだ設定ファイルの値を今の形式にする
+pub fn config(table: &mut toml::Table) -> Outcome {
+    let version = table
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or(0)
+        .clamp(0, i64::from(u32::MAX)) as u32;
+    let outcome = run("config", version, CONFIG_VERSION, CONFIG_MIGRATIONS, table);
+    if outcome == Outcome::Migrated {
+        table.insert(
+            "version".to_string(),
+            toml::Value::Integer(i64::from(CONFIG_VERSION)),
+        );
+    }
+    outcome
+}
+
+fn run<T>(
+    file: &str,
+    version: u32,
+    current: u32,
+    migrations: &[Migration<T>],
+    value: &mut T,
+) -> Outcome {
+    if version > current {
+        warn!(file, version, current, "written by a newer projector");
+        return Outcome::Newer(version);
+    }
+    let mut changed = false;
+    for (from, migrate) in migrations.iter().enumerate().skip(version as usize) {
+        if migrate(value) {
+            info!(file, from, to = from + 1, "migrated to a newer format");
+            changed = true;
+        }
+    }
+    if changed {
+        Outcome::Migrated
+    } else {
+        Outcome::Current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_migrations_from_the_file_version() {
+        let migrations: &[Migration<Vec<u32>>] = &[
+            |v| {
+                v.push(0);
+                true
+            },
+            |v| {
+                v.push(1);
+                true
+            },
+        ];
+        let mut applied = vec![];
+        assert_eq!(
+            run("test", 1, 2, migrations, &mut applied),
+            Outcome::Migrated
+        );
+        assert_eq!(applied, [1]);
+
+        let mut applied = vec![];
+        assert_eq!(
+            run("test", 2, 2, migrations, &mut applied),
+            Outcome::Current
+        );
+        assert_eq!(
+            run("test", 3, 2, migrations, &mut applied),
+            Outcome::Newer(3)
+        );
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(STATE_MIGRATIONS.len(), STATE_VERSION as usize);
+        assert_eq!(CONFIG_MIGRATIONS.len(), CONFIG_VERSION as usize);
+    }
+}
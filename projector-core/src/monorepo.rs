@@ -0,0 +1,357 @@
+//! モノレポ（cargo workspace / pnpm・yarn・npm workspaces / Nx）の検出
+//!
+//! マニフェストに書かれたメンバーのパターンを読み、リポジトリの下のパッケージを並べる。
+//! ツールを起動せずにファイルを読むだけなので、凝った書き方のものは拾えないことがある。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde_json::Value;
+use tracing::warn;
+
+/// パッケージを探す深さ（リポジトリ直下が 1）
+const MAX_DEPTH: usize = 4;
+
+/// パッケージを探すときに入らないディレクトリ
+const SKIP_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "vendor"];
+
+/// モノレポの種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MonorepoKind {
+    /// Cargo.toml の `[workspace]`
+    Cargo,
+    /// pnpm-workspace.yaml
+    Pnpm,
+    /// package.json の `workspaces`（yarn / npm）
+    Npm,
+    /// nx.json（`project.json` のあるディレクトリがプロジェクト）
+    Nx,
+}
+
+impl MonorepoKind {
+    /// 表示用の名前
+    pub fn label(self) -> &'static str {
+        match self {
+            MonorepoKind::Cargo => "cargo workspace",
+            MonorepoKind::Pnpm => "pnpm workspace",
+            MonorepoKind::Npm => "workspaces",
+            MonorepoKind::Nx => "nx",
+        }
+    }
+}
+
+/// 検出したモノレポ
+#[derive(Debug)]
+pub struct Monorepo {
+    /// 種類
+    pub kind: MonorepoKind,
+    /// リポジトリのディレクトリ
+    pub dir: PathBuf,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl Monorepo {
+    /// `dir` がモノレポのルートなら検出する
+    pub fn detect(dir: &Path) -> Option<Monorepo> {
+        let (kind, include, exclude) = if let Some((include, exclude)) = cargo_members(dir) {
+            (MonorepoKind::Cargo, include, exclude)
+        } else if let Some(include) = pnpm_packages(dir) {
+            (MonorepoKind::Pnpm, include, vec![])
+        } else if let Some(include) = npm_workspaces(dir) {
+            (MonorepoKind::Npm, include, vec![])
+        } else if dir.join("nx.json").is_file() {
+            (MonorepoKind::Nx, vec![], vec![])
+        } else {
+            return None;
+        };
+        // pnpm などは `!` で始まるパターンで除外する
+        let (negated, include): (Vec<String>, Vec<String>) =
+            include.into_iter().partition(|p| p.starts_with('!'));
+        let exclude = exclude
+            .into_iter()
+            .chain(negated.into_iter().map(|p| p[1..].to_string()));
+        Some(Monorepo {
+            kind,
+            dir: dir.to_path_buf(),
+            include: glob_set(include),
+            exclude: glob_set(exclude),
+        })
+    }
+
+    /// `path` がこのモノレポのパッケージか
+    pub fn contains(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.dir) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        match self.kind {
+            MonorepoKind::Nx => path.join("project.json").is_file(),
+            _ => self.include.is_match(relative) && !self.exclude.is_match(relative),
+        }
+    }
+
+    /// パッケージのディレクトリ（パスの順）
+    pub fn members(&self) -> Vec<PathBuf> {
+        let mut members = vec![];
+        self.collect(&self.dir, 1, &mut members);
+        members.sort();
+        members
+    }
+
+    fn collect(&self, dir: &Path, level: usize, members: &mut Vec<PathBuf>) {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in read_dir.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIP_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let path = entry.path();
+            if self.contains(&path) {
+                members.push(path.clone());
+            }
+            if level < MAX_DEPTH {
+                self.collect(&path, level + 1, members);
+            }
+        }
+    }
+}
+
+/// `path` をパッケージに含むモノレポを祖先から探す
+pub fn containing(path: &Path) -> Option<Monorepo> {
+    path.ancestors()
+        .skip(1)
+        .take(MAX_DEPTH)
+        .filter_map(Monorepo::detect)
+        .find(|monorepo| monorepo.contains(path))
+}
+
+/// `*` がディレクトリの区切りをまたがないパターンの組。不正なパターンは警告して無視する
+fn glob_set(patterns: impl IntoIterator<Item = String>) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        match GlobBuilder::new(pattern).literal_separator(true).build() {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => warn!(pattern = %pattern, error = %e, "invalid workspace pattern"),
+        }
+    }
+    builder.build().unwrap_or_default()
+}
+
+/// Cargo.toml の `workspace.members` と `workspace.exclude`
+fn cargo_members(dir: &Path) -> Option<(Vec<String>, Vec<String>)> {
+    let content = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let manifest: toml::Table = content.parse().ok()?;
+    let workspace = manifest.get("workspace")?.as_table()?;
+    let strings = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(toml::Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Some((strings("members"), strings("exclude")))
+}
+
+/// pnpm-workspace.yaml の `packages:` の項目。YAML を解釈せず、`- ` で始まる行を読む
+fn pnpm_packages(dir: &Path) -> Option<Vec<String>> {
+    let content = fs::read_to_string(dir.join("pnpm-workspace.yaml")).ok()?;
+    let mut in_packages = false;
+    let mut packages = vec![];
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            packages.push(item.trim().trim_matches(['"', '\'']).to_string());
+        }
+    }
+    Some(packages)
+}
+
+/// package.json の `workspaces`（配列か `{ "packages": [...] }`）
+fn npm_workspaces(dir: &Path) -> Option<Vec<String>> {
+    let content = fs::read_to_string(dir.join("package.json")).ok()?;
+    let json: Value = serde_json::from_str(&content).ok()?;
+    let workspaces = json.get("workspaces")?;
+    let patterns = workspaces
+        .as_array()
+        .or_else(|| workspaces.get("packages").and_then(Value::as_array))?;
+    Some(
+        patterns
+            .iter()
+            .filter_map(|p| p.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    /// `tree` の（パス, 中身）を置いたリポジトリ。パスが `/` で終わるものはディレクトリ
+    fn repo(tree: &[(&str, &str)]) -> TempDir {
+        let repo = TempDir::new().unwrap();
+        for (path, content) in tree {
+            let path = repo.path().join(path);
+            if path.to_string_lossy().ends_with('/') {
+                fs::create_dir_all(&path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+            }
+        }
+        repo
+    }
+
+    /// 検出した種類と、リポジトリからの相対パスで並べたパッケージ
+    fn detect(repo: &TempDir) -> Option<(MonorepoKind, Vec<String>)> {
+        let monorepo = Monorepo::detect(repo.path())?;
+        let members = monorepo
+            .members()
+            .iter()
+            .map(|path| {
+                let relative = path.strip_prefix(repo.path()).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        Some((monorepo.kind, members))
+    }
+
+    #[test]
+    fn reads_cargo_workspace_members_and_excludes() {
+        let repo = repo(&[
+            (
+                "Cargo.toml",
+                "[workspace]\nmembers = [\"crates/*\", \"cli\"]\nexclude = [\"crates/old\"]\n",
+            ),
+            ("crates/core/", ""),
+            ("crates/old/", ""),
+            ("crates/core/src/", ""),
+            ("cli/", ""),
+            ("docs/", ""),
+            ("target/debug/", ""),
+        ]);
+        assert_eq!(
+            detect(&repo),
+            Some((
+                MonorepoKind::Cargo,
+                vec!["cli".into(), "crates/core".into()]
+            ))
+        );
+    }
+
+    #[test]
+    fn a_single_crate_is_not_a_monorepo() {
+        let repo = repo(&[("Cargo.toml", "[package]\nname = \"app\"\n"), ("src/", "")]);
+        assert_eq!(detect(&repo), None);
+    }
+
+    #[test]
+    fn reads_pnpm_packages_with_negations() {
+        let repo = repo(&[
+            (
+                "pnpm-workspace.yaml",
+                "packages:\n  # アプリ\n  - 'apps/*'\n  - \"packages/**\"\n  - '!packages/legacy'\ncatalog:\n  - react\n",
+            ),
+            // pnpm-workspace.yaml があれば package.json の workspaces より優先する
+            ("package.json", r#"{"workspaces": ["other/*"]}"#),
+            ("apps/web/", ""),
+            ("packages/ui/", ""),
+            ("packages/legacy/", ""),
+            ("packages/ui/node_modules/dep/", ""),
+            ("other/x/", ""),
+        ]);
+        assert_eq!(
+            detect(&repo),
+            Some((
+                MonorepoKind::Pnpm,
+                vec!["apps/web".into(), "packages/ui".into()]
+            ))
+        );
+    }
+
+    #[test]
+    fn reads_yarn_and_npm_workspaces() {
+        let array = repo(&[
+            ("package.json", r#"{"workspaces": ["packages/*"]}"#),
+            ("packages/a/", ""),
+            ("packages/b/", ""),
+        ]);
+        assert_eq!(
+            detect(&array),
+            Some((
+                MonorepoKind::Npm,
+                vec!["packages/a".into(), "packages/b".into()]
+            ))
+        );
+
+        // yarn の `{ "packages": [...] }` の形
+        let object = repo(&[
+            (
+                "package.json",
+                r#"{"workspaces": {"packages": ["libs/*"]}}"#,
+            ),
+            ("libs/core/", ""),
+        ]);
+        assert_eq!(
+            detect(&object),
+            Some((MonorepoKind::Npm, vec!["libs/core".into()]))
+        );
+
+        let plain = repo(&[("package.json", r#"{"name": "app"}"#)]);
+        assert_eq!(detect(&plain), None);
+    }
+
+    #[test]
+    fn nx_projects_are_directories_with_project_json() {
+        let repo = repo(&[
+            ("nx.json", "{}"),
+            ("apps/shop/project.json", "{}"),
+            ("libs/cart/project.json", "{}"),
+            ("tools/", ""),
+        ]);
+        assert_eq!(
+            detect(&repo),
+            Some((
+                MonorepoKind::Nx,
+                vec!["apps/shop".into(), "libs/cart".into()]
+            ))
+        );
+    }
+
+    #[test]
+    fn finds_the_monorepo_containing_a_package() {
+        let repo = repo(&[
+            ("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n"),
+            ("crates/core/src/", ""),
+        ]);
+        let monorepo = containing(&repo.path().join("crates/core")).unwrap();
+        assert_eq!(monorepo.dir, repo.path());
+        assert!(containing(&repo.path().join("crates/core/src")).is_none());
+    }
+}
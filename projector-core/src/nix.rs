@@ -0,0 +1,47 @@
+//! Nix の開発環境との連携
+
+use std::path::Path;
+
+use crate::config::NixConfig;
+use crate::shell;
+
+/// Nix の開発環境定義の種類
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NixKind {
+    /// flake.nix（`nix develop`）
+    Flake,
+    /// shell.nix（`nix-shell`）
+    Shell,
+}
+
+impl NixKind {
+    /// 表示用のファイル名
+    pub fn label(self) -> &'static str {
+        match self {
+            NixKind::Flake => "flake.nix",
+            NixKind::Shell => "shell.nix",
+        }
+    }
+}
+
+/// flake.nix を優先して Nix の開発環境定義を探す
+pub fn detect(project: &Path) -> Option<NixKind> {
+    if project.join("flake.nix").is_file() {
+        Some(NixKind::Flake)
+    } else if project.join("shell.nix").is_file() {
+        Some(NixKind::Shell)
+    } else {
+        None
+    }
+}
+
+/// 各ペインで実行するコマンド。無効化されているか nix が無ければ None
+pub fn pane_command(project: &Path, config: &NixConfig) -> Option<String> {
+    if !config.enabled || !shell::command_exists("nix") {
+        return None;
+    }
+    match detect(project)? {
+        NixKind::Flake => Some(config.flake_command.clone()),
+        NixKind::Shell => Some(config.shell_command.clone()),
+    }
+}
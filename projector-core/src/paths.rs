@@ -0,0 +1,73 @@
+//! 設定・キャッシュなどのファイルの置き場所
+//!
+//! XDG Base Directory に従い、`XDG_CONFIG_HOME` / `XDG_CACHE_HOME` / `XDG_STATE_HOME` /
+//! `XDG_DATA_HOME` が絶対パスで設定されていればその下の `projector` を使う。
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// XDG の環境変数が示すディレクトリ。未設定か相対パスならホーム以下の既定の場所
+fn xdg_home(var: &str, default: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(default)))
+}
+
+/// XDG の環境変数が示すディレクトリの下の `projector`
+fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    xdg_home(var, default).map(|dir| dir.join("projector"))
+}
+
+/// 設定ファイル。`PROJECTOR_CONFIG` があればそのパス、
+/// 無ければ `$XDG_CONFIG_HOME/projector/config.toml` (~/.config/projector/config.toml)
+pub fn config_file() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("PROJECTOR_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("config.toml"))
+}
+
+/// キャッシュディレクトリ `$XDG_CACHE_HOME/projector` (~/.cache/projector)
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// ログなどの状態ディレクトリ `$XDG_STATE_HOME/projector` (~/.local/state/projector)
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// デスクトップのゴミ箱 `$XDG_DATA_HOME/Trash` (~/.local/share/Trash)。macOS は ~/.Trash
+pub fn trash_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return dirs::home_dir().map(|home| home.join(".Trash"));
+    }
+    xdg_home("XDG_DATA_HOME", ".local/share").map(|dir| dir.join("Trash"))
+}
+
+/// デーモンのソケット `$XDG_RUNTIME_DIR/projector/daemon.sock`。
+/// `XDG_RUNTIME_DIR` が無ければ `state_dir()` の下
+pub fn socket_file() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("projector"))
+        .or_else(state_dir)
+        .map(|dir| dir.join("daemon.sock"))
+}
+
+/// ログファイル (`state_dir()` の下の log)
+pub fn log_file() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("log"))
+}
+
+/// ホームディレクトリ以下のパスを `~/` で始まる形に縮める
+pub fn shorten_path(path: &Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(relative) = path.strip_prefix(&home) {
+            return format!("~/{}", relative.display());
+        }
+    }
+    path.display().to_string()
+}
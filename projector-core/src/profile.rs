@@ -0,0 +1,87 @@
+//! `--profile` で表示する処理時間の計測
+//!
+//! 有効にしたときだけ、区間の名前ごとに回数と合計時間を集める。ワーカーのスレッドからも記録できる。
+//! 無効なときは時間を測らない。
+
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+struct Profile {
+    started: Instant,
+    timings: Mutex<Vec<Timing>>,
+}
+
+/// 1つの区間の計測結果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timing {
+    /// 区間の名前（`scan` や `git` など）
+    pub label: &'static str,
+    /// 計測した回数
+    pub count: usize,
+    /// 合計時間
+    pub total: Duration,
+}
+
+/// 計測を始める。これより前の区間は記録しない
+pub fn enable() {
+    let _ = PROFILE.set(Profile {
+        started: Instant::now(),
+        timings: Mutex::new(vec![]),
+    });
+}
+
+/// `f` の実行時間を `label` の区間として記録する
+pub fn time<T>(label: &'static str, f: impl FnOnce() -> T) -> T {
+    if PROFILE.get().is_none() {
+        return f();
+    }
+    let started = Instant::now();
+    let value = f();
+    record(label, started.elapsed());
+    value
+}
+
+/// 計測を始めてからの時間を `label` の区間として記録する（最初の描画までなど）。
+/// 2回目以降は記録しない
+pub fn mark(label: &'static str) {
+    let Some(profile) = PROFILE.get() else {
+        return;
+    };
+    let recorded = lock(profile).iter().any(|timing| timing.label == label);
+    if !recorded {
+        record(label, profile.started.elapsed());
+    }
+}
+
+/// `label` の区間に `elapsed` を足す
+pub fn record(label: &'static str, elapsed: Duration) {
+    let Some(profile) = PROFILE.get() else {
+        return;
+    };
+    let mut timings = lock(profile);
+    match timings.iter_mut().find(|timing| timing.label == label) {
+        Some(timing) => {
+            timing.count += 1;
+            timing.total += elapsed;
+        }
+        None => timings.push(Timing {
+            label,
+            count: 1,
+            total: elapsed,
+        }),
+    }
+}
+
+/// ここまでの計測結果を記録した順に取り出す。取り出したものは消える
+pub fn take() -> Vec<Timing> {
+    PROFILE
+        .get()
+        .map(|profile| std::mem::take(&mut *lock(profile)))
+        .unwrap_or_default()
+}
+
+fn lock(profile: &Profile) -> std::sync::MutexGuard<'_, Vec<Timing>> {
+    profile.timings.lock().unwrap_or_else(|e| e.into_inner())
+}
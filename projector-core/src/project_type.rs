@@ -0,0 +1,30 @@
+//! マニフェストから分かるプロジェクトの種類
+
+use std::path::Path;
+
+/// 種類の名前と、その種類だと分かるファイル（[`detect`] はこの順に並べる）
+pub const TYPES: &[(&str, &[&str])] = &[
+    ("rust", &["Cargo.toml"]),
+    ("node", &["package.json"]),
+    ("deno", &["deno.json", "deno.jsonc"]),
+    ("go", &["go.mod"]),
+    (
+        "python",
+        &["pyproject.toml", "setup.py", "requirements.txt"],
+    ),
+    ("ruby", &["Gemfile"]),
+    ("java", &["pom.xml", "build.gradle", "build.gradle.kts"]),
+    ("elixir", &["mix.exs"]),
+    ("haskell", &["stack.yaml", "cabal.project"]),
+    ("zig", &["build.zig"]),
+    ("nix", &["flake.nix", "shell.nix"]),
+];
+
+/// `project` の種類（1つも当てはまらなければ空）
+pub fn detect(project: &Path) -> Vec<&'static str> {
+    TYPES
+        .iter()
+        .filter(|(_, files)| files.iter().any(|file| project.join(file).is_file()))
+        .map(|(name, _)| *name)
+        .collect()
+}
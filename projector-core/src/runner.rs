@@ -0,0 +1,396 @@
+//! 外部コマンドの実行
+//!
+//! tmux / git / ssh などの呼び出しはすべて [`CommandRunner`] を経由する。
+//! 実行器を差し替えれば、実際にプロセスを起動せずに組み立てた引数を検証できる。
+//! [`DryRunRunner`] は状態を変えるコマンドを実行せずに表示し、`MockRunner` は記録する
+//! （`MockRunner` はテストと `testing` フィーチャーのときだけ使える）。
+
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+#[cfg(any(test, feature = "testing"))]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use tracing::{debug, trace, warn};
+
+use crate::shell;
+
+/// 実行するコマンド
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cmd {
+    /// 実行ファイル名
+    pub program: OsString,
+    /// 引数
+    pub args: Vec<OsString>,
+    /// 作業ディレクトリ
+    pub cwd: Option<PathBuf>,
+    /// 追加する環境変数
+    pub env: Vec<(OsString, OsString)>,
+    /// 標準入力に書き込む内容
+    pub stdin: Option<Vec<u8>>,
+    /// 標準エラー出力を取り込まずに端末へそのまま流す
+    pub inherit_stderr: bool,
+    /// 状態を変えない問い合わせ（dry-run でも実行する）
+    pub read_only: bool,
+}
+
+impl Cmd {
+    /// 引数なしのコマンドを作る
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Cmd {
+            program: program.as_ref().to_os_string(),
+            args: vec![],
+            cwd: None,
+            env: vec![],
+            stdin: None,
+            inherit_stderr: false,
+            read_only: false,
+        }
+    }
+
+    /// 引数を1つ追加する
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// 引数をまとめて追加する
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_os_string()));
+        self
+    }
+
+    /// 作業ディレクトリを指定する
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// 環境変数を追加する
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.env
+            .push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// 標準入力に渡す内容を指定する
+    pub fn stdin(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    /// 標準エラー出力を端末に流す（ビルドログなどを見せたいとき）
+    pub fn inherit_stderr(mut self) -> Self {
+        self.inherit_stderr = true;
+        self
+    }
+
+    /// 状態を変えない問い合わせであることを示す
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// シェルにそのまま貼り付けられる `program arg1 arg2 ...` 形式の文字列
+    pub fn display(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|a| shell::quote(&a.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// 取り込んだ実行結果
+#[derive(Clone, Debug, Default)]
+pub struct CmdOutput {
+    /// 終了コードが 0 だったか
+    pub success: bool,
+    /// 終了コード（シグナルで終了した場合は None）
+    pub code: Option<i32>,
+    /// 標準出力
+    pub stdout: Vec<u8>,
+    /// 標準エラー出力（`inherit_stderr` のときは空）
+    pub stderr: Vec<u8>,
+}
+
+impl CmdOutput {
+    /// 標準出力を文字列として返す
+    pub fn stdout_str(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).to_string()
+    }
+
+    /// 標準エラー出力を文字列として返す
+    pub fn stderr_str(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).to_string()
+    }
+}
+
+/// 外部コマンドの実行器
+pub trait CommandRunner: Send + Sync {
+    /// 出力を取り込んで実行する
+    fn output(&self, cmd: &Cmd) -> io::Result<CmdOutput>;
+
+    /// 端末を引き継いで実行し、終了を待つ（tmux attach など）。成功したら true
+    fn interactive(&self, cmd: &Cmd) -> io::Result<bool>;
+
+    /// 終了を待たずに起動する（端末のウィンドウなど、projector より長く動き続けるもの）
+    fn spawn(&self, cmd: &Cmd) -> io::Result<()>;
+
+    /// 状態を変えるコマンドを実行しない（dry-run）か
+    fn dry_run(&self) -> bool {
+        false
+    }
+}
+
+/// 実際にプロセスを起動する実行器
+pub struct SystemRunner;
+
+impl SystemRunner {
+    fn command(cmd: &Cmd) -> Command {
+        let mut command = Command::new(&cmd.program);
+        command.args(&cmd.args);
+        command.envs(cmd.env.iter().map(|(key, value)| (key, value)));
+        if let Some(cwd) = &cmd.cwd {
+            command.current_dir(cwd);
+        }
+        command
+    }
+}
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, cmd: &Cmd) -> io::Result<CmdOutput> {
+        let mut command = Self::command(cmd);
+        command
+            .stdin(if cmd.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(if cmd.inherit_stderr {
+                Stdio::inherit()
+            } else {
+                Stdio::piped()
+            });
+
+        debug!(command = %cmd.display(), cwd = ?cmd.cwd, "run");
+        let mut child = command.spawn().inspect_err(|e| {
+            warn!(command = %cmd.display(), error = %e, "spawn failed");
+        })?;
+        // 出力を読みながら書かないと、パイプが一杯になったところで子プロセスと待ち合って止まる
+        let input = cmd.stdin.as_deref().zip(child.stdin.take());
+        let output = thread::scope(|scope| {
+            let writer =
+                input.map(|(input, mut stdin)| scope.spawn(move || stdin.write_all(input)));
+            let output = child.wait_with_output()?;
+            if let Some(Ok(written)) = writer.map(|writer| writer.join()) {
+                written?;
+            }
+            Ok::<_, io::Error>(output)
+        })?;
+
+        if output.status.success() {
+            trace!(stdout = %String::from_utf8_lossy(&output.stdout), "done");
+        } else {
+            warn!(
+                command = %cmd.display(),
+                status = %output.status,
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "command failed"
+            );
+        }
+        Ok(CmdOutput {
+            success: output.status.success(),
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn interactive(&self, cmd: &Cmd) -> io::Result<bool> {
+        debug!(command = %cmd.display(), cwd = ?cmd.cwd, "run interactive");
+        let mut command = Self::command(cmd);
+        // 候補をパイプで受け取ったときも tmux attach などは端末から入力させる
+        #[cfg(unix)]
+        if !io::stdin().is_terminal() {
+            if let Ok(tty) = File::open("/dev/tty") {
+                command.stdin(tty);
+            }
+        }
+        let status = command.status().inspect_err(|e| {
+            warn!(command = %cmd.display(), error = %e, "spawn failed");
+        })?;
+        if !status.success() {
+            warn!(command = %cmd.display(), status = %status, "command failed");
+        }
+        Ok(status.success())
+    }
+
+    fn spawn(&self, cmd: &Cmd) -> io::Result<()> {
+        debug!(command = %cmd.display(), cwd = ?cmd.cwd, "spawn");
+        Self::command(cmd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .inspect_err(|e| {
+                warn!(command = %cmd.display(), error = %e, "spawn failed");
+            })?;
+        Ok(())
+    }
+}
+
+/// 問い合わせだけ実行し、それ以外のコマンドは実行せずに標準出力へ表示する実行器
+pub struct DryRunRunner;
+
+impl DryRunRunner {
+    fn print(cmd: &Cmd) {
+        match &cmd.cwd {
+            Some(cwd) => println!(
+                "cd {} && {}",
+                shell::quote(&cwd.to_string_lossy()),
+                cmd.display()
+            ),
+            None => println!("{}", cmd.display()),
+        }
+    }
+}
+
+impl CommandRunner for DryRunRunner {
+    fn output(&self, cmd: &Cmd) -> io::Result<CmdOutput> {
+        if cmd.read_only {
+            return SystemRunner.output(cmd);
+        }
+        Self::print(cmd);
+        Ok(CmdOutput {
+            success: true,
+            code: Some(0),
+            ..CmdOutput::default()
+        })
+    }
+
+    fn interactive(&self, cmd: &Cmd) -> io::Result<bool> {
+        Self::print(cmd);
+        Ok(true)
+    }
+
+    fn spawn(&self, cmd: &Cmd) -> io::Result<()> {
+        Self::print(cmd);
+        Ok(())
+    }
+
+    fn dry_run(&self) -> bool {
+        true
+    }
+}
+
+/// コマンドを実行せずに記録し、決めておいた結果を返す実行器（テスト用）
+///
+/// 結果を決めていないコマンドは、出力の無い成功として扱う。
+#[cfg(any(test, feature = "testing"))]
+#[derive(Default)]
+pub struct MockRunner {
+    calls: Mutex<Vec<Cmd>>,
+    /// コマンドの先頭の語と、それに当てはまるときの結果
+    responses: Mutex<Vec<(Vec<String>, CmdOutput)>>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl MockRunner {
+    /// 何も決めていない実行器を作る
+    pub fn new() -> Self {
+        MockRunner::default()
+    }
+
+    /// `program arg...` の先頭が `prefix` に一致するコマンドの結果を決める。
+    /// 複数に一致するなら後から決めたものを使う
+    pub fn respond(&self, prefix: &[&str], success: bool, stdout: &str) {
+        let output = CmdOutput {
+            success,
+            code: Some(if success { 0 } else { 1 }),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        };
+        let prefix = prefix.iter().map(|word| word.to_string()).collect();
+        lock(&self.responses).push((prefix, output));
+    }
+
+    /// 記録したコマンド（実行した順）
+    pub fn calls(&self) -> Vec<Cmd> {
+        lock(&self.calls).clone()
+    }
+
+    /// 記録したコマンドのうち `program` のものを、シェルに貼り付けられる形で返す
+    pub fn commands(&self, program: &str) -> Vec<String> {
+        lock(&self.calls)
+            .iter()
+            .filter(|cmd| cmd.program == program)
+            .map(Cmd::display)
+            .collect()
+    }
+
+    fn run(&self, cmd: &Cmd) -> CmdOutput {
+        lock(&self.calls).push(cmd.clone());
+        let words: Vec<String> = std::iter::once(&cmd.program)
+            .chain(&cmd.args)
+            .map(|word| word.to_string_lossy().to_string())
+            .collect();
+        lock(&self.responses)
+            .iter()
+            .rev()
+            .find(|(prefix, _)| words.starts_with(prefix))
+            .map(|(_, output)| output.clone())
+            .unwrap_or(CmdOutput {
+                success: true,
+                code: Some(0),
+                ..CmdOutput::default()
+            })
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl CommandRunner for MockRunner {
+    fn output(&self, cmd: &Cmd) -> io::Result<CmdOutput> {
+        Ok(self.run(cmd))
+    }
+
+    fn interactive(&self, cmd: &Cmd) -> io::Result<bool> {
+        Ok(self.run(cmd).success)
+    }
+
+    fn spawn(&self, cmd: &Cmd) -> io::Result<()> {
+        self.run(cmd);
+        Ok(())
+    }
+}
+
+/// テストが途中で失敗してロックが壊れても、記録は読めるようにする
+#[cfg(any(test, feature = "testing"))]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_stdin_larger_than_the_pipe_buffer() {
+        // cat が出力のパイプを埋めて止まっても、書き込みと読み出しが並行するので終わる
+        let input = vec![b'x'; 1 << 20];
+        let output = SystemRunner
+            .output(&Cmd::new("cat").stdin(input.clone()))
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout, input);
+    }
+}
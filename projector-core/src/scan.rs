@@ -0,0 +1,154 @@
+//! ディレクトリの走査
+
+use std::cmp::{Ordering, Reverse};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::config::{self, SortOrder, SymlinkPolicy};
+
+/// 走査のしかた。デーモンへの問い合わせにも載せる
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// シンボリックリンクのディレクトリの扱い
+    pub symlinks: SymlinkPolicy,
+    /// 一覧に出さないディレクトリ名のパターン（`*.bak` のような glob）
+    pub ignore: Vec<String>,
+    /// 並べる順番
+    pub sort: SortOrder,
+    /// `.` で始まる隠しディレクトリも並べる
+    pub hidden: bool,
+    /// ローカルのルート。`.projector/config.toml` はこれより上では探さない
+    pub roots: Vec<PathBuf>,
+    /// プロジェクトとみなすディレクトリにあるファイル・ディレクトリ名（`.git` `Cargo.toml` など）
+    pub markers: Vec<String>,
+}
+
+impl ScanOptions {
+    /// `dir` とその祖先（ルートまで）の `.projector/config.toml` を反映した走査のしかた
+    pub fn for_dir(&self, dir: &Path) -> ScanOptions {
+        let mut options = self.clone();
+        for local in config::local_configs(dir, &self.roots) {
+            if let Some(ignore) = local.ignore {
+                options.ignore = ignore;
+            }
+            if let Some(sort) = local.sort {
+                options.sort = sort;
+            }
+        }
+        options
+    }
+
+    /// `dir` がプロジェクト（[`markers`](Self::markers) のどれかがある）か
+    pub fn is_project(&self, dir: &Path) -> bool {
+        self.markers.iter().any(|marker| dir.join(marker).exists())
+    }
+
+    /// ignore のパターンをまとめる。不正なパターンは警告して無視する
+    pub(crate) fn ignore_set(&self) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.ignore {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => warn!(pattern = %pattern, error = %e, "invalid ignore pattern"),
+            }
+        }
+        builder.build().unwrap_or_default()
+    }
+
+    /// ディレクトリを並べ替える
+    pub fn sort(&self, dirs: &mut [Directory]) {
+        dirs.sort_by(|a, b| self.sort.compare(a, b));
+    }
+}
+
+impl SortOrder {
+    /// この並び順でのディレクトリの前後
+    pub fn compare(self, a: &Directory, b: &Directory) -> Ordering {
+        match self {
+            SortOrder::Name => a.name.cmp(&b.name),
+            // 更新日時が分からないものは最後に回す
+            SortOrder::Modified => Reverse(a.modified)
+                .cmp(&Reverse(b.modified))
+                .then_with(|| a.name.cmp(&b.name)),
+        }
+    }
+}
+
+/// 一覧に並べるサブディレクトリ
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Directory {
+    /// ディレクトリ名（UTF-8 とは限らない）
+    pub name: OsString,
+    /// シンボリックリンクで、[`SymlinkPolicy::Mark`] のとき印を付けるもの
+    pub symlink: bool,
+    /// 更新日時（リモートでは取得しない）
+    pub modified: Option<SystemTime>,
+}
+
+/// ignore に当てはまるものと（`options.hidden` でなければ）隠しディレクトリを除いたサブディレクトリを返す
+///
+/// `path` とその祖先に `.projector/config.toml` があれば、ignore と並び順はそちらを優先する。
+/// シンボリックリンクは `options.symlinks` に従う。辿る場合も、走査中のディレクトリ自身や
+/// その祖先を指すリンクは循環になるので除く。
+pub fn get_directories(path: &Path, options: &ScanOptions) -> Vec<Directory> {
+    let options = options.for_dir(path);
+    let ignore = options.ignore_set();
+    let mut dirs = Vec::new();
+    // 循環の判定はリンクがあったときだけ行う
+    let mut canonical = None;
+
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            // リンク先のディレクトリも含めるため、リンクを辿ったメタデータを見る
+            let Ok(metadata) = fs::metadata(&entry_path) else {
+                continue;
+            };
+            if !metadata.is_dir() {
+                continue;
+            }
+            let name = entry.file_name();
+            let hidden = name.as_encoded_bytes().starts_with(b".");
+            if (hidden && !options.hidden) || is_ignored(&ignore, &name) {
+                continue;
+            }
+
+            let symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+            if symlink {
+                if options.symlinks == SymlinkPolicy::Skip {
+                    continue;
+                }
+                let here = canonical.get_or_insert_with(|| fs::canonicalize(path).ok());
+                let target = fs::canonicalize(&entry_path).ok();
+                if let (Some(here), Some(target)) = (here.as_ref(), target) {
+                    if here.starts_with(&target) {
+                        debug!(link = %entry_path.display(), "skipping symlink cycle");
+                        continue;
+                    }
+                }
+            }
+
+            dirs.push(Directory {
+                name,
+                symlink: symlink && options.symlinks == SymlinkPolicy::Mark,
+                modified: metadata.modified().ok(),
+            });
+        }
+    }
+
+    options.sort(&mut dirs);
+    dirs
+}
+
+/// ディレクトリ名が ignore のパターンに当てはまるか
+pub(crate) fn is_ignored(ignore: &GlobSet, name: &OsStr) -> bool {
+    !ignore.is_empty() && ignore.is_match(Path::new(name))
+}
@@ -0,0 +1,63 @@
+//! ripgrep でコードの断片を含むプロジェクトを探す
+
+use std::path::Path;
+
+use crate::location::Entry;
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+const PROGRAM: &str = "rg";
+
+/// `query` をそのままの文字列として含むファイルがあるプロジェクトを、`projects` の順で返す
+///
+/// ファイルは最も深いところにあるプロジェクトのものとする（ルート直下のディレクトリとその中の
+/// リポジトリの両方が候補にあれば、リポジトリの方）。`.gitignore` などは ripgrep の既定に従う。
+pub fn projects_containing(
+    runner: &dyn CommandRunner,
+    projects: &[Entry],
+    query: &str,
+) -> Result<Vec<Entry>> {
+    let paths: Vec<&Path> = projects
+        .iter()
+        .filter_map(|entry| entry.location.local_path())
+        .collect();
+    if query.is_empty() || paths.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let cmd = Cmd::new(PROGRAM)
+        .args([
+            "--files-with-matches",
+            "--fixed-strings",
+            "--no-messages",
+            "--",
+        ])
+        .arg(query)
+        .args(&paths)
+        .read_only();
+    let output = runner.output(&cmd).map_err(|e| Error::spawn(PROGRAM, e))?;
+    // 終了コード 1 は一致なし。出力があればエラーがあっても一致した分は使う
+    let stdout = output.stdout_str();
+    if !output.success && stdout.is_empty() && output.code != Some(1) {
+        return Err(Error::CommandFailed(cmd.display()));
+    }
+
+    let mut found = vec![false; projects.len()];
+    for file in stdout.lines().map(Path::new) {
+        let owner = projects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((i, entry.location.local_path()?)))
+            .filter(|(_, path)| file.starts_with(path))
+            .max_by_key(|(_, path)| path.components().count());
+        if let Some((i, _)) = owner {
+            found[i] = true;
+        }
+    }
+    Ok(projects
+        .iter()
+        .zip(found)
+        .filter(|(_, found)| *found)
+        .map(|(entry, _)| entry.clone())
+        .collect())
+}
@@ -0,0 +1,464 @@
+//! tmux セッションの組み立てと起動
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Msg;
+use crate::location::Location;
+use crate::monorepo;
+use crate::runner::{Cmd, CommandRunner};
+use crate::shell;
+use crate::{Error, Result};
+
+/// tmux のセッション名に使えるよう整える（`.` と `:` はターゲット指定と衝突する）
+pub fn session_name(name: &str) -> String {
+    let name = name.to_lowercase().replace(['.', ':'], "_");
+    if name.is_empty() {
+        "default".to_string()
+    } else {
+        name
+    }
+}
+
+/// プロジェクトの tmux セッション名。モノレポのパッケージならリポジトリ名を前に付ける
+pub fn project_session_name(location: &Location) -> String {
+    let repository = location
+        .local_path()
+        .and_then(monorepo::containing)
+        .and_then(|monorepo| Some(monorepo.dir.file_name()?.to_string_lossy().to_string()));
+    match repository {
+        Some(repository) => session_name(&format!("{}-{}", repository, location.name())),
+        None => session_name(&location.name()),
+    }
+}
+
+/// 作成するセッションの内容。ローカルとリモートで同じレイアウトを使う
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionSpec {
+    /// セッション名
+    pub name: String,
+    /// ペインの作業ディレクトリ（リモートでは `~` を含んでよい）
+    pub dir: PathBuf,
+    /// 左から順に並べるペインと、それぞれで実行するコマンド（None ならログインシェル）
+    pub panes: Vec<Option<String>>,
+    /// セッションを作った直後に `set-option` する tmux のオプション
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    /// ウィンドウのペインへのキー入力を揃える（`synchronize-panes`）
+    #[serde(default)]
+    pub synchronize: bool,
+}
+
+impl SessionSpec {
+    /// プロジェクトの場所からセッションを組み立てる
+    pub fn new(location: &Location) -> Self {
+        SessionSpec {
+            name: project_session_name(location),
+            dir: match location {
+                Location::Local(path) => path.clone(),
+                Location::Remote { path, .. } => PathBuf::from(path),
+            },
+            panes: vec![None, None],
+            options: BTreeMap::new(),
+            synchronize: false,
+        }
+    }
+
+    /// すべてのペインで同じコマンドを実行する
+    pub fn set_command(&mut self, command: String) {
+        for pane in &mut self.panes {
+            *pane = Some(command.clone());
+        }
+    }
+
+    /// `index` 番目（0 始まり）のペインのコマンドを置き換える。足りなければペインを増やす
+    pub fn set_pane(&mut self, index: usize, command: String) {
+        if self.panes.len() <= index {
+            self.panes.resize(index + 1, None);
+        }
+        self.panes[index] = Some(command);
+    }
+
+    /// 組み立てに使う tmux の機能
+    pub fn features(&self) -> Vec<Feature> {
+        let mut features = vec![Feature::StartDirectory];
+        if self.options.contains_key("mouse") {
+            features.push(Feature::MouseOption);
+        }
+        if self.options.keys().any(|option| option.ends_with("-style")) {
+            features.push(Feature::StyleOptions);
+        }
+        features
+    }
+
+    /// セッションをバックグラウンドで組み立てる tmux コマンドの引数列。
+    /// ディレクトリ名が UTF-8 でなくてもそのまま渡せるよう `OsString` で返す
+    ///
+    /// `history-limit` のように新しいペインにだけ効くオプションもあるので、
+    /// オプションはセッションを作った直後、ペインを分割する前に設定する。
+    pub fn setup_commands(&self) -> Vec<Vec<OsString>> {
+        let name = self.name.as_str();
+        let mut commands: Vec<Vec<OsString>> = vec![];
+        for (i, command) in self.panes.iter().enumerate() {
+            let args: &[&str] = if i == 0 {
+                // 新規セッションをバックグラウンドで作成
+                &["new-session", "-d", "-s", name, "-c"]
+            } else {
+                // 垂直分割
+                &["split-window", "-h", "-t", name, "-c"]
+            };
+            let mut args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            args.push(self.dir.clone().into_os_string());
+            args.extend(command.iter().map(OsString::from));
+            commands.push(args);
+            if i == 0 {
+                commands.extend(self.options.iter().map(|(option, value)| {
+                    ["set-option", "-t", name, option, value]
+                        .iter()
+                        .map(OsString::from)
+                        .collect()
+                }));
+            }
+        }
+        // 3つ以上に分けたら幅を揃える
+        if self.panes.len() > 2 {
+            commands.push(
+                ["select-layout", "-t", name, "even-horizontal"]
+                    .iter()
+                    .map(OsString::from)
+                    .collect(),
+            );
+        }
+        if self.synchronize {
+            commands.push(
+                ["set-window-option", "-t", name, "synchronize-panes", "on"]
+                    .iter()
+                    .map(OsString::from)
+                    .collect(),
+            );
+        }
+        commands
+    }
+
+    /// リモートで実行するシェルスクリプト。既存のセッションがあればそのままアタッチする
+    pub fn remote_script(&self) -> String {
+        let setup = self
+            .setup_commands()
+            .iter()
+            .map(|args| {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| shell::quote_path(&a.to_string_lossy()))
+                    .collect();
+                format!("tmux {}", args.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join(" && ");
+        let name = shell::quote(&self.name);
+        format!(
+            "tmux has-session -t {name} 2>/dev/null || {{ {setup}; }}; exec tmux attach-session -t {name}"
+        )
+    }
+}
+
+/// tmux のバージョン（`tmux -V` の `3.3a` なら 3.3）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TmuxVersion {
+    /// メジャーバージョン
+    pub major: u32,
+    /// マイナーバージョン
+    pub minor: u32,
+}
+
+impl TmuxVersion {
+    /// `tmux -V` の出力を読む。`next-3.4` は 3.4 とみなし、`master` などの開発版は None
+    pub fn parse(output: &str) -> Option<TmuxVersion> {
+        let version = output.trim().strip_prefix("tmux ")?;
+        let version = version.strip_prefix("next-").unwrap_or(version);
+        let (major, rest) = version.split_once('.')?;
+        let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        Some(TmuxVersion {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for TmuxVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// 古い tmux には無い機能
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    /// `new-session` `split-window` `new-window` の `-c`（ペインの作業ディレクトリ）
+    StartDirectory,
+    /// `mouse` オプション（それより前は `mode-mouse` などに分かれていた）
+    MouseOption,
+    /// `status-style` などの `-style` オプション（それより前は `-fg` `-bg` `-attr` に分かれていた）
+    StyleOptions,
+    /// `display-popup`（tmux の中から TUI をポップアップで開く）
+    Popup,
+}
+
+impl Feature {
+    /// 使えるようになったバージョン
+    pub fn since(self) -> TmuxVersion {
+        let (major, minor) = match self {
+            Feature::StartDirectory => (1, 9),
+            Feature::MouseOption => (2, 1),
+            Feature::StyleOptions => (1, 9),
+            Feature::Popup => (3, 2),
+        };
+        TmuxVersion { major, minor }
+    }
+
+    /// エラーメッセージに出す名前
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::StartDirectory => "-c (start directory)",
+            Feature::MouseOption => "mouse option",
+            Feature::StyleOptions => "-style options",
+            Feature::Popup => "display-popup",
+        }
+    }
+}
+
+/// ローカルの tmux のバージョン。tmux が無いか、読めない形式なら None
+pub fn version(runner: &dyn CommandRunner) -> Option<TmuxVersion> {
+    let cmd = Cmd::new("tmux").arg("-V").read_only();
+    let output = runner.output(&cmd).ok().filter(|output| output.success)?;
+    TmuxVersion::parse(&output.stdout_str())
+}
+
+/// セッションの組み立てに要る機能がローカルの tmux にあるか確かめる。
+/// バージョンが分からなければ新しいものとみなす
+pub fn check_version(runner: &dyn CommandRunner, spec: &SessionSpec) -> Result<()> {
+    let Some(version) = version(runner) else {
+        return Ok(());
+    };
+    spec.features()
+        .into_iter()
+        .try_for_each(|feature| require(version, feature))
+}
+
+/// `version` の tmux に `feature` があるか
+pub fn require(version: TmuxVersion, feature: Feature) -> Result<()> {
+    if version < feature.since() {
+        return Err(Error::TmuxTooOld {
+            feature: feature.label(),
+            required: feature.since().to_string(),
+            found: version.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// ローカルの tmux にセッションが存在するか
+pub fn exists(runner: &dyn CommandRunner, session_name: &str) -> bool {
+    let cmd = Cmd::new("tmux")
+        .args(["has-session", "-t", session_name])
+        .read_only();
+    runner.output(&cmd).is_ok_and(|output| output.success)
+}
+
+/// ローカルの tmux のセッションを終了する
+pub fn kill(runner: &dyn CommandRunner, session_name: &str) -> Result<()> {
+    let cmd = Cmd::new("tmux").args(["kill-session", "-t", session_name]);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        return Err(Error::Tmux("kill-session".to_string()));
+    }
+    Ok(())
+}
+
+/// ローカルの tmux でセッションを作成してアタッチする。既にあればアタッチだけ行う
+pub fn start_local(runner: &dyn CommandRunner, spec: &SessionSpec) -> Result<()> {
+    let session_name = &spec.name;
+
+    // セッションが既に存在するかチェック
+    if exists(runner, session_name) {
+        println!("{}", Msg::SessionExists(session_name));
+        return attach(runner, session_name);
+    }
+
+    create(runner, spec)?;
+
+    // セッションにアタッチ
+    attach(runner, session_name)
+}
+
+/// セッションをバックグラウンドで作成する
+pub fn create(runner: &dyn CommandRunner, spec: &SessionSpec) -> Result<()> {
+    check_version(runner, spec)?;
+    for args in spec.setup_commands() {
+        let output = runner
+            .output(&Cmd::new("tmux").args(&args))
+            .map_err(|e| Error::spawn("tmux", e))?;
+
+        if !output.success {
+            return Err(Error::Tmux(args[0].to_string_lossy().to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// プロジェクトのセッション（無ければ作る）に新しいウィンドウを開いてコマンドを実行し、アタッチする。
+/// 終わった後も出力を読めるよう、ウィンドウではコマンドの後にシェルを起動する
+pub fn run_in_window(
+    runner: &dyn CommandRunner,
+    spec: &SessionSpec,
+    window: &str,
+    command: &str,
+) -> Result<()> {
+    if !exists(runner, &spec.name) {
+        create(runner, spec)?;
+    }
+    let target = format!("{}:", spec.name);
+    let cmd = Cmd::new("tmux")
+        .args(["new-window", "-t", &target, "-n", window, "-c"])
+        .arg(&spec.dir)
+        .arg(format!("{}; exec \"${{SHELL:-/bin/sh}}\"", command));
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        return Err(Error::Tmux("new-window".to_string()));
+    }
+    attach(runner, &spec.name)
+}
+
+/// セッションが無ければバックグラウンドで作る。作ったら true
+pub fn ensure(runner: &dyn CommandRunner, spec: &SessionSpec) -> Result<bool> {
+    if exists(runner, &spec.name) {
+        return Ok(false);
+    }
+    create(runner, spec)?;
+    Ok(true)
+}
+
+/// tmux の中から、端末を使わずに今のクライアントをセッションに切り替える
+pub fn switch_client(runner: &dyn CommandRunner, session_name: &str) -> Result<()> {
+    let cmd = Cmd::new("tmux").args(["switch-client", "-t", session_name]);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        return Err(Error::Tmux("switch-client".to_string()));
+    }
+    Ok(())
+}
+
+/// tmux の中で実行されているか
+pub fn inside_tmux() -> bool {
+    std::env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty())
+}
+
+/// 今の tmux セッションに `dir` で始まる新しいウィンドウを開く
+pub fn new_window(runner: &dyn CommandRunner, name: &str, dir: &Path) -> Result<()> {
+    let cmd = Cmd::new("tmux")
+        .args(["new-window", "-n", name, "-c"])
+        .arg(dir);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        return Err(Error::Tmux("new-window".to_string()));
+    }
+    Ok(())
+}
+
+/// 今の tmux のウィンドウを左右に分割し、`dir` でコマンドを実行する
+pub fn split(runner: &dyn CommandRunner, dir: &Path, command: &str) -> Result<()> {
+    let cmd = Cmd::new("tmux")
+        .args(["split-window", "-h", "-c"])
+        .arg(dir)
+        .arg(command);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        return Err(Error::Tmux("split-window".to_string()));
+    }
+    Ok(())
+}
+
+/// アタッチに使う tmux のサブコマンド。tmux の中からは入れ子にせず switch-client で切り替える
+fn attach_subcommand() -> &'static str {
+    if inside_tmux() {
+        "switch-client"
+    } else {
+        "attach-session"
+    }
+}
+
+/// セッションにアタッチするシェルのコマンド（`tmux attach-session -t <名前>`）
+pub fn attach_command(session_name: &str) -> String {
+    format!(
+        "tmux {} -t {}",
+        attach_subcommand(),
+        shell::quote(session_name)
+    )
+}
+
+/// セッションにアタッチする
+fn attach(runner: &dyn CommandRunner, session_name: &str) -> Result<()> {
+    let subcommand = attach_subcommand();
+    let cmd = Cmd::new("tmux").args([subcommand, "-t", session_name]);
+    let success = runner
+        .interactive(&cmd)
+        .map_err(|e| Error::spawn("tmux", e))?;
+
+    if !success {
+        return Err(Error::Tmux(subcommand.to_string()));
+    }
+    Ok(())
+}
+
+/// `ssh -t` でリモートの tmux セッションを作成・アタッチする
+pub fn start_remote(runner: &dyn CommandRunner, host: &str, spec: &SessionSpec) -> Result<()> {
+    let cmd = Cmd::new("ssh")
+        .args(["-t", host])
+        .arg(format!("sh -c {}", shell::quote(&spec.remote_script())));
+    let success = runner
+        .interactive(&cmd)
+        .map_err(|e| Error::spawn("ssh", e))?;
+
+    if !success {
+        return Err(Error::RemoteSession(host.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_the_features_the_layout_needs() {
+        let mut spec = SessionSpec::new(&Location::Local(PathBuf::from("/srv/app")));
+        assert_eq!(spec.features(), [Feature::StartDirectory]);
+        spec.options.insert("mouse".into(), "on".into());
+        spec.options.insert("status-style".into(), "bg=blue".into());
+        assert_eq!(
+            spec.features(),
+            [
+                Feature::StartDirectory,
+                Feature::MouseOption,
+                Feature::StyleOptions
+            ]
+        );
+    }
+
+    #[test]
+    fn popups_need_tmux_3_2() {
+        let version = |output| TmuxVersion::parse(output).unwrap();
+        assert!(require(version("tmux 3.2a"), Feature::Popup).is_ok());
+        let error = require(version("tmux 3.1c"), Feature::Popup).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::TmuxTooOld {
+                feature: "display-popup",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+//! シェルのクォートと実行ファイルの検索
+
+/// POSIX シェル向けにシングルクォートで囲む
+pub fn quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// パスをクォートする。先頭の `~` はリモート側で展開されるようクォートの外に残す
+pub fn quote_path(path: &str) -> String {
+    if path == "~" {
+        return path.to_string();
+    }
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", quote(rest)),
+        None => quote(path),
+    }
+}
+
+/// PATH 上に実行ファイルがあるか（Windows では `.exe` を補う）
+pub fn command_exists(name: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&paths).any(|dir| {
+        let path = dir.join(name);
+        path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file())
+    })
+}
@@ -0,0 +1,236 @@
+//! 実行をまたいで覚えておく状態（最近開いたプロジェクトや作ったセッションなど）
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::cache;
+use crate::config::SortOrder;
+use crate::location::Location;
+use crate::migrate::{self, Outcome};
+use crate::paths;
+use crate::session::SessionSpec;
+use crate::timelog::Span;
+
+/// 覚えておく最近のプロジェクトの数
+pub const MAX_RECENT: usize = 20;
+
+/// 作業時間の記録を残す期間（秒）。これより前に終わった記録は捨てる
+const SPAN_RETENTION: u64 = 53 * 7 * 24 * 60 * 60;
+
+/// `$XDG_STATE_HOME/projector/state.json` に保存する状態
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// 最近開いたプロジェクト（[`Location::spec`] の形式）。新しい順
+    pub recent: Vec<String>,
+    /// projector が作ったローカルの tmux セッション。再起動後に `projector resurrect` で作り直す
+    pub sessions: Vec<SessionSpec>,
+    /// TUI を終了したときの表示の設定。次の起動はこの状態から始める
+    pub view: ViewPrefs,
+    /// TUI を終了したときに見ていた場所。設定の `resume` が有効なときだけ覚える
+    pub browse: BrowsePosition,
+    /// プロジェクト（[`Location::spec`] の形式）ごとの開いた回数。一覧をよく使う順に並べるのに使う
+    pub visits: BTreeMap<String, Visits>,
+    /// ローカルの tmux のセッションで作業した時間の記録（[`crate::timelog`]）。古い順
+    pub spans: Vec<Span>,
+    /// このバージョンより新しい projector が書いた状態。書き戻すと知らない値を落とすので保存しない
+    #[serde(skip)]
+    pub from_newer_version: bool,
+}
+
+/// プロジェクトを開いた回数と最後に開いた日時
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Visits {
+    /// 開いた回数
+    pub count: u32,
+    /// 最後に開いた UNIX 時刻（秒）
+    pub last: u64,
+}
+
+impl Visits {
+    /// 回数に最後に開いてからの経過で重みを付けた点数（frecency）
+    ///
+    /// zoxide と同じく、1時間以内なら4倍、1日以内なら2倍、1週間以内ならそのまま、
+    /// それより前なら半分にする。
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let weight = match age {
+            0..=3_599 => 4.0,
+            3_600..=86_399 => 2.0,
+            86_400..=604_799 => 1.0,
+            _ => 0.5,
+        };
+        f64::from(self.count) * weight
+    }
+}
+
+/// TUI で見ていたディレクトリと選択（[`Location::spec`] の形式）
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrowsePosition {
+    /// 見ていたディレクトリ。None はルート一覧
+    pub current: Option<String>,
+    /// 選んでいたエントリ
+    pub selected: Option<String>,
+}
+
+impl BrowsePosition {
+    /// 見ていた場所を覚える
+    pub fn new(current: Option<&Location>, selected: Option<&Location>) -> Self {
+        BrowsePosition {
+            current: current.map(Location::spec),
+            selected: selected.map(Location::spec),
+        }
+    }
+
+    /// 見ていたディレクトリ
+    pub fn current(&self) -> Option<Location> {
+        self.current.as_deref().map(Location::parse)
+    }
+
+    /// 選んでいたエントリ
+    pub fn selected(&self) -> Option<Location> {
+        self.selected.as_deref().map(Location::parse)
+    }
+}
+
+/// TUI で切り替えた表示の設定
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewPrefs {
+    /// 切り替えた並び順。None なら設定ファイルの `sort`
+    pub sort: Option<SortOrder>,
+    /// 隠しディレクトリも並べる
+    pub hidden: bool,
+    /// ディレクトリを辿らず、全ルートのプロジェクトを並べる
+    pub flat: bool,
+}
+
+impl State {
+    /// 保存した状態を読み込む。無いか壊れていれば空の状態
+    pub fn load() -> State {
+        let Some(path) = state_file() else {
+            return State::default();
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return State::default();
+        };
+        let broken = |e: serde_json::Error| {
+            warn!(path = %path.display(), error = %e, "ignoring broken state file");
+            State::default()
+        };
+        let mut value = match serde_json::from_str(&content) {
+            Ok(value) => value,
+            Err(e) => return broken(e),
+        };
+        let outcome = migrate::state(&mut value);
+        let mut state = serde_json::from_value(value).unwrap_or_else(broken);
+        state.from_newer_version = matches!(outcome, Outcome::Newer(_));
+        state
+    }
+
+    /// 状態を形式のバージョンと一緒に書き出す。失敗しても動作には影響しないのでログに残すだけ
+    pub fn save(&self) {
+        let Some(path) = state_file() else {
+            return;
+        };
+        if self.from_newer_version {
+            warn!(path = %path.display(), "not overwriting state written by a newer projector");
+            return;
+        }
+        let write = || {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut value = serde_json::to_value(self)?;
+            migrate::stamp_state(&mut value);
+            fs::write(&path, serde_json::to_string_pretty(&value)?)
+        };
+        if let Err(e) = write() {
+            warn!(path = %path.display(), error = %e, "could not save state");
+        }
+    }
+
+    /// 開いたプロジェクトを最近の先頭に置く
+    pub fn record_open(&mut self, location: &Location) {
+        let spec = location.spec();
+        self.recent.retain(|recent| *recent != spec);
+        self.recent.insert(0, spec);
+        self.recent.truncate(MAX_RECENT);
+        let visits = self.visits.entry(location.spec()).or_default();
+        visits.count = visits.count.saturating_add(1);
+        visits.last = cache::now();
+    }
+
+    /// 開いたことのあるプロジェクトの frecency。点数の高いものほどよく使う
+    pub fn frecency(&self) -> HashMap<Location, f64> {
+        let now = cache::now();
+        self.visits
+            .iter()
+            .map(|(spec, visits)| (Location::parse(spec), visits.score(now)))
+            .collect()
+    }
+
+    /// 作ったセッションを覚える。同じ名前のものは置き換える
+    pub fn record_session(&mut self, spec: &SessionSpec) {
+        match self.sessions.iter_mut().find(|s| s.name == spec.name) {
+            Some(session) => *session = spec.clone(),
+            None => self.sessions.push(spec.clone()),
+        }
+    }
+
+    /// ローカルの tmux に開いたセッションの記録を始める。同じセッションの記録が続いていればそのまま
+    pub fn record_session_start(&mut self, location: &Location, session: &str) {
+        if self
+            .spans
+            .iter()
+            .any(|span| span.open && span.session == session)
+        {
+            return;
+        }
+        let span = Span::start(location.spec(), session.to_string(), cache::now());
+        self.spans.push(span);
+    }
+
+    /// 動いているセッションの最後に使った時刻で記録を延ばし、無くなったセッションの記録を閉じる。
+    /// 記録が変わったら true
+    pub fn update_spans(&mut self, activity: &HashMap<String, u64>, now: u64) -> bool {
+        let before = self.spans.len();
+        self.spans
+            .retain(|span| span.open || span.end + SPAN_RETENTION > now);
+        let mut changed = self.spans.len() != before;
+        for span in self.spans.iter_mut().filter(|span| span.open) {
+            match activity.get(&span.session) {
+                // 開く前に使っていたセッションにつないだだけなら、開いた時刻のまま
+                Some(&time) if time.min(now) > span.end => {
+                    span.end = time.min(now);
+                    changed = true;
+                }
+                Some(_) => {}
+                None => {
+                    span.open = false;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// 1つ前に開いたプロジェクト（`cd -` のように直前の2つを行き来する）。
+    /// 1つしか開いていなければそれ
+    pub fn previous(&self) -> Option<Location> {
+        self.recent
+            .get(1)
+            .or_else(|| self.recent.first())
+            .map(|spec| Location::parse(spec))
+    }
+}
+
+/// 状態を保存するファイル (`state_dir()` の下の state.json)
+pub fn state_file() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join("state.json"))
+}
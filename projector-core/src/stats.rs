@@ -0,0 +1,208 @@
+//! プロジェクトの統計（ディスク使用量・言語・TODO）
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+/// du と同様にディレクトリ以下の使用量をバイト単位で数える（シンボリックリンクは辿らない）
+pub fn disk_usage(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut stack: Vec<PathBuf> = vec![path.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            total += allocated_size(&metadata);
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            }
+        }
+    }
+
+    total
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks は 512 バイト単位
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// バイト数を KiB / MiB などの単位付きで表す
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// 言語ごとのファイル数と行数（空行を除く）
+pub struct LanguageStat {
+    /// 言語名
+    pub name: &'static str,
+    /// ファイル数
+    pub files: usize,
+    /// 空行を除いた行数
+    pub lines: usize,
+}
+
+// 1ファイルあたりの読み込み上限。巨大な生成ファイルで固まらないようにする
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// tokei 風に .gitignore を尊重しながら言語別の行数を数える。行数の多い順に並べて返す
+pub fn languages(path: &Path) -> Vec<LanguageStat> {
+    let mut stats: Vec<LanguageStat> = Vec::new();
+
+    for entry in WalkBuilder::new(path).build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Some(name) = language_of(entry.path()) else {
+            continue;
+        };
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+        let Ok(content) = fs::read(entry.path()) else {
+            continue;
+        };
+
+        let lines = content
+            .split(|&b| b == b'\n')
+            .filter(|line| line.iter().any(|b| !b.is_ascii_whitespace()))
+            .count();
+
+        match stats.iter_mut().find(|s| s.name == name) {
+            Some(stat) => {
+                stat.files += 1;
+                stat.lines += lines;
+            }
+            None => stats.push(LanguageStat {
+                name,
+                files: 1,
+                lines,
+            }),
+        }
+    }
+
+    stats.sort_by(|a, b| b.lines.cmp(&a.lines).then(a.name.cmp(b.name)));
+    stats
+}
+
+fn language_of(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+    match file_name {
+        "Dockerfile" => return Some("Dockerfile"),
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("Makefile"),
+        _ => {}
+    }
+
+    let language = match path.extension()?.to_str()? {
+        "rs" => "Rust",
+        "go" => "Go",
+        "py" => "Python",
+        "rb" => "Ruby",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "mts" | "cts" | "tsx" => "TypeScript",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "php" => "PHP",
+        "sh" | "bash" | "zsh" => "Shell",
+        "lua" => "Lua",
+        "ex" | "exs" => "Elixir",
+        "hs" => "Haskell",
+        "ml" | "mli" => "OCaml",
+        "scala" => "Scala",
+        "dart" => "Dart",
+        "zig" => "Zig",
+        "nix" => "Nix",
+        "tf" => "Terraform",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "sql" => "SQL",
+        "md" => "Markdown",
+        "toml" => "TOML",
+        "yml" | "yaml" => "YAML",
+        "json" => "JSON",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// TODO / FIXME マーカーの出現数
+#[derive(Default)]
+pub struct TodoCount {
+    /// TODO の数
+    pub todo: usize,
+    /// FIXME の数
+    pub fixme: usize,
+}
+
+/// .gitignore を尊重しながらテキストファイル中の TODO / FIXME を数える
+pub fn todos(path: &Path) -> TodoCount {
+    let mut count = TodoCount::default();
+
+    for entry in WalkBuilder::new(path).build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+            continue;
+        }
+        let Ok(content) = fs::read(entry.path()) else {
+            continue;
+        };
+        // NUL を含むファイルはバイナリとみなす
+        if content.iter().take(8192).any(|&b| b == 0) {
+            continue;
+        }
+
+        count.todo += count_marker(&content, b"TODO");
+        count.fixme += count_marker(&content, b"FIXME");
+    }
+
+    count
+}
+
+/// 識別子の一部（TODOS や MY_TODO など）を除いてマーカーを数える
+fn count_marker(content: &[u8], marker: &[u8]) -> usize {
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    content
+        .windows(marker.len())
+        .enumerate()
+        .filter(|(i, window)| {
+            *window == marker
+                && (*i == 0 || !is_ident(content[i - 1]))
+                && content.get(i + marker.len()).is_none_or(|&b| !is_ident(b))
+        })
+        .count()
+}
@@ -0,0 +1,306 @@
+//! プロジェクトのタスクランナーの検出
+//!
+//! justfile / Makefile / package.json の scripts / Taskfile.yml から実行できるタスクを集める。
+//! それぞれのツールを起動せずにファイルを読むだけなので、凝った書き方のものは拾えないことがある。
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+const TASKFILES: &[&str] = &[
+    "Taskfile.yml",
+    "Taskfile.yaml",
+    "taskfile.yml",
+    "taskfile.yaml",
+];
+
+/// タスクの定義元
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskSource {
+    /// justfile
+    Just,
+    /// Makefile
+    Make,
+    /// package.json の scripts
+    Npm,
+    /// Taskfile.yml
+    Task,
+}
+
+impl TaskSource {
+    /// 表示用の名前
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskSource::Just => "just",
+            TaskSource::Make => "make",
+            TaskSource::Npm => "package.json",
+            TaskSource::Task => "Taskfile",
+        }
+    }
+}
+
+/// 実行できるタスク
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task {
+    /// タスク名
+    pub name: String,
+    /// プロジェクトのディレクトリで実行するコマンド
+    pub command: String,
+    /// 定義元
+    pub source: TaskSource,
+}
+
+/// プロジェクトのタスクを定義元ごとに、ファイルに書かれた順で返す
+pub fn detect(project: &Path) -> Vec<Task> {
+    let mut tasks = vec![];
+    if let Some(content) = read_first(project, &["justfile", "Justfile", ".justfile"]) {
+        tasks.extend(just_recipes(&content).map(|name| Task {
+            command: format!("just {}", name),
+            name,
+            source: TaskSource::Just,
+        }));
+    }
+    if let Some(content) = read_first(project, &["GNUmakefile", "makefile", "Makefile"]) {
+        tasks.extend(make_targets(&content).map(|name| Task {
+            command: format!("make {}", name),
+            name,
+            source: TaskSource::Make,
+        }));
+    }
+    if let Some(content) = read_first(project, &["package.json"]) {
+        let runner = node_runner(project);
+        tasks.extend(npm_scripts(&content).into_iter().map(|name| Task {
+            command: format!("{} run {}", runner, name),
+            name,
+            source: TaskSource::Npm,
+        }));
+    }
+    if let Some(content) = read_first(project, TASKFILES) {
+        tasks.extend(taskfile_tasks(&content).map(|name| Task {
+            command: format!("task {}", name),
+            name,
+            source: TaskSource::Task,
+        }));
+    }
+    tasks
+}
+
+/// 候補のうち最初に見つかったファイルの中身
+fn read_first(project: &Path, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| fs::read_to_string(project.join(name)).ok())
+}
+
+/// `name args...:` で始まるレシピ。`_` で始まる非公開のものと設定行は除く
+fn just_recipes(content: &str) -> impl Iterator<Item = String> + '_ {
+    content.lines().filter_map(|line| {
+        if line.starts_with([' ', '\t', '#', '_', '@', '[']) || line.contains(":=") {
+            return None;
+        }
+        let (head, _) = line.split_once(':')?;
+        let name = head.split_whitespace().next()?;
+        if matches!(name, "set" | "alias" | "export" | "import" | "mod") {
+            return None;
+        }
+        let valid = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| name.to_string())
+    })
+}
+
+/// 明示的なターゲット。`.PHONY` などの特殊ターゲット、パターンルール、変数の代入は除く
+fn make_targets(content: &str) -> impl Iterator<Item = String> + '_ {
+    let mut seen = vec![];
+    content.lines().filter_map(move |line| {
+        if line.starts_with([' ', '\t', '#', '.']) {
+            return None;
+        }
+        let (head, rest) = line.split_once(':')?;
+        // `:=` や `::=` は代入
+        if rest.starts_with('=') || head.contains('=') || head.contains(['%', '$']) {
+            return None;
+        }
+        let name = head.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) || seen.iter().any(|s| s == name) {
+            return None;
+        }
+        seen.push(name.to_string());
+        Some(name.to_string())
+    })
+}
+
+/// package.json の scripts の名前
+fn npm_scripts(content: &str) -> Vec<String> {
+    let Ok(json) = serde_json::from_str::<Value>(content) else {
+        return vec![];
+    };
+    json.get("scripts")
+        .and_then(Value::as_object)
+        .map(|scripts| scripts.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// ロックファイルから使っているパッケージマネージャーを決める
+fn node_runner(project: &Path) -> &'static str {
+    [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .into_iter()
+    .find(|(lock, _)| project.join(lock).exists())
+    .map_or("npm", |(_, runner)| runner)
+}
+
+/// Taskfile.yml の `tasks:` の直下のキー。YAML を解釈せず、インデントで判断する
+fn taskfile_tasks(content: &str) -> impl Iterator<Item = String> + '_ {
+    let mut in_tasks = false;
+    let mut indent = None;
+    content.lines().filter_map(move |line| {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return None;
+        }
+        let depth = line.len() - trimmed.len();
+        if depth == 0 {
+            in_tasks = trimmed.trim_end() == "tasks:";
+            indent = None;
+            return None;
+        }
+        if !in_tasks || *indent.get_or_insert(depth) != depth {
+            return None;
+        }
+        let (name, _) = trimmed.split_once(':')?;
+        let name = name.trim().trim_matches(['"', '\'']);
+        (!name.is_empty()).then(|| name.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    /// `files` の（名前, 中身）を置いたプロジェクトのタスクを `定義元 名前 = コマンド` で返す
+    fn tasks(files: &[(&str, &str)]) -> Vec<String> {
+        let project = TempDir::new().unwrap();
+        for (name, content) in files {
+            fs::write(project.path().join(name), content).unwrap();
+        }
+        detect(project.path())
+            .into_iter()
+            .map(|task| format!("{} {} = {}", task.source.label(), task.name, task.command))
+            .collect()
+    }
+
+    #[test]
+    fn reads_just_recipes() {
+        let justfile = "\
+set shell := [\"bash\", \"-c\"]
+version := \"1.0\"
+alias b := build
+
+# ビルドする
+build target='debug':
+    cargo build
+[private]
+_helper:
+    true
+test: build
+    cargo test
+";
+        assert_eq!(
+            tasks(&[("justfile", justfile)]),
+            ["just build = just build", "just test = just test"]
+        );
+    }
+
+    #[test]
+    fn reads_make_targets() {
+        let makefile = "\
+CC := gcc
+.PHONY: all clean
+all: main
+main: main.o
+\tgcc -o main main.o
+%.o: %.c
+\tgcc -c $<
+clean:
+\trm -f main
+main: extra.o
+";
+        assert_eq!(
+            tasks(&[("Makefile", makefile)]),
+            [
+                "make all = make all",
+                "make main = make main",
+                "make clean = make clean"
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_npm_scripts_with_the_locked_package_manager() {
+        let package = r#"{"name": "web", "scripts": {"dev": "vite", "build": "vite build"}}"#;
+        assert_eq!(
+            tasks(&[("package.json", package)]),
+            [
+                "package.json build = npm run build",
+                "package.json dev = npm run dev"
+            ]
+        );
+        assert_eq!(
+            tasks(&[("package.json", package), ("pnpm-lock.yaml", "")]),
+            [
+                "package.json build = pnpm run build",
+                "package.json dev = pnpm run dev"
+            ]
+        );
+        assert!(tasks(&[("package.json", "{ not json")]).is_empty());
+    }
+
+    #[test]
+    fn reads_taskfile_tasks() {
+        let taskfile = "\
+version: '3'
+vars:
+  NAME: app
+tasks:
+  # 開発サーバー
+  dev:
+    cmds:
+      - go run .
+  \"lint\":
+    cmds:
+      - golangci-lint run
+";
+        assert_eq!(
+            tasks(&[("Taskfile.yml", taskfile)]),
+            ["Taskfile dev = task dev", "Taskfile lint = task lint"]
+        );
+    }
+
+    #[test]
+    fn lists_every_source_in_a_fixed_order() {
+        let files = [
+            ("Taskfile.yml", "tasks:\n  up:\n"),
+            ("package.json", r#"{"scripts": {"start": "node ."}}"#),
+            ("Makefile", "install:\n"),
+            ("justfile", "fmt:\n"),
+        ];
+        assert_eq!(
+            tasks(&files),
+            [
+                "just fmt = just fmt",
+                "make install = make install",
+                "package.json start = npm run start",
+                "Taskfile up = task up"
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! 設定ファイルに書くコマンドのテンプレート
+//!
+//! `code {path}` のように `{変数名}` を値で置き換える。値はシェル向けにクォートするので、
+//! 空白を含むパスでもそのまま `sh -c` に渡せる。`{{` と `}}` はそれぞれ `{` と `}` になる。
+//!
+//! プロジェクトについて使える変数は [`Vars::project`] を参照。
+
+use crate::git;
+use crate::location::Location;
+use crate::runner::CommandRunner;
+use crate::shell;
+
+/// テンプレートに渡す変数の組
+#[derive(Clone, Debug, Default)]
+pub struct Vars(Vec<(&'static str, String)>);
+
+impl Vars {
+    /// プロジェクトについての変数を集める
+    ///
+    /// - `{path}`: プロジェクトのパス
+    /// - `{name}`: ディレクトリ名
+    /// - `{session}`: tmux のセッション名
+    /// - `{root}`: プロジェクトを含むルート（ルートの外なら親ディレクトリ）
+    /// - `{branch}`: 現在の git ブランチ（ローカルの git リポジトリのときだけ）
+    /// - `{host}`: リモートの接続先（ローカルなら空）
+    pub fn project(
+        runner: &dyn CommandRunner,
+        location: &Location,
+        roots: &[Location],
+        session: &str,
+    ) -> Self {
+        let root = roots
+            .iter()
+            .find(|root| root.contains(location))
+            .cloned()
+            .or_else(|| location.parent())
+            .unwrap_or_else(|| location.clone());
+        let branch = location
+            .local_path()
+            .and_then(|path| git::current_branch(runner, path))
+            .unwrap_or_default();
+        Vars(vec![
+            ("path", location.path_str()),
+            ("name", location.name()),
+            ("session", session.to_string()),
+            ("root", root.path_str()),
+            ("branch", branch),
+            ("host", location.host().unwrap_or_default().to_string()),
+        ])
+    }
+
+    /// 変数を展開する
+    pub fn render(&self, template: &str) -> String {
+        let vars: Vec<(&str, &str)> = self.0.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        render(template, &vars)
+    }
+}
+
+/// テンプレートを展開する。知らない変数名はそのまま残す
+///
+/// 値の先頭の `~/` はリモートのシェルで展開されるようクォートの外に残す。
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(|r| r.split_once('}'))
+            .and_then(|(name, after)| {
+                let (_, value) = vars.iter().find(|(var, _)| *var == name)?;
+                Some((value, after))
+            });
+        match placeholder {
+            Some((value, after)) => {
+                out.push_str(&shell::quote_path(value));
+                rest = after;
+            }
+            None => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,237 @@
+//! セッションで作業した時間の記録（`projector stats`）
+//!
+//! projector がローカルの tmux にセッションを開いたときに始まりを記録し、その後は tmux の
+//! `session_activity`（最後にキー入力などがあった時刻）を調べるたびに終わりを延ばす。
+//! セッションが無くなっていれば、最後に見た時刻で閉じる。調べるのは projector を実行したときと
+//! デーモンが一覧を作り直すときなので、デーモンが動いていないと終わりの時刻は粗くなる。
+//! 記録はローカルの状態ファイルに残すだけで、どこにも送らない。
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache;
+use crate::runner::{Cmd, CommandRunner};
+use crate::state::State;
+
+const DAY: u64 = 24 * 60 * 60;
+const WEEK: u64 = 7 * DAY;
+
+/// セッションを開いていた1回分の時間
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    /// プロジェクト（[`Location::spec`](crate::location::Location::spec) の形式）
+    pub project: String,
+    /// tmux のセッション名
+    pub session: String,
+    /// 開いた UNIX 時刻（秒）
+    pub start: u64,
+    /// 最後に使っていた UNIX 時刻（秒）
+    pub end: u64,
+    /// セッションがまだ動いている（終わりが延びうる）
+    pub open: bool,
+}
+
+impl Span {
+    /// 開いたセッションの記録を始める
+    pub fn start(project: String, session: String, now: u64) -> Self {
+        Span {
+            project,
+            session,
+            start: now,
+            end: now,
+            open: true,
+        }
+    }
+}
+
+/// ローカルの tmux のセッションごとの最後に使った時刻。tmux を実行できなければ None
+///
+/// tmux のサーバーが動いていなければセッションは1つも無いので、空にする。
+pub fn activity(runner: &dyn CommandRunner) -> Option<HashMap<String, u64>> {
+    let cmd = Cmd::new("tmux")
+        // tmux はタブなどの制御文字を `_` に置き換えて出力するので、空白の無い時刻を先にする
+        .args(["list-sessions", "-F", "#{session_activity} #{session_name}"])
+        .read_only();
+    let output = runner.output(&cmd).ok()?;
+    if !output.success {
+        return Some(HashMap::new());
+    }
+    Some(
+        output
+            .stdout_str()
+            .lines()
+            .filter_map(|line| {
+                let (time, name) = line.split_once(' ')?;
+                Some((name.to_string(), time.parse().ok()?))
+            })
+            .collect(),
+    )
+}
+
+/// 動いているセッションを調べて記録を更新し、変わっていれば保存する
+pub fn poll(runner: &dyn CommandRunner) {
+    if runner.dry_run() {
+        return;
+    }
+    let Some(activity) = activity(runner) else {
+        return;
+    };
+    let mut state = State::load();
+    if state.update_spans(&activity, cache::now()) {
+        state.save();
+    }
+}
+
+/// 1週間分の、プロジェクトごとの作業時間
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Week {
+    /// 週の始まり（UTC の月曜 0 時）の UNIX 時刻
+    pub start: u64,
+    /// プロジェクトと作業時間（秒）。長い順
+    pub projects: Vec<(String, u64)>,
+}
+
+impl Week {
+    /// 週の合計の作業時間（秒）
+    pub fn total(&self) -> u64 {
+        self.projects.iter().map(|(_, seconds)| seconds).sum()
+    }
+}
+
+/// `now` を含む週から `weeks` 週さかのぼって、週ごとにプロジェクトの作業時間を集める。
+/// 新しい週が先で、記録の無い週は含めない
+///
+/// 週をまたいだ記録は、それぞれの週に分けて数える。
+pub fn weekly(spans: &[Span], now: u64, weeks: usize) -> Vec<Week> {
+    let first = week_start(now).saturating_sub(WEEK * weeks.saturating_sub(1) as u64);
+    let mut totals: BTreeMap<u64, HashMap<&str, u64>> = BTreeMap::new();
+    for span in spans {
+        let mut start = span.start.max(first);
+        while start < span.end {
+            let week = week_start(start);
+            let end = span.end.min(week + WEEK);
+            *totals
+                .entry(week)
+                .or_default()
+                .entry(&span.project)
+                .or_default() += end - start;
+            start = end;
+        }
+    }
+    totals
+        .into_iter()
+        .rev()
+        .map(|(start, projects)| {
+            let mut projects: Vec<(String, u64)> = projects
+                .into_iter()
+                .map(|(project, seconds)| (project.to_string(), seconds))
+                .collect();
+            projects.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            Week { start, projects }
+        })
+        .collect()
+}
+
+/// `time` を含む週の始まり（UTC の月曜 0 時）
+pub fn week_start(time: u64) -> u64 {
+    let days = time / DAY;
+    // 1970-01-01 は木曜日
+    let since_monday = (days + 3) % 7;
+    (days - since_monday.min(days)) * DAY
+}
+
+/// UNIX 時刻の日付（UTC、`2024-01-31` の形）
+pub fn format_date(time: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html の civil_from_days
+    let days = (time / DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 作業時間を `3h 05m` や `45m` の形で表す
+pub fn format_duration(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(project: &str, start: u64, end: u64) -> Span {
+        Span {
+            project: project.to_string(),
+            session: project.to_string(),
+            start,
+            end,
+            open: false,
+        }
+    }
+
+    // 2024-01-01 は月曜日
+    const MONDAY: u64 = 1_704_067_200;
+
+    #[test]
+    fn weeks_start_on_monday() {
+        assert_eq!(format_date(MONDAY), "2024-01-01");
+        assert_eq!(week_start(MONDAY), MONDAY);
+        assert_eq!(week_start(MONDAY + 6 * DAY + 3600), MONDAY);
+        assert_eq!(week_start(MONDAY - 1), MONDAY - WEEK);
+        assert_eq!(format_date(MONDAY + 59 * DAY), "2024-02-29");
+    }
+
+    #[test]
+    fn splits_spans_across_weeks() {
+        let spans = [
+            span("a", MONDAY + 3600, MONDAY + 3 * 3600),
+            span("b", MONDAY + 3600, MONDAY + 1800 + 3600),
+            // 日曜の夜から月曜の朝まで
+            span("a", MONDAY + WEEK - 3600, MONDAY + WEEK + 1800),
+        ];
+        let weeks = weekly(&spans, MONDAY + WEEK + DAY, 4);
+        assert_eq!(
+            weeks,
+            [
+                Week {
+                    start: MONDAY + WEEK,
+                    projects: vec![("a".to_string(), 1800)],
+                },
+                Week {
+                    start: MONDAY,
+                    projects: vec![("a".to_string(), 3 * 3600), ("b".to_string(), 1800)],
+                },
+            ]
+        );
+        // 範囲より前の週は数えない
+        assert_eq!(weekly(&spans, MONDAY + WEEK + DAY, 1).len(), 1);
+    }
+
+    #[test]
+    fn closes_spans_of_ended_sessions() {
+        let mut state = State::default();
+        state
+            .spans
+            .push(Span::start("a".to_string(), "a".to_string(), 100));
+        state
+            .spans
+            .push(Span::start("b".to_string(), "b".to_string(), 100));
+        let activity = HashMap::from([("a".to_string(), 400)]);
+        assert!(state.update_spans(&activity, 500));
+        assert_eq!((state.spans[0].end, state.spans[0].open), (400, true));
+        assert_eq!((state.spans[1].end, state.spans[1].open), (100, false));
+        assert!(!state.update_spans(&activity, 600));
+    }
+}
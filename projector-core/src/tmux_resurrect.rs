@@ -0,0 +1,117 @@
+//! tmux-resurrect / tmux-continuum との連携
+//!
+//! tmux-resurrect が保存したセッションを projector が作り直すと、プラグインの復元と二重になる。
+//! 保存ファイルからどのセッションが復元されるかを調べ、必要ならプラグインの復元を先に実行する。
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+/// 復元のために一時的に作るセッション（tmux サーバーが無いと run-shell できない）
+const TEMP_SESSION: &str = "projector-restore";
+
+/// tmux-resurrect の最新の保存ファイル（保存ディレクトリの `last`）
+pub fn last_save(runner: &dyn CommandRunner) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let mut dirs = vec![];
+    // `@resurrect-dir` の設定はサーバーが動いているときだけ分かる
+    let cmd = Cmd::new("tmux")
+        .args(["show-option", "-gqv", "@resurrect-dir"])
+        .read_only();
+    if let Ok(output) = runner.output(&cmd) {
+        let dir = output.stdout_str().trim().to_string();
+        if output.success && !dir.is_empty() {
+            let dir = dir.replace("$HOME", &home.to_string_lossy());
+            dirs.push(match dir.strip_prefix("~/") {
+                Some(rest) => home.join(rest),
+                None => PathBuf::from(dir),
+            });
+        }
+    }
+    let data = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(".local/share"));
+    dirs.push(data.join("tmux/resurrect"));
+    dirs.push(home.join(".tmux/resurrect"));
+
+    dirs.into_iter()
+        .map(|dir| dir.join("last"))
+        .find(|last| last.exists())
+}
+
+/// 保存ファイルにあるセッション名（保存された順）
+pub fn saved_sessions(save: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(save) else {
+        return vec![];
+    };
+    let mut sessions: Vec<String> = vec![];
+    for line in content.lines() {
+        let mut fields = line.split('\t');
+        if !matches!(fields.next(), Some("pane" | "window")) {
+            continue;
+        }
+        if let Some(name) = fields.next().filter(|name| !name.is_empty()) {
+            if !sessions.iter().any(|s| s == name) {
+                sessions.push(name.to_string());
+            }
+        }
+    }
+    sessions
+}
+
+/// インストールされている tmux-resurrect の復元スクリプト
+pub fn restore_script() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+    let mut plugin_dirs: Vec<PathBuf> = env::var_os("TMUX_PLUGIN_MANAGER_PATH")
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    plugin_dirs.push(home.join(".tmux/plugins"));
+    plugin_dirs.push(config.join("tmux/plugins"));
+
+    plugin_dirs
+        .into_iter()
+        .map(|dir| dir.join("tmux-resurrect/scripts/restore.sh"))
+        .find(|script| script.is_file())
+}
+
+/// tmux-resurrect の復元を実行する。tmux サーバーが無ければ一時的なセッションを作ってから行う
+pub fn restore(runner: &dyn CommandRunner, script: &Path) -> Result<()> {
+    let running = runner
+        .output(&Cmd::new("tmux").arg("list-sessions").read_only())
+        .is_ok_and(|output| output.success);
+    if !running {
+        run_tmux(
+            runner,
+            Cmd::new("tmux").args(["new-session", "-d", "-s", TEMP_SESSION]),
+        )?;
+    }
+    run_tmux(runner, Cmd::new("tmux").arg("run-shell").arg(script))?;
+    if !running {
+        // 復元の途中で消されていることがあるので、失敗しても構わない
+        let kill = Cmd::new("tmux").args(["kill-session", "-t", TEMP_SESSION]);
+        if let Err(e) = runner.output(&kill) {
+            debug!(error = %e, "could not remove the temporary session");
+        }
+    }
+    Ok(())
+}
+
+fn run_tmux(runner: &dyn CommandRunner, cmd: Cmd) -> Result<()> {
+    let output = runner.output(&cmd).map_err(|e| Error::spawn("tmux", e))?;
+    if !output.success {
+        let subcommand = cmd.args[0].to_string_lossy().to_string();
+        return Err(Error::Tmux(subcommand));
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! TUI で消したり移したりしたプロジェクトの移動と、その取り消し
+//!
+//! 消したプロジェクトは削除せず、デスクトップのゴミ箱（[`paths::trash_dir`]）に移す。
+//! macOS 以外では freedesktop.org の Trash の形式（`files/` と `info/<名前>.trashinfo`）で置くので、
+//! TUI を終了したあとでもファイルマネージャーから一覧・復元・削除できる。
+//! 大きなプロジェクトを誤って複製しないよう、どの移動も rename だけで、別のファイルシステムへは移さない。
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use crate::paths;
+use crate::{Error, Result};
+
+/// ディレクトリを移す。移動先が既にあれば上書きせずにエラー
+pub fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(Error::AlreadyExists(to.to_path_buf()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::move_failed(from, e))?;
+    }
+    fs::rename(from, to).map_err(|e| match e.kind() {
+        io::ErrorKind::CrossesDevices => Error::CrossDevice {
+            path: from.to_path_buf(),
+            to: to.to_path_buf(),
+        },
+        _ => Error::move_failed(from, e),
+    })?;
+    info!(from = %from.display(), to = %to.display(), "moved directory");
+    Ok(())
+}
+
+/// ディレクトリをゴミ箱に移し、ゴミ箱の中のパスを返す
+pub fn trash(path: &Path) -> Result<PathBuf> {
+    let dir = paths::trash_dir().ok_or(Error::NoHome)?;
+    trash_into(&dir, path)
+}
+
+fn trash_into(dir: &Path, path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::move_failed(path, io::ErrorKind::InvalidInput.into()))?;
+    let files = if cfg!(target_os = "macos") {
+        dir.to_path_buf()
+    } else {
+        dir.join("files")
+    };
+    // 同じ名前のものを何度消しても上書きしないよう番号を付ける
+    let mut n = 0;
+    loop {
+        let trashed = match n {
+            0 => files.join(name),
+            n => files.join(format!("{}.{}", name.to_string_lossy(), n)),
+        };
+        n += 1;
+        if trashed.exists() {
+            continue;
+        }
+        let info = info_file(&trashed);
+        if let Some(info) = &info {
+            // freedesktop の形式では、先に .trashinfo を作って名前を確保する
+            match create_info(info, path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(Error::move_failed(path, e)),
+            }
+        }
+        if let Err(e) = move_dir(path, &trashed) {
+            if let Some(info) = &info {
+                let _ = fs::remove_file(info);
+            }
+            return Err(e);
+        }
+        return Ok(trashed);
+    }
+}
+
+/// ゴミ箱に移したディレクトリを元の場所に戻す
+pub fn restore(trashed: &Path, original: &Path) -> Result<()> {
+    move_dir(trashed, original)?;
+    if let Some(info) = info_file(trashed) {
+        if let Err(e) = fs::remove_file(&info) {
+            warn!(path = %info.display(), error = %e, "could not remove the trash info");
+        }
+    }
+    Ok(())
+}
+
+/// `files/<名前>` に対応する `info/<名前>.trashinfo`。macOS のゴミ箱には無い
+fn info_file(trashed: &Path) -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return None;
+    }
+    let mut name = trashed.file_name()?.to_os_string();
+    name.push(".trashinfo");
+    Some(trashed.parent()?.parent()?.join("info").join(name))
+}
+
+/// 元の場所と消した日時を書いた `.trashinfo` を作る。既にあればエラー
+fn create_info(info: &Path, original: &Path) -> io::Result<()> {
+    if let Some(dir) = info.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().write(true).create_new(true).open(info)?;
+    write!(
+        file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(original),
+        deletion_date(SystemTime::now())
+    )
+}
+
+/// `.trashinfo` の Path。区切りの `/` 以外の予約文字を `%XX` にする
+fn encode_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                char::from(b).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `.trashinfo` の DeletionDate（`YYYY-MM-DDThh:mm:ss`）。タイムゾーンを調べずに UTC で書く
+fn deletion_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rest) = ((secs / 86_400) as i64, secs % 86_400);
+    // 1970-01-01 からの日数を暦の日付にする（Howard Hinnant の civil_from_days）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3_600,
+        rest % 3_600 / 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn formats_the_deletion_date() {
+        assert_eq!(deletion_date(UNIX_EPOCH), "1970-01-01T00:00:00");
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(deletion_date(time), "2024-02-29T12:34:56");
+    }
+
+    #[test]
+    fn encodes_the_original_path() {
+        assert_eq!(
+            encode_path(Path::new("/home/me/my app/日")),
+            "/home/me/my%20app/%E6%97%A5"
+        );
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn trashes_in_the_freedesktop_layout_and_restores() {
+        let home = TempDir::new().unwrap();
+        let trash = home.path().join("Trash");
+        let project = home.path().join("src/app");
+        fs::create_dir_all(&project).unwrap();
+
+        let trashed = trash_into(&trash, &project).unwrap();
+        assert_eq!(trashed, trash.join("files/app"));
+        let info = fs::read_to_string(trash.join("info/app.trashinfo")).unwrap();
+        assert!(info.starts_with(&format!(
+            "[Trash Info]\nPath={}\nDeletionDate=",
+            encode_path(&project)
+        )));
+
+        // 同じ名前のものは番号を付けて並べる
+        fs::create_dir_all(&project).unwrap();
+        assert_eq!(
+            trash_into(&trash, &project).unwrap(),
+            trash.join("files/app.1")
+        );
+
+        restore(&trashed, &project).unwrap();
+        assert!(project.is_dir());
+        assert!(!trash.join("info/app.trashinfo").exists());
+    }
+}
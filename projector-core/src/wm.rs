@@ -0,0 +1,135 @@
+//! ウィンドウマネージャー（Hyprland / i3 / sway）との連携
+//!
+//! プロジェクトを開くときに、ワークスペースをプロジェクト名にそろえて tmux のセッションと
+//! 対応させる。i3 と sway は同じコマンドを受け付けるので、`i3-msg` と `swaymsg` を使い分けるだけ。
+//! Hyprland は `hyprctl dispatch` で同じことをする。
+
+use std::env;
+
+use serde_json::Value;
+
+use crate::config::WorkspaceMode;
+use crate::runner::{Cmd, CommandRunner};
+use crate::{Error, Result};
+
+/// 実行中のウィンドウマネージャー
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowManager {
+    /// Hyprland
+    Hyprland,
+    /// sway
+    Sway,
+    /// i3
+    I3,
+}
+
+impl WindowManager {
+    /// 環境変数（`HYPRLAND_INSTANCE_SIGNATURE` / `SWAYSOCK` / `I3SOCK`）から判断する。
+    /// どれでもなければ None
+    pub fn detect() -> Option<WindowManager> {
+        let set = |var| env::var_os(var).is_some_and(|value| !value.is_empty());
+        if set("HYPRLAND_INSTANCE_SIGNATURE") {
+            Some(WindowManager::Hyprland)
+        } else if set("SWAYSOCK") {
+            Some(WindowManager::Sway)
+        } else if set("I3SOCK") {
+            Some(WindowManager::I3)
+        } else {
+            None
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            WindowManager::Hyprland => "hyprctl",
+            WindowManager::Sway => "swaymsg",
+            WindowManager::I3 => "i3-msg",
+        }
+    }
+
+    /// プロジェクト名のワークスペースを用意する
+    pub fn prepare_workspace(
+        self,
+        runner: &dyn CommandRunner,
+        mode: WorkspaceMode,
+        name: &str,
+    ) -> Result<()> {
+        if self == WindowManager::Hyprland {
+            let args = match mode {
+                WorkspaceMode::Rename => {
+                    vec![
+                        "renameworkspace".to_string(),
+                        active_workspace(runner)?,
+                        name.to_string(),
+                    ]
+                }
+                WorkspaceMode::Move => {
+                    vec!["movetoworkspace".to_string(), format!("name:{}", name)]
+                }
+            };
+            return self.run(runner, Cmd::new("hyprctl").arg("dispatch").args(args));
+        }
+        let workspace = quote(name);
+        let command = match mode {
+            WorkspaceMode::Rename => format!("rename workspace to {}", workspace),
+            WorkspaceMode::Move => {
+                format!("move container to workspace {0}; workspace {0}", workspace)
+            }
+        };
+        self.run(runner, Cmd::new(self.program()).arg(command))
+    }
+
+    /// `command` を `sh -c` で実行する端末を、名前の付いたワークスペースに開く
+    ///
+    /// Hyprland では起動したウィンドウだけをそのワークスペースに置くルールを付ける。
+    /// i3 / sway はワークスペースを切り替えてから起動する。
+    pub fn open_terminal(
+        self,
+        runner: &dyn CommandRunner,
+        workspace: &str,
+        command: &str,
+    ) -> Result<()> {
+        let cmd = match self {
+            WindowManager::Hyprland => Cmd::new("hyprctl").args([
+                "dispatch".to_string(),
+                "exec".to_string(),
+                format!("[workspace name:{}] {}", workspace, command),
+            ]),
+            WindowManager::Sway | WindowManager::I3 => Cmd::new(self.program()).arg(format!(
+                "workspace {}; exec {}",
+                quote(workspace),
+                quote(command)
+            )),
+        };
+        self.run(runner, cmd)
+    }
+
+    fn run(self, runner: &dyn CommandRunner, cmd: Cmd) -> Result<()> {
+        let program = self.program();
+        let output = runner.output(&cmd).map_err(|e| Error::spawn(program, e))?;
+        if !output.success {
+            return Err(Error::CommandFailed(program.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Hyprland の今のワークスペースの ID
+fn active_workspace(runner: &dyn CommandRunner) -> Result<String> {
+    let cmd = Cmd::new("hyprctl")
+        .args(["activeworkspace", "-j"])
+        .read_only();
+    let output = runner
+        .output(&cmd)
+        .map_err(|e| Error::spawn("hyprctl", e))?;
+    serde_json::from_str::<Value>(&output.stdout_str())
+        .ok()
+        .and_then(|json| json.get("id").and_then(Value::as_i64))
+        .map(|id| id.to_string())
+        .ok_or_else(|| Error::CommandFailed("hyprctl activeworkspace".to_string()))
+}
+
+/// i3 のコマンドの文字列として引用する
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,46 @@
+//! Windows Terminal でプロジェクトを開く
+//!
+//! tmux の無い Windows では、セッションの代わりに Windows Terminal の新しいタブを開き、
+//! レイアウトのペインを `split-pane` で並べる。リモートのプロジェクトはタブの中で
+//! `ssh -t` してリモートの tmux にアタッチする。
+
+use crate::runner::{Cmd, CommandRunner};
+use crate::session::SessionSpec;
+use crate::shell;
+use crate::{Error, Result};
+
+const PROGRAM: &str = "wt.exe";
+
+/// 新しいタブを開く `wt.exe` の呼び出しを組み立てる
+pub fn open_command(host: Option<&str>, spec: &SessionSpec) -> Cmd {
+    // `-w 0` で今のウィンドウにタブを追加する
+    let cmd = Cmd::new(PROGRAM).args(["-w", "0", "new-tab", "--title", &spec.name]);
+    if let Some(host) = host {
+        return cmd
+            .args(["ssh", "-t", host])
+            .arg(format!("sh -c {}", shell::quote(&spec.remote_script())));
+    }
+
+    let mut cmd = cmd;
+    for (i, command) in spec.panes.iter().enumerate() {
+        if i > 0 {
+            // wt.exe は `;` でサブコマンドを区切る
+            cmd = cmd.args([";", "split-pane", "-V"]);
+        }
+        cmd = cmd.arg("-d").arg(&spec.dir);
+        if let Some(command) = command {
+            cmd = cmd.arg(command);
+        }
+    }
+    cmd
+}
+
+/// Windows Terminal の新しいタブでプロジェクトを開く
+pub fn open(runner: &dyn CommandRunner, host: Option<&str>, spec: &SessionSpec) -> Result<()> {
+    let cmd = open_command(host, spec);
+    let output = runner.output(&cmd).map_err(|e| Error::spawn(PROGRAM, e))?;
+    if !output.success {
+        return Err(Error::CommandFailed(cmd.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,1760 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use projector_core::compose::{self, ComposeStatus};
+use projector_core::config::{Backend, Config, HealthConfig, HintConfig, SortOrder};
+use projector_core::forge::{CiStatus, Forges, OpenCounts};
+use projector_core::i18n::Msg;
+use projector_core::location::{self, Entry, Location};
+use projector_core::matcher::{FuzzyMatcher, REGEX_PREFIX};
+use projector_core::monorepo::{Monorepo, MonorepoKind};
+use projector_core::nix::{self, NixKind};
+use projector_core::project_type;
+use projector_core::runner::CommandRunner;
+use projector_core::scan::{self, Directory, ScanOptions};
+use projector_core::session;
+use projector_core::state::{BrowsePosition, ViewPrefs};
+use projector_core::stats::{self, LanguageStat, TodoCount};
+use projector_core::tasks::{self, Task};
+use projector_core::wm::WindowManager;
+use projector_core::{
+    daemon, devcontainer, git, index, paths, profile, search, shell, trash, Error, Result,
+};
+use tracing::warn;
+
+use crate::input::{Edit, TextInput};
+use crate::keys::Keymap;
+use crate::worker::Worker;
+
+/// お知らせを出しておく時間。エラーは読めるよう長めにする
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const ERROR_TOAST_DURATION: Duration = Duration::from_secs(8);
+
+/// 同時に出すお知らせの数。超えたら古いものから消す
+const MAX_TOASTS: usize = 3;
+
+/// 全ルートを辿るのがこれより長引いたら進み具合を表示する
+const SCAN_PROGRESS_DELAY: Duration = Duration::from_millis(100);
+
+/// 選択したプロジェクトに対して行う操作
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    /// tmux セッションを開く
+    Open,
+    /// devcontainer を起動し、コンテナ内のシェルで tmux セッションを開く
+    Devcontainer,
+    /// docker compose up してから tmux セッションを開く
+    Compose,
+    /// 2つ目のペインで lazygit などの git の UI を起動した tmux セッションを開く
+    Git,
+    /// セッションを作らずにファイルマネージャーで中を見る（tmux の中なら分割したペインで）
+    Files,
+    /// プロジェクトのセッションの新しいウィンドウでタスクを実行する
+    Task(Task),
+    /// ウィンドウマネージャーのプロジェクトのワークスペースに、セッションを開く端末を起動する
+    Workspace,
+    /// macOS の端末アプリの新しいウィンドウで tmux セッションを開く
+    NewWindow,
+    /// tmux の中から、今のセッションにプロジェクトで始まる新しいウィンドウを開く
+    Window,
+}
+
+impl Action {
+    /// `--format json` で出力する操作名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Open => "open",
+            Action::Devcontainer => "devcontainer",
+            Action::Compose => "compose",
+            Action::Git => "git",
+            Action::Files => "files",
+            Action::Task(_) => "task",
+            Action::Workspace => "workspace",
+            Action::NewWindow => "new-window",
+            Action::Window => "window",
+        }
+    }
+
+    /// 設定の backend によらず tmux が要るか（ペインやウィンドウを使う）
+    pub fn needs_tmux(&self) -> bool {
+        matches!(self, Action::Git | Action::Task(_) | Action::Window)
+    }
+
+    /// この操作で開く tmux セッションの名前
+    pub fn session_name(&self, location: &Location) -> String {
+        let name = session::project_session_name(location);
+        match self {
+            Action::Devcontainer => format!("{}-devcontainer", name),
+            Action::Git => format!("{}-git", name),
+            Action::Open
+            | Action::Compose
+            | Action::Files
+            | Action::Task(_)
+            | Action::Workspace
+            | Action::NewWindow
+            | Action::Window => name,
+        }
+    }
+
+    /// 起動前に表示するメッセージ
+    pub fn message(&self, location: &Location) -> String {
+        let name = location.display();
+        match self {
+            Action::Open => Msg::Opening(&name).to_string(),
+            Action::Devcontainer => Msg::OpeningDevcontainer(&name).to_string(),
+            Action::Compose => Msg::OpeningCompose(&name).to_string(),
+            Action::Git => Msg::OpeningGit(&name).to_string(),
+            Action::Files => Msg::OpeningFiles(&name).to_string(),
+            Action::Task(task) => Msg::RunningTask {
+                name: &name,
+                command: &task.command,
+            }
+            .to_string(),
+            Action::Workspace => Msg::OpeningWorkspace(&name).to_string(),
+            Action::NewWindow => Msg::OpeningNewWindow(&name).to_string(),
+            Action::Window => Msg::OpeningWindow(&name).to_string(),
+        }
+    }
+}
+
+/// ワーカーから届く計算結果
+pub enum Update {
+    DiskUsage(PathBuf, u64),
+    Languages(PathBuf, Vec<LanguageStat>),
+    Todos(PathBuf, TodoCount),
+    CiStatus(PathBuf, Option<CiStatus>),
+    OpenCounts(PathBuf, Option<OpenCounts>),
+    RemoteListing(Location, Vec<Directory>),
+    Index(Vec<Entry>),
+    Search(String, Result<Vec<Entry>>),
+    ComposeStatus(PathBuf, Option<ComposeStatus>),
+    StashCount(PathBuf, usize),
+    GitStatus(PathBuf, Option<u64>, Option<bool>),
+    Upstream(PathBuf, Option<(usize, usize)>),
+}
+
+/// 詳細ペインに表示するプロジェクト情報（None は計算中）
+#[derive(Default)]
+pub struct Details {
+    pub size: Option<u64>,
+    pub languages: Option<Vec<LanguageStat>>,
+    pub todos: Option<TodoCount>,
+    /// HEAD のコミットの UNIX 時刻（git リポジトリでない・計算中は None）
+    pub last_commit: Option<u64>,
+    /// コミットしていない変更があるか（git リポジトリでない・計算中は None）
+    pub dirty: Option<bool>,
+    /// GitHub / GitLab 以外のリポジトリは Some(None)
+    pub open_counts: Option<Option<OpenCounts>>,
+    pub devcontainer: bool,
+    pub compose: bool,
+    pub nix: Option<NixKind>,
+    /// docker が使えない場合は Some(None)
+    pub compose_status: Option<Option<ComposeStatus>>,
+}
+
+/// 一覧に並べているもの
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum View {
+    /// 今いるディレクトリの中身
+    #[default]
+    Directory,
+    /// 全ルートのプロジェクト（f）
+    Find,
+    /// 検索語を含むプロジェクト（s）
+    Search(String),
+}
+
+/// 1行入力の用途
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    /// `:` で移動先のパスを入力する
+    Jump,
+    /// `s` でプロジェクトの内容から探す語を入力する
+    Search,
+    /// `m` で選択中のプロジェクトの新しい名前を入力する
+    Rename,
+    /// `N` で今いるディレクトリに作るディレクトリの名前を入力する
+    NewDir,
+}
+
+/// `u` で取り消せる操作
+enum Undo {
+    /// 名前を変えたかアーカイブに移した
+    Move { from: PathBuf, to: PathBuf },
+    /// ゴミ箱に移した
+    Trash { original: PathBuf, trashed: PathBuf },
+}
+
+/// 画面の右下にしばらく出すお知らせ
+pub struct Toast {
+    pub text: String,
+    /// エラーなら赤く出す
+    pub error: bool,
+    until: Instant,
+}
+
+/// 確認ダイアログで「はい」を選んだら行う操作
+enum ConfirmAction {
+    /// ゴミ箱に移す
+    Trash(PathBuf),
+    /// tmux セッションを終了する
+    KillSession(String),
+    /// プロジェクトではなさそうなディレクトリを開く
+    Open(Location),
+    /// 移動先にあるものをゴミ箱に移してから移す
+    Overwrite {
+        from: PathBuf,
+        to: PathBuf,
+        message: String,
+    },
+}
+
+/// 取り消しにくい操作の前に出す、はい・いいえの確認ダイアログ
+pub struct Confirm {
+    /// 尋ねる内容
+    pub message: String,
+    /// 「はい」にフォーカスがある（開いたときは「いいえ」）
+    pub yes: bool,
+    action: ConfirmAction,
+}
+
+/// バックグラウンドで全ルートを辿っている間の進み具合
+struct ScanProgress {
+    started: Instant,
+    /// 読み終えたディレクトリの数
+    scanned: Arc<AtomicUsize>,
+}
+
+impl ScanProgress {
+    fn start() -> Self {
+        ScanProgress {
+            started: Instant::now(),
+            scanned: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// `t` で開くタスクの選択
+pub struct TaskPicker {
+    /// タスクを実行するプロジェクト
+    pub location: Location,
+    pub tasks: Vec<Task>,
+    pub selected: usize,
+}
+
+/// T で開く、種類・タグで絞り込むメニュー
+pub struct TypePicker {
+    /// 選べる種類とタグ。先頭の None は絞り込みをやめる
+    pub options: Vec<Option<String>>,
+    pub selected: usize,
+}
+
+pub struct App {
+    pub roots: Vec<Location>,
+    /// `--stdin` で渡された候補。Some ならルートの代わりにこれを並べる
+    candidates: Option<Vec<Entry>>,
+    /// 解決済みのエイリアス。ルート一覧の先頭に並べる
+    aliases: Vec<Entry>,
+    /// None は全ルート直下をまとめた一覧
+    pub current: Option<Location>,
+    /// 今いるディレクトリをモノレポのパッケージの一覧として並べているなら、その種類
+    packages: Option<MonorepoKind>,
+    /// 一覧に並べているもの
+    pub view: View,
+    /// 全ルートのプロジェクトの一覧。None は作成中
+    index: Option<Vec<Entry>>,
+    /// 開いたことのあるプロジェクトの frecency。全ルートの一覧をよく使う順に並べる
+    frecency: HashMap<Location, f64>,
+    /// 全ルートを辿っている最中なら、その進み具合
+    scan: Option<ScanProgress>,
+    /// 内容の検索結果。None は検索中
+    search_results: Option<Vec<Entry>>,
+    pub path_stack: Vec<Option<Location>>,
+    /// 訪れたディレクトリの履歴。H で戻る
+    history: Vec<Option<Location>>,
+    /// H で戻ってきたディレクトリ。L で進む
+    forward: Vec<Option<Location>>,
+    /// 入れる階層の深さ（ルート直下が 1）
+    max_depth: Option<usize>,
+    scan_options: ScanOptions,
+    pub items: Vec<Entry>,
+    /// 絞り込み後に表示する items の位置（表示順）
+    pub visible: Vec<usize>,
+    /// visible の各エントリで検索語にマッチした文字の位置
+    pub highlights: Vec<Vec<usize>>,
+    /// visible の中での選択位置
+    pub selected: usize,
+    /// `/` で入力中の検索語。None なら絞り込みなし
+    pub filter: Option<TextInput>,
+    /// `o` `.` で切り替えた表示の設定
+    prefs: ViewPrefs,
+    /// 種類（`rust` など）かタグで絞り込んでいるなら、その名前
+    pub type_filter: Option<String>,
+    /// 種類・タグを選んでいる
+    pub type_picker: Option<TypePicker>,
+    /// 設定のタグと、それが付いた場所
+    tags: Vec<(String, Vec<Location>)>,
+    /// 調べたプロジェクトの種類
+    types: HashMap<PathBuf, Vec<&'static str>>,
+    /// 入力中の1行とその用途。None なら入力していない
+    pub prompt: Option<(PromptKind, TextInput)>,
+    /// 選択中のプロジェクトのタスクを選んでいる
+    pub task_picker: Option<TaskPicker>,
+    /// 検索語が正規表現として不正
+    pub filter_invalid: bool,
+    /// 右下に出しているお知らせ（古いものが前）
+    pub toasts: Vec<Toast>,
+    /// 答えを待っている確認ダイアログ
+    pub confirm: Option<Confirm>,
+    /// 閉じるまで出しておくエラー（外部コマンドの失敗など）
+    pub error: Option<String>,
+    /// 取り消せる操作（新しいものが後ろ）
+    undo: Vec<Undo>,
+    /// a でプロジェクトを移す場所（設定の `archive`）
+    archive: String,
+    /// Enter でプロジェクトではなさそうなディレクトリを開く前に確認する（設定の `open.confirm_plain_dirs`）
+    confirm_plain_dirs: bool,
+    /// ヘッダーの操作説明の行
+    pub hint: HintConfig,
+    /// 詳細ペインの健全性の行に出す項目
+    pub health: HealthConfig,
+    /// リーダーキーの後に続けて押すキーの割り当て
+    pub keymap: Keymap,
+    /// リーダーキーの後に押したキー（続きを待っていなければ None）
+    pub chord: Option<String>,
+    matcher: FuzzyMatcher,
+    pub details: HashMap<PathBuf, Details>,
+    /// 取得を開始したプロジェクトの CI ステータス（CI が無い・取得中は None）
+    pub ci: HashMap<PathBuf, Option<CiStatus>>,
+    /// 数えたプロジェクトのスタッシュの数
+    pub stashes: HashMap<PathBuf, usize>,
+    /// upstream より進んでいる・遅れているコミット数（upstream が無い・計算中は None）
+    pub upstream: HashMap<PathBuf, Option<(usize, usize)>>,
+    /// リポジトリを fetch するワーカー（設定の `fetch.enabled` のときだけ）
+    fetcher: Option<Worker<Update>>,
+    /// 同じリポジトリを fetch し直すまでの間隔
+    fetch_interval: Duration,
+    /// CI ステータスやオープンな PR の数を問い合わせるホスティングサービス
+    forges: Arc<Forges>,
+    /// ホスティングサービスの API のレスポンスをキャッシュするディレクトリ
+    cache_dir: Option<PathBuf>,
+    /// tmux が PATH にある。無ければ tmux の要る操作（g・t・K）を止める
+    tmux: bool,
+    /// 取得を始めたリモートのディレクトリ直下のディレクトリ名（取得中は None）
+    remote_listings: HashMap<Location, Option<Vec<Directory>>>,
+    runner: Arc<dyn CommandRunner>,
+    worker: Worker<Update>,
+}
+
+impl App {
+    pub fn new(
+        roots: Vec<Location>,
+        candidates: Option<Vec<Entry>>,
+        config: &Config,
+        prefs: ViewPrefs,
+        cache_dir: Option<PathBuf>,
+        runner: Arc<dyn CommandRunner>,
+    ) -> Self {
+        let mut app = App {
+            roots: vec![],
+            candidates,
+            aliases: vec![],
+            current: None,
+            packages: None,
+            view: View::Directory,
+            index: None,
+            frecency: HashMap::new(),
+            scan: None,
+            search_results: None,
+            path_stack: vec![],
+            history: vec![],
+            forward: vec![],
+            max_depth: None,
+            scan_options: ScanOptions::default(),
+            items: vec![],
+            visible: vec![],
+            highlights: vec![],
+            selected: 0,
+            filter: None,
+            prefs,
+            type_filter: None,
+            type_picker: None,
+            tags: vec![],
+            types: HashMap::new(),
+            filter_invalid: false,
+            prompt: None,
+            task_picker: None,
+            toasts: vec![],
+            confirm: None,
+            confirm_plain_dirs: false,
+            error: None,
+            undo: vec![],
+            archive: String::new(),
+            hint: HintConfig::default(),
+            health: HealthConfig::default(),
+            keymap: Keymap::default(),
+            chord: None,
+            matcher: FuzzyMatcher::new(),
+            details: HashMap::new(),
+            ci: HashMap::new(),
+            stashes: HashMap::new(),
+            upstream: HashMap::new(),
+            fetcher: None,
+            fetch_interval: Duration::ZERO,
+            forges: Arc::default(),
+            cache_dir,
+            tmux: true,
+            remote_listings: HashMap::new(),
+            runner,
+            worker: Worker::new(4),
+        };
+        app.apply_config(roots, config);
+        app
+    }
+
+    /// 設定を反映し、今いるディレクトリの一覧を作り直す（起動時と設定の再読み込み時）
+    pub fn apply_config(&mut self, roots: Vec<Location>, config: &Config) {
+        self.tmux = shell::command_exists("tmux");
+        // Enter で tmux のつもりがシェルになるので、選ぶ前に知らせておく
+        if !self.tmux
+            && config.backend != Backend::Shell
+            && config.backend.resolve() == Backend::Shell
+        {
+            self.notify(Msg::TmuxMissing(&config.fallback.shell()));
+        }
+        self.forges = Arc::new(Forges::from_config(
+            config,
+            &self.runner,
+            self.cache_dir.as_deref(),
+        ));
+        self.fetcher = config.fetch.enabled.then(|| Worker::new(config.fetch.jobs));
+        self.fetch_interval = Duration::from_secs(config.fetch.interval * 60);
+        self.ci.clear();
+        self.stashes.clear();
+        self.upstream.clear();
+        self.matcher.set_case(config.filter.case);
+        self.max_depth = config.max_depth;
+        self.scan_options = config.scan_options();
+        self.hint = config.hint.clone();
+        self.health = config.health;
+        self.keymap = Keymap::new(&config.keys);
+        self.confirm_plain_dirs = config.open.confirm_plain_dirs;
+        self.archive = config
+            .archive
+            .clone()
+            .unwrap_or_else(|| ".archive".to_string());
+        self.aliases = match self.candidates {
+            Some(_) => vec![],
+            None => index::aliases(
+                self.runner.as_ref(),
+                &roots,
+                &self.scan_options,
+                &config.aliases,
+            ),
+        };
+        self.tags = config
+            .tags
+            .iter()
+            .map(|(tag, targets)| {
+                let locations = targets
+                    .iter()
+                    .filter_map(|target| {
+                        let location = location::resolve(
+                            self.runner.as_ref(),
+                            &roots,
+                            &self.scan_options,
+                            target,
+                        );
+                        if location.is_none() {
+                            warn!(tag = %tag, target = %target, "tagged project not found");
+                        }
+                        location
+                    })
+                    .collect();
+                (tag.clone(), locations)
+            })
+            .collect();
+
+        if let Some(sort) = self.prefs.sort {
+            self.scan_options.sort = sort;
+        }
+        self.scan_options.hidden = self.prefs.hidden;
+        self.roots = roots;
+        self.rescan();
+
+        let selected = self.selected_entry().map(|e| e.location.clone());
+        let items = self.current_entries();
+        self.set_items(items);
+        self.select(selected);
+    }
+
+    /// リモートの一覧と全ルートのプロジェクトの一覧を、バックグラウンドで作り直す
+    fn rescan(&mut self) {
+        // リモートは応答が遅いことがあるので、ローカルの一覧を先に表示して後から合流させる
+        self.remote_listings.clear();
+        let remotes: Vec<Location> = self.roots.iter().chain(&self.current).cloned().collect();
+        for location in &remotes {
+            self.request_listing(location);
+        }
+
+        // 全ルートを辿るのは時間がかかるので、f で使うまでにバックグラウンドで作っておく
+        self.index = None;
+        if self.candidates.is_none() {
+            let roots = self.roots.clone();
+            let options = self.scan_options.clone();
+            let depth = self.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+            let progress = ScanProgress::start();
+            let scanned = Arc::clone(&progress.scanned);
+            self.scan = Some(progress);
+            self.worker.submit(move || {
+                let entries = daemon::walk(&roots, &options, depth).unwrap_or_else(|| {
+                    profile::time("index", || {
+                        index::walk_counting(&roots, &options, depth, &scanned)
+                    })
+                });
+                Update::Index(entries)
+            });
+        }
+    }
+
+    /// リモートのディレクトリの一覧をバックグラウンドで取りに行く。ローカル・取得済み・取得中なら何もしない
+    fn request_listing(&mut self, location: &Location) {
+        if location.host().is_none() || self.remote_listings.contains_key(location) {
+            return;
+        }
+        self.remote_listings.insert(location.clone(), None);
+        let location = location.clone();
+        let runner = Arc::clone(&self.runner);
+        let options = self.scan_options.clone();
+        self.worker.submit(move || {
+            let dirs = location.list_directories(runner.as_ref(), &options);
+            Update::RemoteListing(location, dirs)
+        });
+    }
+
+    /// リモートのディレクトリの一覧を取りに行っている最中か
+    fn listing_pending(&self, location: &Location) -> bool {
+        matches!(self.remote_listings.get(location), Some(None))
+    }
+
+    /// 全ルートの一覧を並べる frecency を差し替える
+    pub fn set_frecency(&mut self, frecency: HashMap<Location, f64>) {
+        self.frecency = frecency;
+    }
+
+    /// 一覧の並び順を切り替える
+    pub fn cycle_sort(&mut self) {
+        let sort = match self.scan_options.sort {
+            SortOrder::Name => SortOrder::Modified,
+            SortOrder::Modified => SortOrder::Name,
+        };
+        self.prefs.sort = Some(sort);
+        self.scan_options.sort = sort;
+        self.notify(Msg::SortedBy(sort));
+        self.reload_listing();
+    }
+
+    /// 隠しディレクトリを並べるかを切り替える
+    pub fn toggle_hidden(&mut self) {
+        self.prefs.hidden = !self.prefs.hidden;
+        self.scan_options.hidden = self.prefs.hidden;
+        self.notify(Msg::HiddenShown(self.prefs.hidden));
+        self.reload_listing();
+    }
+
+    /// 走査のしかたを変えたあと、選択を保ったまま一覧を作り直す
+    fn reload_listing(&mut self) {
+        self.rescan();
+        let selected = self.selected_entry().map(|e| e.location.clone());
+        let items = self.current_entries();
+        self.set_items(items);
+        self.select(selected);
+    }
+
+    /// 次の起動に引き継ぐ表示の設定
+    pub fn prefs(&self) -> ViewPrefs {
+        ViewPrefs {
+            flat: self.view == View::Find,
+            ..self.prefs.clone()
+        }
+    }
+
+    /// 今の表示（ディレクトリ・全ルートのプロジェクト・内容の検索結果）のエントリ
+    fn current_entries(&self) -> Vec<Entry> {
+        match (&self.view, &self.current) {
+            (View::Find, _) => {
+                let mut entries = self.index.clone().unwrap_or_default();
+                // 同じ点数（開いたことが無いものなど）は走査した順のまま
+                let score =
+                    |entry: &Entry| self.frecency.get(&entry.location).copied().unwrap_or(0.0);
+                entries.sort_by(|a, b| score(b).total_cmp(&score(a)));
+                entries
+            }
+            (View::Search(_), _) => self.search_results.clone().unwrap_or_default(),
+            (View::Directory, Some(location)) => {
+                profile::time("scan", || self.listing(location, self.packages.is_none()).0)
+            }
+            (View::Directory, None) => profile::time("scan", || self.top_level_entries()),
+        }
+    }
+
+    /// ディレクトリ以外を並べているか
+    pub fn finding(&self) -> bool {
+        self.view != View::Directory
+    }
+
+    /// 全ルートのプロジェクトを並べて検索を始める
+    pub fn start_find(&mut self) {
+        if self.candidates.is_some() {
+            return;
+        }
+        self.view = View::Find;
+        self.filter = Some(TextInput::default());
+        let items = self.current_entries();
+        self.set_items(items);
+    }
+
+    /// 検索をやめて元のディレクトリの一覧に戻る
+    pub fn stop_find(&mut self) {
+        self.view = View::Directory;
+        self.filter = None;
+        let items = self.current_entries();
+        self.set_items(items);
+    }
+
+    /// 全ルートのプロジェクトの一覧の作成か、内容の検索を待っているか
+    pub fn indexing(&self) -> bool {
+        match self.view {
+            View::Directory => match &self.current {
+                Some(location) => self.listing_pending(location),
+                None => self.roots.iter().any(|root| self.listing_pending(root)),
+            },
+            View::Find => self.index.is_none(),
+            View::Search(_) => self.search_results.is_none(),
+        }
+    }
+
+    /// 全ルートを辿り始めてからしばらく経っていれば、その経過時間と読み終えたディレクトリの数
+    pub fn scan_progress(&self) -> Option<(Duration, usize)> {
+        let progress = self.scan.as_ref()?;
+        let elapsed = progress.started.elapsed();
+        (elapsed >= SCAN_PROGRESS_DELAY)
+            .then(|| (elapsed, progress.scanned.load(Ordering::Relaxed)))
+    }
+
+    /// ripgrep で `query` を含むプロジェクトをバックグラウンドで探す
+    fn search(&mut self, query: String) {
+        if self.candidates.is_some() {
+            return;
+        }
+        self.view = View::Search(query.clone());
+        self.search_results = None;
+        self.filter = None;
+        self.set_items(vec![]);
+
+        // 全ルートの一覧ができていなければ検索と一緒に作る
+        let projects = self.index.clone();
+        let roots = self.roots.clone();
+        let options = self.scan_options.clone();
+        let depth = self.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+        let runner = Arc::clone(&self.runner);
+        let scanned = match (&projects, &self.scan) {
+            (None, Some(progress)) => Arc::clone(&progress.scanned),
+            _ => Arc::new(AtomicUsize::new(0)),
+        };
+        self.worker.submit(move || {
+            let projects = projects
+                .or_else(|| daemon::walk(&roots, &options, depth))
+                .unwrap_or_else(|| index::walk_counting(&roots, &options, depth, &scanned));
+            let results = search::projects_containing(runner.as_ref(), &projects, &query);
+            Update::Search(query, results)
+        });
+    }
+
+    /// 指定した場所のエントリを選択する。見つからなければ先頭
+    fn select(&mut self, location: Option<Location>) {
+        self.selected = location
+            .and_then(|loc| self.visible_entries().position(|e| e.location == loc))
+            .unwrap_or(0);
+    }
+
+    /// 一覧を差し替え、絞り込みをやり直す
+    fn set_items(&mut self, items: Vec<Entry>) {
+        self.items = items;
+        self.apply_filter();
+    }
+
+    fn apply_filter(&mut self) {
+        let allowed: Vec<bool> = match self.type_filter.clone() {
+            Some(name) => (0..self.items.len())
+                .map(|i| self.has_type(i, &name))
+                .collect(),
+            None => vec![true; self.items.len()],
+        };
+        let query = self
+            .filter
+            .as_ref()
+            .map(TextInput::text)
+            .unwrap_or_default();
+        let names: Vec<&str> = self.items.iter().map(|e| e.name.as_str()).collect();
+        self.visible = self.matcher.rank(query, &names);
+        self.visible.retain(|&i| allowed[i]);
+        self.filter_invalid = self.matcher.is_invalid(query);
+        self.highlights = self
+            .visible
+            .iter()
+            .map(|&i| self.matcher.indices(query, names[i]))
+            .collect();
+        self.selected = 0;
+    }
+
+    /// items の i 番目が種類かタグ `name` に当てはまるか
+    fn has_type(&mut self, i: usize, name: &str) -> bool {
+        let location = &self.items[i].location;
+        let tagged = self
+            .tags
+            .iter()
+            .any(|(tag, locations)| tag == name && locations.contains(location));
+        tagged || self.types_of(i).contains(&name)
+    }
+
+    /// items の i 番目の種類。リモートは調べない
+    fn types_of(&mut self, i: usize) -> &[&'static str] {
+        let path = self.items[i].location.local_path().map(PathBuf::from);
+        match path {
+            Some(path) => self
+                .types
+                .entry(path)
+                .or_insert_with_key(|path| project_type::detect(path)),
+            None => &[],
+        }
+    }
+
+    /// 一覧にある種類と設定のタグを並べたメニューを開く
+    pub fn open_type_picker(&mut self) {
+        let mut found: Vec<&'static str> = vec![];
+        for i in 0..self.items.len() {
+            found.extend(self.types_of(i).iter().copied());
+        }
+        let mut options: Vec<Option<String>> = vec![None];
+        options.extend(
+            project_type::TYPES
+                .iter()
+                .map(|(name, _)| *name)
+                .filter(|name| found.contains(name))
+                .map(|name| Some(name.to_string())),
+        );
+        options.extend(self.tags.iter().map(|(tag, _)| Some(tag.clone())));
+        if options.len() == 1 {
+            self.notify(Msg::NoProjectTypes);
+            return;
+        }
+        let selected = options
+            .iter()
+            .position(|option| *option == self.type_filter)
+            .unwrap_or(0);
+        self.type_picker = Some(TypePicker { options, selected });
+    }
+
+    /// 種類・タグの選択位置を動かす
+    pub fn move_type(&mut self, down: bool) {
+        if let Some(picker) = &mut self.type_picker {
+            picker.selected = if down {
+                (picker.selected + 1).min(picker.options.len() - 1)
+            } else {
+                picker.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// 選んだ種類・タグで絞り込む
+    pub fn choose_type(&mut self) {
+        if let Some(mut picker) = self.type_picker.take() {
+            self.set_type_filter(picker.options.swap_remove(picker.selected));
+        }
+    }
+
+    /// 種類・タグでの絞り込みを変える。None ならやめる
+    pub fn set_type_filter(&mut self, name: Option<String>) {
+        let selected = self.selected_entry().map(|e| e.location.clone());
+        self.type_filter = name;
+        self.apply_filter();
+        self.select(selected);
+    }
+
+    /// 絞り込みの入力を始める
+    pub fn start_filter(&mut self) {
+        self.filter.get_or_insert_with(TextInput::default);
+    }
+
+    /// 検索語を編集する。空のときに Backspace を押したら絞り込みをやめる
+    pub fn edit_filter(&mut self, key: &KeyEvent) {
+        let Some(filter) = &mut self.filter else {
+            return;
+        };
+        if key.code == KeyCode::Backspace && filter.is_empty() {
+            self.filter = None;
+            self.apply_filter();
+        } else if filter.handle_key(key) == Edit::Changed {
+            self.apply_filter();
+        }
+    }
+
+    /// 検索語の `re:` を付け外しして、あいまい検索と正規表現を切り替える
+    pub fn toggle_regex(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            let text = match filter.text().strip_prefix(REGEX_PREFIX) {
+                Some(rest) => rest.to_string(),
+                None => format!("{}{}", REGEX_PREFIX, filter.text()),
+            };
+            filter.set(text);
+            self.apply_filter();
+        }
+    }
+
+    /// 絞り込みをやめて全件表示に戻す。全ルートの検索中なら検索をやめる
+    pub fn clear_filter(&mut self) {
+        if self.finding() {
+            self.stop_find();
+            return;
+        }
+        self.filter = None;
+        self.apply_filter();
+    }
+
+    /// 1行入力を始める
+    pub fn start_prompt(&mut self, kind: PromptKind) {
+        self.prompt = Some((kind, TextInput::default()));
+    }
+
+    /// 入力中の1行を編集する。空のときに Backspace を押したら入力をやめる
+    pub fn edit_prompt(&mut self, key: &KeyEvent) {
+        if let Some((_, input)) = &mut self.prompt {
+            if key.code == KeyCode::Backspace && input.is_empty() {
+                self.prompt = None;
+            } else {
+                input.handle_key(key);
+            }
+        }
+    }
+
+    /// 貼り付けた文字列を入力中の1行か検索語に入れる
+    pub fn paste(&mut self, text: &str) {
+        if let Some((_, input)) = &mut self.prompt {
+            input.insert_str(text);
+        } else if let Some(filter) = &mut self.filter {
+            filter.insert_str(text);
+            self.apply_filter();
+        }
+    }
+
+    /// 移動先のパスをディレクトリ名で補完する
+    pub fn complete_prompt(&mut self) {
+        if let Some((PromptKind::Jump, input)) = &mut self.prompt {
+            input.set(location::complete_path(input.text()));
+        }
+    }
+
+    /// 入力を確定する
+    pub fn finish_prompt(&mut self) {
+        let Some((kind, input)) = self.prompt.take() else {
+            return;
+        };
+        let input = input.text().trim();
+        if input.is_empty() {
+            return;
+        }
+        match kind {
+            PromptKind::Jump => self.jump(input),
+            PromptKind::Search => self.search(input.to_string()),
+            PromptKind::Rename => self.rename(input),
+            PromptKind::NewDir => self.make_dir(input),
+        }
+    }
+
+    /// 選択中のプロジェクトの新しい名前の入力を始める（今の名前を入れておく）
+    pub fn start_rename(&mut self) {
+        let Some(path) = self.selected_local("rename") else {
+            return;
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.prompt = Some((PromptKind::Rename, TextInput::new(name)));
+    }
+
+    /// 選択中のプロジェクトの名前を変える
+    fn rename(&mut self, name: &str) {
+        let Some(from) = self.selected_local("rename") else {
+            return;
+        };
+        if !valid_name(name) {
+            self.notify(Msg::InvalidName(name));
+            return;
+        }
+        let to = from.with_file_name(name);
+        if to == from {
+            return;
+        }
+        let message = Msg::Renamed {
+            from: &from.file_name().unwrap_or_default().to_string_lossy(),
+            to: name,
+        }
+        .to_string();
+        self.apply_move(from, to, message);
+    }
+
+    /// 今いるディレクトリ（ルート直下の一覧なら選択中のもののあるルート）にディレクトリを作る
+    fn make_dir(&mut self, name: &str) {
+        if !valid_name(name) {
+            self.notify(Msg::InvalidName(name));
+            return;
+        }
+        let parent = match &self.current {
+            Some(location) => location.local_path().map(PathBuf::from),
+            None => {
+                let selected = self.selected_path();
+                let mut roots = self.roots.iter().filter_map(Location::local_path);
+                roots
+                    .clone()
+                    .find(|root| selected.as_ref().is_some_and(|path| path.starts_with(root)))
+                    .or_else(|| roots.next())
+                    .map(PathBuf::from)
+            }
+        };
+        let Some(parent) = parent else {
+            self.notify_error(&Error::RemoteUnsupported("mkdir"));
+            return;
+        };
+        let path = parent.join(name);
+        if path.exists() {
+            self.notify_error(&Error::AlreadyExists(path));
+            return;
+        }
+        if let Err(e) = fs::create_dir(&path) {
+            self.notify_error(&Error::create_dir_failed(&path, e));
+            return;
+        }
+        self.notify(Msg::Created(&paths::shorten_path(&path)));
+        self.reload_listing();
+        self.select(Some(Location::Local(path)));
+    }
+
+    /// 選択中のプロジェクトをアーカイブの場所に移す
+    pub fn archive_selected(&mut self) {
+        let Some(from) = self.selected_local("archive") else {
+            return;
+        };
+        let dir = match Location::parse(&self.archive).local_path() {
+            Some(dir) if dir.is_absolute() => dir.to_path_buf(),
+            _ => {
+                // プロジェクトのあるルート（無ければ親ディレクトリ）からの位置
+                let root = self
+                    .roots
+                    .iter()
+                    .filter_map(Location::local_path)
+                    .find(|root| from.starts_with(root))
+                    .or_else(|| from.parent())
+                    .unwrap_or(&from);
+                root.join(&self.archive)
+            }
+        };
+        let to = dir.join(from.file_name().unwrap_or_default());
+        let message = Msg::Archived {
+            name: &from.file_name().unwrap_or_default().to_string_lossy(),
+            to: &paths::shorten_path(&dir),
+        }
+        .to_string();
+        self.apply_move(from, to, message);
+    }
+
+    /// 選択中のプロジェクトをゴミ箱に移すか尋ねる
+    pub fn trash_selected(&mut self) {
+        let Some(original) = self.selected_local("delete") else {
+            return;
+        };
+        let name = original.file_name().unwrap_or_default().to_string_lossy();
+        let message = Msg::ConfirmTrash(&name).to_string();
+        self.ask(message, ConfirmAction::Trash(original));
+    }
+
+    /// ディレクトリをゴミ箱に移し、取り消せるよう覚えておく
+    fn trash(&mut self, original: PathBuf) -> bool {
+        match trash::trash(&original) {
+            Ok(trashed) => {
+                let name = original.file_name().unwrap_or_default().to_string_lossy();
+                self.notify(Msg::Trashed(&name));
+                self.undo.push(Undo::Trash { original, trashed });
+                self.reload_listing();
+                true
+            }
+            Err(e) => {
+                self.notify_error(&e);
+                false
+            }
+        }
+    }
+
+    /// 選択中のプロジェクトの tmux セッションを終了するか尋ねる
+    pub fn kill_session_selected(&mut self) {
+        let Some(path) = self.selected_local("kill-session") else {
+            return;
+        };
+        if !self.tmux {
+            self.notify_error(&Error::TmuxMissing);
+            return;
+        }
+        let name = Action::Open.session_name(&Location::Local(path));
+        if !session::exists(self.runner.as_ref(), &name) {
+            self.notify(Msg::NoSession(&name));
+            return;
+        }
+        let message = Msg::ConfirmKillSession(&name).to_string();
+        self.ask(message, ConfirmAction::KillSession(name));
+    }
+
+    fn ask(&mut self, message: String, action: ConfirmAction) {
+        self.confirm = Some(Confirm {
+            message,
+            yes: false,
+            action,
+        });
+    }
+
+    /// 確認ダイアログのフォーカスを「はい」と「いいえ」で切り替える
+    pub fn toggle_confirm(&mut self) {
+        if let Some(confirm) = &mut self.confirm {
+            confirm.yes = !confirm.yes;
+        }
+    }
+
+    /// 確認ダイアログを閉じ、`yes` なら操作を行う。開く操作ならその場所を返す
+    pub fn answer_confirm(&mut self, yes: bool) -> Option<(Action, Location)> {
+        let confirm = self.confirm.take()?;
+        if !yes {
+            return None;
+        }
+        match confirm.action {
+            ConfirmAction::Open(location) => return Some((Action::Open, location)),
+            ConfirmAction::Trash(path) => {
+                self.trash(path);
+            }
+            ConfirmAction::KillSession(name) => match session::kill(self.runner.as_ref(), &name) {
+                Ok(()) => self.notify(Msg::SessionKilled(&name)),
+                Err(e) => self.show_error(&e),
+            },
+            ConfirmAction::Overwrite { from, to, message } => {
+                if self.trash(to.clone()) {
+                    self.apply_move(from, to, message);
+                }
+            }
+        }
+        None
+    }
+
+    /// ディレクトリを移し、取り消せるよう覚えておく。移動先が既にあれば置き換えるか尋ねる
+    fn apply_move(&mut self, from: PathBuf, to: PathBuf, message: String) {
+        if to.exists() {
+            let name = paths::shorten_path(&to);
+            let question = Msg::ConfirmOverwrite(&name).to_string();
+            self.ask(question, ConfirmAction::Overwrite { from, to, message });
+            return;
+        }
+        if let Err(e) = trash::move_dir(&from, &to) {
+            self.notify_error(&e);
+            return;
+        }
+        let moved = Location::Local(to.clone());
+        self.notify(message);
+        self.undo.push(Undo::Move { from, to });
+        self.reload_listing();
+        self.select(Some(moved));
+    }
+
+    /// 直前の名前の変更・アーカイブ・削除を取り消す
+    pub fn undo(&mut self) {
+        let Some(undo) = self.undo.pop() else {
+            self.notify(Msg::NothingToUndo);
+            return;
+        };
+        let (result, restored) = match &undo {
+            Undo::Move { from, to } => (trash::move_dir(to, from), from.clone()),
+            Undo::Trash { original, trashed } => {
+                (trash::restore(trashed, original), original.clone())
+            }
+        };
+        match result {
+            Ok(()) => {
+                let name = restored.file_name().unwrap_or_default().to_string_lossy();
+                self.notify(Msg::Undone(&name));
+                self.reload_listing();
+                self.select(Some(Location::Local(restored)));
+            }
+            Err(e) => {
+                self.notify_error(&e);
+                // 移動先を空ければもう一度取り消せる
+                self.undo.push(undo);
+            }
+        }
+    }
+
+    /// 選択中のエントリのローカルのパス。リモートならお知らせを出して None
+    fn selected_local(&mut self, feature: &'static str) -> Option<PathBuf> {
+        let location = self.selected_entry()?.location.clone();
+        let path = location.local_path().map(PathBuf::from);
+        if path.is_none() {
+            self.notify_error(&Error::RemoteUnsupported(feature));
+        }
+        path
+    }
+
+    /// 入力したパスへ移動する。ルートの外でもよい
+    fn jump(&mut self, input: &str) {
+        let target = match Location::parse(input) {
+            Location::Local(path) if path.is_absolute() && path.is_dir() => Location::Local(path),
+            Location::Local(_) => {
+                self.notify(Msg::NotADirectory(input));
+                return;
+            }
+            remote => remote,
+        };
+        let from = self.current.clone();
+        self.record_visit(from);
+        self.go_to(Some(target));
+    }
+
+    /// ディレクトリの一覧とモノレポの種類。モノレポなら `raw` でない限りパッケージを並べる
+    fn listing(&self, location: &Location, raw: bool) -> (Vec<Entry>, Option<MonorepoKind>) {
+        let monorepo = location
+            .local_path()
+            .filter(|_| !raw)
+            .and_then(Monorepo::detect);
+        match monorepo {
+            Some(monorepo) => (package_entries(&monorepo), Some(monorepo.kind)),
+            // リモートは UI を止めないよう、ワーカーが取ってきたものを使う
+            None if location.host().is_some() => {
+                let dirs = self.remote_listings.get(location).cloned().flatten();
+                let entries = dirs.unwrap_or_default().into_iter();
+                (entries.map(|dir| location.entry(dir)).collect(), None)
+            }
+            None => (
+                location.entries(self.runner.as_ref(), &self.scan_options),
+                None,
+            ),
+        }
+    }
+
+    /// 全ルート直下のディレクトリをまとめた一覧。エイリアスを先頭に置く
+    fn top_level_entries(&self) -> Vec<Entry> {
+        if let Some(candidates) = &self.candidates {
+            return candidates.clone();
+        }
+        let mut dirs = vec![];
+        for root in &self.roots {
+            let listing = match root {
+                Location::Local(path) => scan::get_directories(path, &self.scan_options),
+                Location::Remote { .. } => self
+                    .remote_listings
+                    .get(root)
+                    .cloned()
+                    .flatten()
+                    .unwrap_or_default(),
+            };
+            dirs.extend(listing.into_iter().map(|dir| (root, dir)));
+        }
+        // ルートをまたいだ並び順は設定ファイルのものに従う
+        dirs.sort_by(|(_, a), (_, b)| self.scan_options.sort.compare(a, b));
+        let entries = dirs.into_iter().map(|(root, dir)| root.entry(dir));
+        self.aliases.iter().cloned().chain(entries).collect()
+    }
+
+    /// 表示中のディレクトリ（ルート一覧ではルートを並べたもの）
+    pub fn current_display(&self) -> String {
+        match &self.view {
+            View::Directory => {}
+            View::Find => return Msg::AllProjects.to_string(),
+            View::Search(query) => return Msg::SearchResults(query).to_string(),
+        }
+        match &self.current {
+            Some(location) => match self.packages {
+                Some(kind) => format!("{} {}", location.display(), Msg::Packages(kind.label())),
+                None => location.display(),
+            },
+            None if self.candidates.is_some() => Msg::StdinCandidates.to_string(),
+            None => self
+                .roots
+                .iter()
+                .map(Location::display)
+                .collect::<Vec<_>>()
+                .join(" + "),
+        }
+    }
+
+    /// 選択中のディレクトリに入る。サブディレクトリが無ければ何もせず、深さの上限なら知らせる
+    ///
+    /// プロジェクトの中には `force` のときだけ入る。
+    /// 深さの上限は辿って入るときだけのもので、: で飛んだ先や検索で選んだものには効かない。
+    pub fn enter(&mut self, force: bool) {
+        // 今の一覧の深さは path_stack.len() + 1
+        if let Some(max) = self
+            .max_depth
+            .filter(|max| self.path_stack.len() + 2 > *max)
+        {
+            self.notify(Msg::MaxDepth(max));
+            return;
+        }
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let location = entry.location.clone();
+        // プロジェクトの中のソースを辿っても仕方がないので、開くほうに誘導する
+        let path = location.local_path();
+        let project = path.is_some_and(|path| self.scan_options.is_project(path));
+        let monorepo = path.is_some_and(|path| Monorepo::detect(path).is_some());
+        if project && !monorepo && !force {
+            let message = Msg::ProjectBoundary(&entry.name).to_string();
+            self.notify(message);
+            return;
+        }
+        // 検索で見つけたプロジェクトには、ルートからの途中のディレクトリを経て入る
+        if self.finding() {
+            let from = self.current.clone();
+            self.record_visit(from);
+            self.go_to(Some(location));
+            return;
+        }
+        self.request_listing(&location);
+        let (entries, packages) = self.listing(&location, force);
+        // 取得中のリモートは、届くまで空の一覧に入っておく
+        if entries.is_empty() && !self.listing_pending(&location) {
+            return;
+        }
+        self.packages = packages;
+        let prev = self.current.replace(location);
+        self.record_visit(prev.clone());
+        self.path_stack.push(prev);
+        self.filter = None;
+        self.set_items(entries);
+    }
+
+    /// 親ディレクトリに戻る。全ルートの検索中なら検索をやめる
+    pub fn back(&mut self) {
+        if self.finding() {
+            self.stop_find();
+            return;
+        }
+        let Some(prev) = self.path_stack.pop() else {
+            return;
+        };
+        if let Some(location) = &prev {
+            self.request_listing(location);
+        }
+        let (items, packages) = match &prev {
+            Some(location) => self.listing(location, false),
+            None => (self.top_level_entries(), None),
+        };
+        self.packages = packages;
+        let from = std::mem::replace(&mut self.current, prev);
+        self.record_visit(from.clone());
+        self.filter = None;
+        self.set_items(items);
+        // 出てきたディレクトリを選ぶ
+        self.select(from);
+    }
+
+    /// 離れるディレクトリを履歴に積む。新しく移動したので進む先は捨てる
+    fn record_visit(&mut self, from: Option<Location>) {
+        self.history.push(from);
+        self.forward.clear();
+    }
+
+    /// 履歴を1つ戻る
+    pub fn history_back(&mut self) {
+        let Some(target) = self.history.pop() else {
+            return;
+        };
+        let from = self.current.clone();
+        self.forward.push(from);
+        self.go_to(target);
+    }
+
+    /// 履歴を1つ進む
+    pub fn history_forward(&mut self) {
+        let Some(target) = self.forward.pop() else {
+            return;
+        };
+        let from = self.current.clone();
+        self.history.push(from);
+        self.go_to(target);
+    }
+
+    /// 指定したディレクトリ（None はルート一覧）を表示する。
+    /// 親に戻れるよう、ルートからの途中のディレクトリを path_stack に積み直す
+    fn go_to(&mut self, target: Option<Location>) {
+        let mut stack = vec![];
+        if let Some(location) = &target {
+            let mut parent = location.parent();
+            while let Some(dir) = parent {
+                if self.roots.contains(&dir) || !self.roots.iter().any(|r| r.contains(&dir)) {
+                    break;
+                }
+                parent = dir.parent();
+                stack.push(Some(dir));
+            }
+            stack.push(None);
+            stack.reverse();
+            self.request_listing(location);
+        }
+        let (items, packages) = match &target {
+            Some(location) => self.listing(location, false),
+            None => (self.top_level_entries(), None),
+        };
+        self.packages = packages;
+        self.current = target;
+        self.view = View::Directory;
+        self.path_stack = stack;
+        self.filter = None;
+        self.set_items(items);
+    }
+
+    /// 前回終了したときのディレクトリと選択に戻る。そのディレクトリが無くなっていればルート一覧のまま
+    pub fn resume(&mut self, position: &BrowsePosition) {
+        if let Some(current) = position.current() {
+            let exists = current.local_path().is_none_or(|path| path.is_dir());
+            if !exists || !self.roots.iter().any(|root| root.contains(&current)) {
+                return;
+            }
+            self.go_to(Some(current));
+        }
+        self.select(position.selected());
+    }
+
+    /// 今見ているディレクトリと選択
+    pub fn position(&self) -> BrowsePosition {
+        let selected = self.selected_entry().map(|e| &e.location);
+        BrowsePosition::new(self.current.as_ref(), selected)
+    }
+
+    pub fn move_up(&mut self) {
+        if !self.visible.is_empty() && self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if !self.visible.is_empty() && self.selected < self.visible.len() - 1 {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.visible
+            .get(self.selected)
+            .and_then(|&i| self.items.get(i))
+    }
+
+    /// 表示順のエントリ
+    pub fn visible_entries(&self) -> impl Iterator<Item = &Entry> {
+        self.visible.iter().filter_map(|&i| self.items.get(i))
+    }
+
+    /// 選択中のエントリに対して操作を行えるなら、その操作と対象を返す
+    pub fn launch_target(&mut self, action: Action) -> Option<(Action, Location)> {
+        // リモートでは ssh 先の tmux を使う
+        let remote = self.selected_entry()?.location.host().is_some();
+        if !self.tmux && action.needs_tmux() && !remote {
+            self.notify_error(&Error::TmuxMissing);
+            return None;
+        }
+        let entry = self.selected_entry()?;
+        let available = match action {
+            Action::Open | Action::Git => true,
+            Action::Files | Action::Task(_) => entry.location.local_path().is_some(),
+            Action::Workspace => WindowManager::detect().is_some(),
+            Action::NewWindow => cfg!(target_os = "macos"),
+            Action::Window => session::inside_tmux() && entry.location.local_path().is_some(),
+            Action::Devcontainer => self.selected_details()?.devcontainer,
+            Action::Compose => self.selected_details()?.compose,
+        };
+        if !available {
+            return None;
+        }
+        // 目印の無い途中のディレクトリからセッションを作る前に確かめる
+        let plain = entry
+            .location
+            .local_path()
+            .is_some_and(|path| !self.scan_options.is_project(path));
+        if action == Action::Open && self.confirm_plain_dirs && plain {
+            let message = Msg::ConfirmOpenPlain(&entry.name).to_string();
+            let location = entry.location.clone();
+            self.ask(message, ConfirmAction::Open(location));
+            return None;
+        }
+        Some((action, entry.location.clone()))
+    }
+
+    /// 選択中のものではなく、今いるディレクトリそのものを開く。ルートの一覧や検索結果では開かない
+    pub fn launch_current(&self) -> Option<(Action, Location)> {
+        if self.view != View::Directory {
+            return None;
+        }
+        Some((Action::Open, self.current.clone()?))
+    }
+
+    /// 選択中のプロジェクトのタスクを並べる。タスクが無ければお知らせを出す
+    pub fn open_tasks(&mut self) {
+        if !self.tmux {
+            self.notify_error(&Error::TmuxMissing);
+            return;
+        }
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let Some(path) = entry.location.local_path() else {
+            return;
+        };
+        let tasks = tasks::detect(path);
+        if tasks.is_empty() {
+            self.notify(Msg::NoTasks);
+            return;
+        }
+        self.task_picker = Some(TaskPicker {
+            location: entry.location.clone(),
+            tasks,
+            selected: 0,
+        });
+    }
+
+    /// タスクの選択位置を動かす
+    pub fn move_task(&mut self, down: bool) {
+        if let Some(picker) = &mut self.task_picker {
+            picker.selected = if down {
+                (picker.selected + 1).min(picker.tasks.len() - 1)
+            } else {
+                picker.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// 選んだタスクを実行する操作にする
+    pub fn choose_task(&mut self) -> Option<(Action, Location)> {
+        let mut picker = self.task_picker.take()?;
+        let task = picker.tasks.swap_remove(picker.selected);
+        Some((Action::Task(task), picker.location))
+    }
+
+    pub fn selected_details(&self) -> Option<&Details> {
+        self.details.get(&self.selected_path()?)
+    }
+
+    /// 選択中のエントリがローカルならそのパス
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.selected_entry()
+            .and_then(|entry| entry.location.local_path())
+            .map(PathBuf::from)
+    }
+
+    /// 選択中のプロジェクトの詳細が未計算ならバックグラウンドで計算を始める
+    pub fn request_details(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        if self.details.contains_key(&path) {
+            return;
+        }
+        let compose = compose::compose_file(&path).is_some();
+        self.details.insert(
+            path.clone(),
+            Details {
+                devcontainer: devcontainer::config_path(&path).is_some(),
+                compose,
+                nix: nix::detect(&path),
+                ..Details::default()
+            },
+        );
+
+        if compose {
+            let compose_path = path.clone();
+            let runner = Arc::clone(&self.runner);
+            self.worker.submit(move || {
+                let status = compose::status(runner.as_ref(), &compose_path);
+                Update::ComposeStatus(compose_path, status)
+            });
+        }
+
+        if path.join(".git").exists() {
+            let git_path = path.clone();
+            let runner = Arc::clone(&self.runner);
+            self.worker.submit(move || {
+                let last_commit = git::last_commit_time(runner.as_ref(), &git_path);
+                let dirty = git::is_dirty(runner.as_ref(), &git_path);
+                Update::GitStatus(git_path, last_commit, dirty)
+            });
+        }
+
+        let du_path = path.clone();
+        self.worker.submit(move || {
+            let size = stats::disk_usage(&du_path);
+            Update::DiskUsage(du_path, size)
+        });
+        let languages_path = path.clone();
+        self.worker.submit(move || {
+            let languages = stats::languages(&languages_path);
+            Update::Languages(languages_path, languages)
+        });
+        let todos_path = path.clone();
+        self.worker.submit(move || {
+            let todos = stats::todos(&todos_path);
+            Update::Todos(todos_path, todos)
+        });
+
+        self.request_open_counts(path);
+    }
+
+    pub fn fetches_open_counts(&self) -> bool {
+        self.forges.shows_open_counts()
+    }
+
+    /// オープンな PR / Issue の数は選択されたときだけ取得する
+    fn request_open_counts(&mut self, path: PathBuf) {
+        if !self.fetches_open_counts() {
+            return;
+        }
+        let forges = Arc::clone(&self.forges);
+        let runner = Arc::clone(&self.runner);
+
+        self.worker.submit(move || {
+            let counts =
+                git::remote_url(runner.as_ref(), &path).and_then(|url| forges.open_counts(&url));
+            Update::OpenCounts(path, counts)
+        });
+    }
+
+    /// 選択中のプロジェクトのキャッシュを捨てて計算し直す
+    pub fn refresh_details(&mut self) {
+        if let Some(path) = self.selected_path() {
+            self.details.remove(&path);
+            self.stashes.remove(&path);
+            self.upstream.remove(&path);
+        }
+        self.request_details();
+        self.request_stash_counts();
+        self.request_upstream();
+    }
+
+    /// 一覧に表示中のプロジェクトのスタッシュを数える
+    pub fn request_stash_counts(&mut self) {
+        for entry in &self.items {
+            let Some(path) = entry.location.local_path().map(PathBuf::from) else {
+                continue;
+            };
+            if self.stashes.contains_key(&path) {
+                continue;
+            }
+            self.stashes.insert(path.clone(), 0);
+            self.worker.submit(move || {
+                let count = git::stash_count(&path);
+                Update::StashCount(path, count)
+            });
+        }
+    }
+
+    /// 一覧に表示中のリポジトリの upstream との差を数える
+    ///
+    /// fetch が有効なら、手元の参照で数えたあとに fetch して数え直す。fetch は `fetch.jobs` 件ずつ、
+    /// 同じリポジトリは `fetch.interval` 分に1回まで。
+    pub fn request_upstream(&mut self) {
+        for entry in &self.items {
+            let Some(path) = entry.location.local_path().map(PathBuf::from) else {
+                continue;
+            };
+            // git リポジトリでなければ git を起動しない
+            if self.upstream.contains_key(&path) || !path.join(".git").exists() {
+                continue;
+            }
+            self.upstream.insert(path.clone(), None);
+
+            let runner = Arc::clone(&self.runner);
+            let local_path = path.clone();
+            self.worker.submit(move || {
+                let counts = git::ahead_behind(runner.as_ref(), &local_path);
+                Update::Upstream(local_path, counts)
+            });
+            if let Some(fetcher) = &self.fetcher {
+                let runner = Arc::clone(&self.runner);
+                let interval = self.fetch_interval;
+                fetcher.submit(move || {
+                    git::fetch(runner.as_ref(), &path, interval);
+                    let counts = git::ahead_behind(runner.as_ref(), &path);
+                    Update::Upstream(path, counts)
+                });
+            }
+        }
+    }
+
+    /// 一覧に表示中のプロジェクトの CI ステータスを取得する（CI ステータスの連携が有効な場合のみ）
+    pub fn request_ci_status(&mut self) {
+        if !self.forges.shows_ci() {
+            return;
+        }
+
+        for entry in &self.items {
+            let Some(path) = entry.location.local_path().map(PathBuf::from) else {
+                continue;
+            };
+            if self.ci.contains_key(&path) {
+                continue;
+            }
+            self.ci.insert(path.clone(), None);
+
+            let forges = Arc::clone(&self.forges);
+            let runner = Arc::clone(&self.runner);
+            self.worker.submit(move || {
+                let status =
+                    git::remote_url(runner.as_ref(), &path).and_then(|url| forges.ci_status(&url));
+                Update::CiStatus(path, status)
+            });
+        }
+    }
+
+    /// お知らせを出す
+    pub fn notify(&mut self, message: impl fmt::Display) {
+        self.push_toast(message.to_string(), false, TOAST_DURATION);
+    }
+
+    /// エラーのお知らせを出す
+    pub fn notify_error(&mut self, error: &Error) {
+        self.push_toast(Msg::Error(error).to_string(), true, ERROR_TOAST_DURATION);
+    }
+
+    /// エラーを画面の中央に出す。閉じるまで他のキーは受け付けない
+    pub fn show_error(&mut self, error: &Error) {
+        self.error = Some(error.to_string());
+    }
+
+    fn push_toast(&mut self, text: String, error: bool, duration: Duration) {
+        // 同じ操作を繰り返したときに同じお知らせを積まない
+        self.toasts.retain(|toast| toast.text != text);
+        self.toasts.push(Toast {
+            text,
+            error,
+            until: Instant::now() + duration,
+        });
+        let overflow = self.toasts.len().saturating_sub(MAX_TOASTS);
+        self.toasts.drain(..overflow);
+    }
+
+    /// 届いた計算結果をキャッシュに反映し、時間の過ぎたお知らせを消す。再描画が必要なら true
+    pub fn poll_updates(&mut self) -> bool {
+        let now = Instant::now();
+        let toasts = self.toasts.len();
+        self.toasts.retain(|toast| toast.until > now);
+        let mut changed = self.toasts.len() != toasts;
+        while let Some(update) = self
+            .worker
+            .try_recv()
+            .or_else(|| self.fetcher.as_ref().and_then(Worker::try_recv))
+        {
+            match update {
+                Update::DiskUsage(path, size) => {
+                    self.details.entry(path).or_default().size = Some(size);
+                }
+                Update::Languages(path, languages) => {
+                    self.details.entry(path).or_default().languages = Some(languages);
+                }
+                Update::Todos(path, todos) => {
+                    self.details.entry(path).or_default().todos = Some(todos);
+                }
+                Update::CiStatus(path, status) => {
+                    self.ci.insert(path, status);
+                }
+                Update::StashCount(path, count) => {
+                    self.stashes.insert(path, count);
+                }
+                Update::GitStatus(path, last_commit, dirty) => {
+                    let details = self.details.entry(path).or_default();
+                    details.last_commit = last_commit;
+                    details.dirty = dirty;
+                }
+                Update::Upstream(path, counts) => {
+                    self.upstream.insert(path, counts);
+                }
+                Update::OpenCounts(path, counts) => {
+                    self.details.entry(path).or_default().open_counts = Some(counts);
+                }
+                Update::ComposeStatus(path, status) => {
+                    self.details.entry(path).or_default().compose_status = Some(status);
+                }
+                Update::Index(entries) => {
+                    self.index = Some(entries);
+                    self.scan = None;
+                    if self.view == View::Find {
+                        let items = self.current_entries();
+                        self.set_items(items);
+                    }
+                }
+                Update::Search(query, results) => {
+                    // 結果が届く前に別の検索を始めていたら捨てる
+                    if self.view != View::Search(query) {
+                        continue;
+                    }
+                    match results {
+                        Ok(entries) => {
+                            self.search_results = Some(entries);
+                            let items = self.current_entries();
+                            self.set_items(items);
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "content search failed");
+                            self.show_error(&e);
+                            self.stop_find();
+                        }
+                    }
+                }
+                Update::RemoteListing(location, dirs) => {
+                    let shown = match &self.current {
+                        Some(current) => *current == location,
+                        None => self.roots.contains(&location),
+                    };
+                    self.remote_listings.insert(location, Some(dirs));
+                    if shown && !self.finding() {
+                        // 合流後も同じエントリを選択したままにする
+                        let selected = self.selected_entry().map(|e| e.location.clone());
+                        let items = self.current_entries();
+                        self.set_items(items);
+                        self.select(selected);
+                    }
+                }
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// モノレポのパッケージ。名前はリポジトリからの相対パス
+fn package_entries(monorepo: &Monorepo) -> Vec<Entry> {
+    monorepo
+        .members()
+        .into_iter()
+        .map(|path| Entry {
+            name: path
+                .strip_prefix(&monorepo.dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string(),
+            location: Location::Local(path),
+            alias: false,
+            symlink: false,
+        })
+        .collect()
+}
+
+/// 1つのディレクトリの名前として使えるか（`/` を含まず、`.` `..` でない）
+fn valid_name(name: &str) -> bool {
+    !name.contains(['/', '\\']) && name != "." && name != ".."
+}
@@ -0,0 +1,299 @@
+use std::env;
+use std::path::PathBuf;
+
+use projector_core::config::Picker;
+use projector_core::{Error, Result};
+
+/// `stats` で週の数を指定しなければ表示する週の数
+const DEFAULT_STATS_WEEKS: usize = 4;
+
+/// サブコマンド
+#[derive(Default)]
+pub enum Command {
+    /// TUI でプロジェクトを選ぶ
+    #[default]
+    Browse,
+    /// 一覧を出力する（端末でも TUI を開かない）
+    List,
+    /// 名前を指定して開く
+    Open { name: String },
+    /// 1つ前に開いたプロジェクトをもう一度開く
+    Last,
+    /// 覚えているセッションを作り直す（空なら全部）
+    Resurrect { names: Vec<String> },
+    /// プロジェクトのセッションを終了する（None なら `--all` で覚えているものを全部）
+    Kill { name: Option<String> },
+    /// 環境を調べて問題を表示する
+    Doctor,
+    /// プロジェクトの一覧を表にして出力する
+    Export { format: ExportFormat },
+    /// エイリアス・タグ・開いた履歴を書き出す（None なら標準出力）
+    StateExport { file: Option<PathBuf> },
+    /// 書き出したエイリアス・タグ・開いた履歴を手元のものと合わせる（`-` なら標準入力）
+    StateImport { file: PathBuf },
+    /// 週ごとのプロジェクトの作業時間を表示する
+    Stats { weeks: usize },
+    /// シェルの履歴から開いた回数を取り込む（空なら既定の履歴ファイル）
+    ImportHistory { files: Vec<PathBuf> },
+    /// 一覧を温めておくデーモンとして常駐する
+    Daemon,
+    /// エディタのプラグイン向けに JSON-RPC で答える
+    Serve,
+}
+
+/// 選んだ結果の出力形式
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// そのまま tmux などを起動する
+    #[default]
+    Text,
+    /// 起動せずに JSON で出力する
+    Json,
+    /// Raycast のスクリプトで使う JSON（一覧のみ）
+    Raycast,
+    /// Alfred の Script Filter の JSON（一覧のみ）
+    Alfred,
+}
+
+/// `export` の表の形式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// CSV
+    Csv,
+    /// Markdown の表
+    #[default]
+    Markdown,
+}
+
+/// `--color` の指定
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// 環境変数と端末から決める
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn parse(value: &str) -> Option<ColorChoice> {
+        match value {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// 色や太字を使うか
+    ///
+    /// auto では NO_COLOR（https://no-color.org）があれば使わず、CLICOLOR_FORCE があれば使い、
+    /// CLICOLOR=0 や色を出せない端末（TERM が未設定か dumb）なら使わない。
+    pub fn enabled(self) -> bool {
+        let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if var("NO_COLOR").is_some() => false,
+            ColorChoice::Auto if var("CLICOLOR_FORCE").is_some_and(|v| v != "0") => true,
+            ColorChoice::Auto if var("CLICOLOR").is_some_and(|v| v == "0") => false,
+            // Windows の端末は TERM を設定しないことが多い
+            ColorChoice::Auto if cfg!(windows) => true,
+            ColorChoice::Auto => var("TERM").is_some_and(|term| term != "dumb"),
+        }
+    }
+}
+
+/// コマンドライン引数
+#[derive(Default)]
+pub struct Cli {
+    pub command: Command,
+    /// `-v` の数（0: warn, 1: debug, 2 以上: trace）
+    pub verbose: u8,
+    pub help: bool,
+    /// コマンドを実行せずに表示する
+    pub dry_run: bool,
+    /// 設定ファイルの picker を上書きする
+    pub picker: Option<Picker>,
+    pub format: Format,
+    /// 候補を標準入力から読む
+    pub stdin: bool,
+    /// `serve` で標準入出力を使う
+    pub stdio: bool,
+    /// `serve` で JSON-RPC の代わりに MessagePack-RPC を使う
+    pub msgpack: bool,
+    /// `open` でセッションを作るだけでアタッチしない
+    pub detach: bool,
+    /// `open` でセッションを作り、アタッチするコマンドを出力する
+    pub print_attach: bool,
+    /// `open` で名前に当てはまるプロジェクトが複数あれば最初のものを開く
+    pub first: bool,
+    /// `open` で名前に当てはまるプロジェクトをすべてアタッチせずに開く。
+    /// `kill` では projector が作ったセッションをすべて終了する
+    pub all: bool,
+    /// 走査や描画にかかった時間を標準エラー出力に表示する
+    pub profile: bool,
+    /// TUI で色や太字を使うか
+    pub color: ColorChoice,
+    /// TUI の印や罫線を ASCII だけで描く
+    pub ascii: bool,
+}
+
+impl Cli {
+    /// 先頭のプログラム名を除いた引数を解析する
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli> {
+        let mut cli = Cli::default();
+        let mut positional = vec![];
+        let mut format = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => cli.help = true,
+                "--dry-run" => cli.dry_run = true,
+                "--stdin" => cli.stdin = true,
+                "--stdio" => cli.stdio = true,
+                "--msgpack" => cli.msgpack = true,
+                "--detach" => cli.detach = true,
+                "--print-attach" => cli.print_attach = true,
+                "--first" => cli.first = true,
+                "--all" => cli.all = true,
+                "--profile" => cli.profile = true,
+                "--ascii" => cli.ascii = true,
+                // 使える形式はコマンドで違うので、コマンドが分かってから読む
+                "--format" => {
+                    format = Some(args.next().ok_or(Error::MissingArgument("--format"))?);
+                }
+                "--color" => {
+                    let value = args.next().ok_or(Error::MissingArgument("--color"))?;
+                    cli.color = ColorChoice::parse(&value).ok_or(Error::UnknownArgument(value))?;
+                }
+                flag if flag.starts_with("--color=") => {
+                    cli.color = ColorChoice::parse(&flag["--color=".len()..])
+                        .ok_or_else(|| Error::UnknownArgument(arg.clone()))?;
+                }
+                "--picker" => {
+                    let value = args.next().ok_or(Error::MissingArgument("--picker"))?;
+                    cli.picker = Some(Picker::parse(&value).ok_or(Error::UnknownArgument(value))?);
+                }
+                "--verbose" => cli.verbose = cli.verbose.saturating_add(1),
+                // -vv のようにまとめて指定できる
+                flags if is_verbose_flags(flags) => {
+                    cli.verbose = cli.verbose.saturating_add(flags.len() as u8 - 1);
+                }
+                // `-` は標準入力を表すファイル名
+                "-" => positional.push(arg),
+                flag if flag.starts_with('-') => return Err(Error::UnknownArgument(arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        cli.command = match positional.next().as_deref() {
+            None => Command::Browse,
+            Some("list") => Command::List,
+            Some("open") => Command::Open {
+                name: positional.next().ok_or(Error::MissingArgument("<NAME>"))?,
+            },
+            Some("last") => Command::Last,
+            Some("doctor") => Command::Doctor,
+            Some("export") => Command::Export {
+                format: ExportFormat::default(),
+            },
+            Some("state") => match positional.next().as_deref() {
+                Some("export") => Command::StateExport {
+                    file: positional.next().map(PathBuf::from),
+                },
+                Some("import") => Command::StateImport {
+                    file: positional
+                        .next()
+                        .map(PathBuf::from)
+                        .ok_or(Error::MissingArgument("<FILE>"))?,
+                },
+                Some(other) => return Err(Error::UnknownArgument(other.to_string())),
+                None => return Err(Error::MissingArgument("export | import")),
+            },
+            Some("stats") => Command::Stats {
+                weeks: match positional.next() {
+                    None => DEFAULT_STATS_WEEKS,
+                    Some(weeks) => weeks
+                        .parse()
+                        .ok()
+                        .filter(|weeks| *weeks > 0)
+                        .ok_or(Error::UnknownArgument(weeks))?,
+                },
+            },
+            Some("import-history") => Command::ImportHistory {
+                files: positional.by_ref().map(PathBuf::from).collect(),
+            },
+            Some("daemon") => Command::Daemon,
+            Some("serve") => Command::Serve,
+            Some("resurrect") => Command::Resurrect {
+                names: positional.by_ref().collect(),
+            },
+            Some("kill") => match positional.next() {
+                None if !cli.all => return Err(Error::MissingArgument("<NAME>")),
+                name => Command::Kill { name },
+            },
+            Some(other) => return Err(Error::UnknownArgument(other.to_string())),
+        };
+        if let Some(extra) = positional.next() {
+            return Err(Error::UnknownArgument(extra));
+        }
+        // 表の形式は export だけで使え、export は表しか出力しない
+        if let Some(value) = format {
+            match &mut cli.command {
+                Command::Export { format } => {
+                    *format = match value.as_str() {
+                        "csv" => ExportFormat::Csv,
+                        "md" | "markdown" => ExportFormat::Markdown,
+                        _ => return Err(Error::UnknownArgument(format!("--format {}", value))),
+                    };
+                }
+                _ => {
+                    cli.format = match value.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        "raycast" => Format::Raycast,
+                        "alfred" => Format::Alfred,
+                        _ => return Err(Error::UnknownArgument(format!("--format {}", value))),
+                    };
+                }
+            }
+        }
+        Ok(cli)
+    }
+}
+
+fn is_verbose_flags(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_the_table_format_only_for_export() {
+        let cli = parse(&["export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: ExportFormat::Markdown
+            }
+        ));
+        let cli = parse(&["--format", "csv", "export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                format: ExportFormat::Csv
+            }
+        ));
+        assert!(parse(&["export", "--format", "json"]).is_err());
+        assert!(parse(&["list", "--format", "csv"]).is_err());
+        assert!(parse(&["list", "--format", "alfred"]).unwrap().format == Format::Alfred);
+    }
+}
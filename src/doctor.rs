@@ -0,0 +1,227 @@
+//! `projector doctor`: 動かすのに要るものを調べ、問題があれば直し方と一緒に表示する
+
+use std::fs;
+use std::path::Path;
+
+use projector_core::config::{self, Backend, Config};
+use projector_core::i18n::Msg;
+use projector_core::location::Location;
+use projector_core::runner::{Cmd, CommandRunner};
+use projector_core::session::{self, Feature, SessionSpec};
+use projector_core::{paths, shell, state, Error};
+
+/// 調べた結果
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    /// 動くが、使えない機能がある
+    Warn,
+    /// 直さないと動かない
+    Fail,
+}
+
+/// 1項目の結果
+struct Check {
+    status: Status,
+    /// 調べたもの（`tmux` `root` など）
+    subject: String,
+    detail: String,
+}
+
+impl Check {
+    fn new(status: Status, subject: impl Into<String>, detail: impl ToString) -> Self {
+        Check {
+            status,
+            subject: subject.into(),
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// クリップボードに書き込むコマンド（見つかった最初のもの）
+const CLIPBOARD_COMMANDS: &[&str] = &["pbcopy", "wl-copy", "xclip", "xsel", "clip.exe"];
+
+/// すべての項目を調べて表示する。直さないと動かない問題があれば false
+pub fn run(runner: &dyn CommandRunner) -> bool {
+    println!(
+        "projector {} ({}/{})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let (config, mut checks) = check_config();
+    let config = config.unwrap_or_default();
+    checks.extend(check_tmux(runner, &config));
+    checks.extend(check_roots(runner, &config));
+    checks.extend(check_cache());
+    checks.push(check_clipboard());
+
+    for check in &checks {
+        let mark = match check.status {
+            Status::Ok => "[ok]  ",
+            Status::Warn => "[warn]",
+            Status::Fail => "[fail]",
+        };
+        println!("{} {}: {}", mark, check.subject, check.detail);
+    }
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let (failures, warnings) = (count(Status::Fail), count(Status::Warn));
+    if failures + warnings == 0 {
+        println!("{}", Msg::DoctorAllGood);
+    } else {
+        println!("{}", Msg::DoctorSummary { failures, warnings });
+    }
+    failures == 0
+}
+
+/// 設定ファイルを読めるか。読めなければ既定の設定で残りを調べる
+fn check_config() -> (Option<Config>, Vec<Check>) {
+    let Some(path) = paths::config_file() else {
+        return (
+            None,
+            vec![Check::new(Status::Fail, "config", Error::NoHome)],
+        );
+    };
+    let shown = paths::shorten_path(&path);
+    if !path.exists() {
+        let check = Check::new(Status::Ok, "config", Msg::DoctorNoConfig(&shown));
+        return (None, vec![check]);
+    }
+    match config::load() {
+        Ok(config) => (Some(config), vec![Check::new(Status::Ok, "config", shown)]),
+        Err(e) => (None, vec![Check::new(Status::Fail, "config", e)]),
+    }
+}
+
+/// tmux があるか、設定のペイン構成に足りるバージョンか
+fn check_tmux(runner: &dyn CommandRunner, config: &Config) -> Vec<Check> {
+    if !shell::command_exists("tmux") {
+        // tmux を指定していなければシェルなどで開けるので、g や t が使えないだけ
+        let status = match config.backend {
+            Backend::Tmux => Status::Fail,
+            _ => Status::Warn,
+        };
+        return vec![Check::new(status, "tmux", Error::TmuxMissing)];
+    }
+    let Some(version) = session::version(runner) else {
+        return vec![Check::new(Status::Warn, "tmux", Msg::DoctorUnknownVersion)];
+    };
+    let mut spec = SessionSpec::new(&Location::Local(dirs::home_dir().unwrap_or_default()));
+    spec.options = config
+        .layout
+        .options
+        .iter()
+        .map(|(option, value)| (option.clone(), value.to_tmux()))
+        .collect();
+    let check = match session::check_version(runner, &spec) {
+        Ok(()) => Check::new(Status::Ok, "tmux", version),
+        Err(e) => Check::new(Status::Fail, "tmux", e),
+    };
+    let mut checks = vec![check];
+    // 開くのには困らないが、tmux のキーから display-popup で TUI を出せない
+    if let Err(e) = session::require(version, Feature::Popup) {
+        checks.push(Check::new(Status::Warn, "tmux popup", e));
+    }
+    checks
+}
+
+/// ルートがあって読めるか。リモートは ssh で接続できるか
+fn check_roots(runner: &dyn CommandRunner, config: &Config) -> Vec<Check> {
+    config
+        .roots()
+        .iter()
+        .map(|root| {
+            let location = Location::parse(root);
+            let subject = format!("root {}", location.display());
+            match &location {
+                Location::Local(path) => match fs::read_dir(path) {
+                    Ok(_) => Check::new(Status::Ok, subject, Msg::DoctorReadable),
+                    Err(_) if !path.exists() => {
+                        Check::new(Status::Fail, subject, Msg::DoctorMissing)
+                    }
+                    Err(e) => {
+                        Check::new(Status::Fail, subject, Msg::DoctorUnreadable(&e.to_string()))
+                    }
+                },
+                Location::Remote { host, .. } => {
+                    let cmd = Cmd::new("ssh")
+                        .args([
+                            "-o",
+                            "BatchMode=yes",
+                            "-o",
+                            "ConnectTimeout=5",
+                            host,
+                            "true",
+                        ])
+                        .read_only();
+                    if runner.output(&cmd).is_ok_and(|output| output.success) {
+                        Check::new(Status::Ok, subject, Msg::DoctorReachable)
+                    } else {
+                        Check::new(Status::Fail, subject, Msg::DoctorUnreachable(host))
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// キャッシュと状態のファイルが壊れていないか、キャッシュディレクトリに書き込めるか
+fn check_cache() -> Vec<Check> {
+    let mut checks = vec![];
+    if let Some(dir) = paths::cache_dir() {
+        let shown = paths::shorten_path(&dir);
+        match writable(&dir) {
+            Ok(()) => checks.push(Check::new(Status::Ok, "cache", shown)),
+            Err(e) => checks.push(Check::new(
+                Status::Warn,
+                "cache",
+                Msg::DoctorUnwritable {
+                    path: &shown,
+                    error: &e.to_string(),
+                },
+            )),
+        }
+        let files = fs::read_dir(&dir).into_iter().flatten().flatten();
+        for path in files.map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "json") && !is_json(&path) {
+                let shown = paths::shorten_path(&path);
+                checks.push(Check::new(Status::Warn, "cache", Msg::DoctorBroken(&shown)));
+            }
+        }
+    }
+    if let Some(path) = state::state_file().filter(|path| path.exists()) {
+        let shown = paths::shorten_path(&path);
+        if is_json(&path) {
+            checks.push(Check::new(Status::Ok, "state", shown));
+        } else {
+            checks.push(Check::new(Status::Warn, "state", Msg::DoctorBroken(&shown)));
+        }
+    }
+    checks
+}
+
+/// ディレクトリを作り、中にファイルを書いて消せるか
+fn writable(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".doctor");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+fn is_json(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some()
+}
+
+/// クリップボードに書き込むコマンドがあるか
+fn check_clipboard() -> Check {
+    match CLIPBOARD_COMMANDS
+        .iter()
+        .find(|command| shell::command_exists(command))
+    {
+        Some(command) => Check::new(Status::Ok, "clipboard", command),
+        None => Check::new(Status::Warn, "clipboard", Msg::DoctorNoClipboard),
+    }
+}
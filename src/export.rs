@@ -0,0 +1,131 @@
+//! `projector export`: 全ルートのプロジェクトを CSV か Markdown の表にして出力する
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use projector_core::config::Config;
+use projector_core::i18n::Msg;
+use projector_core::location::{self, Location};
+use projector_core::runner::CommandRunner;
+use projector_core::state::State;
+use projector_core::{daemon, git, index, project_type, timelog, Result};
+
+use crate::cli::ExportFormat;
+
+/// 表の1行
+struct Row {
+    path: String,
+    /// 種類（[`project_type::detect`]）。複数あれば `, ` でつなぐ
+    kind: String,
+    /// 最後に開いた日付
+    opened: String,
+    /// origin リモートの URL
+    remote: String,
+    tags: String,
+}
+
+/// CSV の見出し。表計算ソフトの列名に使うので訳さない
+const CSV_HEADER: &str = "path,type,last_opened,remote,tags";
+
+/// 全ルートの下のプロジェクトを、パスの順に表にして出力する
+pub fn run(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    config: &Config,
+    format: ExportFormat,
+) -> Result<()> {
+    let rows = rows(runner, roots, config);
+    // head などで途中まで読んで閉じられても失敗にしない
+    match print_table(&rows, format) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn print_table(rows: &[Row], format: ExportFormat) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    if format == ExportFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+        for row in rows {
+            let fields = [&row.path, &row.kind, &row.opened, &row.remote, &row.tags];
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            writeln!(out, "{}", fields.join(","))?;
+        }
+        return Ok(());
+    }
+    writeln!(out, "{}", Msg::ExportHeader)?;
+    writeln!(out, "| --- | --- | --- | --- | --- |")?;
+    for row in rows {
+        let fields = [&row.path, &row.kind, &row.opened, &row.remote, &row.tags];
+        let fields: Vec<String> = fields.iter().map(|field| markdown_cell(field)).collect();
+        writeln!(out, "| {} |", fields.join(" | "))?;
+    }
+    Ok(())
+}
+
+fn rows(runner: &dyn CommandRunner, roots: &[Location], config: &Config) -> Vec<Row> {
+    let options = config.scan_options();
+    let depth = config.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+    let entries =
+        daemon::walk(roots, &options, depth).unwrap_or_else(|| index::walk(roots, &options, depth));
+    let tags = tags_by_location(runner, roots, config);
+    let visits = State::load().visits;
+
+    let mut rows: Vec<Row> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            // ルート直下のプロジェクトでないディレクトリ（org など）は除く
+            let path = entry.location.local_path()?;
+            if !options.is_project(path) {
+                return None;
+            }
+            Some(Row {
+                path: entry.location.display(),
+                kind: project_type::detect(path).join(", "),
+                opened: visits
+                    .get(&entry.location.spec())
+                    .map(|visits| timelog::format_date(visits.last))
+                    .unwrap_or_default(),
+                remote: git::remote_url(runner, path).unwrap_or_default(),
+                tags: tags
+                    .get(&entry.location)
+                    .map(|tags| tags.join(", "))
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    rows.sort_by(|a, b| a.path.cmp(&b.path));
+    rows
+}
+
+/// プロジェクトごとの設定のタグ（名前順）
+fn tags_by_location(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    config: &Config,
+) -> HashMap<Location, Vec<String>> {
+    let options = config.scan_options();
+    let mut tags: HashMap<Location, Vec<String>> = HashMap::new();
+    for (tag, targets) in &config.tags {
+        for target in targets {
+            if let Some(location) = location::resolve(runner, roots, &options, target) {
+                tags.entry(location).or_default().push(tag.clone());
+            }
+        }
+    }
+    tags
+}
+
+/// CSV の1つの値。`,` `"` 改行を含むときだけ `"` で囲む
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Markdown の表の1つのセル。`|` は表の区切りになるのでエスケープする
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,193 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor, queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use unicode_width::UnicodeWidthChar;
+
+/// 文字の色と太字
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+}
+
+impl Style {
+    pub fn color(color: Option<Color>) -> Self {
+        Style { color, bold: false }
+    }
+
+    pub fn bold(color: Color) -> Self {
+        Style {
+            color: Some(color),
+            bold: true,
+        }
+    }
+}
+
+/// 画面の1マス
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cell {
+    /// 書く文字。全角文字の右半分は空
+    symbol: String,
+    style: Style,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Cell {
+            symbol: " ".to_string(),
+            style: Style::default(),
+        }
+    }
+
+    fn is_wide_tail(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+/// 端末に書き出す前の1画面分の内容
+///
+/// 描画はまずここに書き、できあがったものをまとめて端末に書き出す。
+/// 端末が無くても中身を文字列にできるので、描画をスナップショットで確かめられる。
+#[derive(Clone, Debug)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    /// 次に書く位置
+    x: u16,
+    y: u16,
+    /// 入力中の行でカーソルを出す位置。None なら隠す
+    cursor: Option<(u16, u16)>,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Self {
+        Frame {
+            width,
+            height,
+            cells: vec![Cell::blank(); width as usize * height as usize],
+            x: 0,
+            y: 0,
+            cursor: None,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// 次に書く位置を動かす
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// 次の行の先頭に移る
+    pub fn next_line(&mut self) {
+        self.move_to(0, self.y.saturating_add(1));
+    }
+
+    /// カーソルを出す位置
+    pub fn show_cursor(&mut self, x: u16, y: u16) {
+        self.cursor = Some((x, y));
+    }
+
+    /// 今の位置に書く。画面の外にはみ出す部分は捨てる
+    pub fn print(&mut self, text: &str, style: Style) {
+        for c in text.chars() {
+            let width = c.width().unwrap_or(0) as u16;
+            if width == 0 {
+                continue;
+            }
+            if self.y >= self.height || self.x + width > self.width {
+                // 右端で切れた全角文字の残りも書かない
+                self.x = self.x.saturating_add(width);
+                continue;
+            }
+            self.set(self.x, c.to_string(), style);
+            if width == 2 {
+                self.set(self.x + 1, String::new(), style);
+            }
+            self.x += width;
+        }
+    }
+
+    /// 1マスを書き換える。全角文字の片側だけを上書きしたら、残った側は空白にする
+    fn set(&mut self, x: u16, symbol: String, style: Style) {
+        let i = self.index(x, self.y);
+        if self.cells[i].is_wide_tail() && x > 0 {
+            self.cells[i - 1] = Cell::blank();
+        }
+        if x + 1 < self.width && self.cells[i + 1].is_wide_tail() {
+            self.cells[i + 1] = Cell::blank();
+        }
+        self.cells[i] = Cell { symbol, style };
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    fn row(&self, y: u16) -> &[Cell] {
+        let start = self.index(0, y);
+        &self.cells[start..start + self.width as usize]
+    }
+
+    /// 色を除いた中身。行末の空白は除く
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        (0..self.height)
+            .map(|y| {
+                let line: String = self
+                    .row(y)
+                    .iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect();
+                format!("{}\n", line.trim_end())
+            })
+            .collect()
+    }
+
+    /// 画面を消して中身を書き出す
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, cursor::Hide, terminal::Clear(ClearType::All))?;
+        for y in 0..self.height {
+            let row = self.row(y);
+            // 行末の何も書いていない部分は飛ばす
+            let end = row
+                .iter()
+                .rposition(|cell| *cell != Cell::blank())
+                .map_or(0, |i| i + 1);
+            queue!(out, cursor::MoveTo(0, y))?;
+            let mut style = Style::default();
+            for cell in row[..end].iter().filter(|cell| !cell.is_wide_tail()) {
+                if cell.style != style {
+                    queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+                    if cell.style.bold {
+                        queue!(out, SetAttribute(Attribute::Bold))?;
+                    }
+                    if let Some(color) = cell.style.color {
+                        queue!(out, SetForegroundColor(color))?;
+                    }
+                    style = cell.style;
+                }
+                queue!(out, Print(&cell.symbol))?;
+            }
+            if style != Style::default() {
+                queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+            }
+        }
+        if let Some((x, y)) = self.cursor {
+            queue!(out, cursor::MoveTo(x, y), cursor::Show)?;
+        }
+        out.flush()
+    }
+}
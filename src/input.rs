@@ -0,0 +1,150 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_width::UnicodeWidthStr;
+
+/// カーソルを動かして編集できる1行の入力（絞り込み・名前の変更・移動先など）
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    text: String,
+    /// カーソルの位置（text のバイト位置。常に文字の境界）
+    cursor: usize,
+}
+
+/// キー入力を受けた結果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// 文字列が変わった
+    Changed,
+    /// カーソルだけ動いた
+    Moved,
+    /// 入力で使わないキー
+    Ignored,
+}
+
+impl TextInput {
+    /// `text` を入れ、カーソルを末尾に置く
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let cursor = text.len();
+        TextInput { text, cursor }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// 文字列を入れ替え、カーソルを末尾に置く
+    pub fn set(&mut self, text: impl Into<String>) {
+        *self = TextInput::new(text);
+    }
+
+    /// カーソルより前の部分の表示幅
+    pub fn cursor_width(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    /// カーソルの位置に文字列を入れる。貼り付けた改行やタブは空白にする
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// キーを1つ受け取って編集する
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Edit {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let before = (self.text.len(), self.cursor);
+        match key.code {
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::Char('e') if control => self.cursor = self.text.len(),
+            KeyCode::Char('b') if control => self.cursor = self.prev(),
+            KeyCode::Char('f') if control => self.cursor = self.next(),
+            KeyCode::Char('b') if alt => self.cursor = self.prev_word(),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(),
+            KeyCode::Char('w') if control => self.delete_to(self.prev_word()),
+            KeyCode::Char('u') if control => self.delete_to(0),
+            KeyCode::Char('k') if control => self.delete_to(self.text.len()),
+            KeyCode::Char('d') if control => self.delete_to(self.next()),
+            KeyCode::Char(c) if !control && !alt => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace if control || alt => self.delete_to(self.prev_word()),
+            KeyCode::Backspace => self.delete_to(self.prev()),
+            KeyCode::Delete => self.delete_to(self.next()),
+            KeyCode::Left if control || alt => self.cursor = self.prev_word(),
+            KeyCode::Right if control || alt => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.prev(),
+            KeyCode::Right => self.cursor = self.next(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return Edit::Ignored,
+        }
+        if self.text.len() != before.0 {
+            Edit::Changed
+        } else if self.cursor != before.1 {
+            Edit::Moved
+        } else {
+            Edit::Ignored
+        }
+    }
+
+    /// カーソルと `to` の間を消す
+    fn delete_to(&mut self, to: usize) {
+        let (start, end) = if to < self.cursor {
+            (to, self.cursor)
+        } else {
+            (self.cursor, to)
+        };
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    fn prev(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// カーソルの前の単語の先頭。単語は英数字の並びで、`/` や `-` などで区切る
+    fn prev_word(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        let end = before
+            .trim_end_matches(|c: char| !c.is_alphanumeric())
+            .len();
+        before[..end]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_alphanumeric())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// カーソルの後の単語の末尾
+    fn next_word(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let start = after.len()
+            - after
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .len();
+        after[start..]
+            .char_indices()
+            .find(|(_, c)| !c.is_alphanumeric())
+            .map_or(self.text.len(), |(i, _)| self.cursor + start + i)
+    }
+}
@@ -0,0 +1,524 @@
+//! TUI のキー操作
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tracing::warn;
+
+use projector_core::config::KeysConfig;
+use projector_core::location::Location;
+use projector_core::state::State;
+
+use crate::app::{Action, App, PromptKind};
+
+/// キーを受けた後にすること
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// 選び続ける
+    Continue,
+    /// 何も開かずに終了する
+    Quit,
+    /// 設定を読み込み直す
+    Reload,
+    /// プロジェクトを開く
+    Launch(Action, Location),
+}
+
+/// キーを1つ処理する。端末を使わないので、キー操作をそのまま再現して確かめられる
+pub fn handle(app: &mut App, key: &KeyEvent) -> Outcome {
+    // エラーは閉じるまで他のキーを渡さない
+    if app.error.is_some() {
+        if matches!(
+            key.code,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char(' ' | 'q')
+        ) {
+            app.error = None;
+        }
+        return Outcome::Continue;
+    }
+
+    // 確認ダイアログには、答えるまで他のキーを渡さない
+    if let Some(confirm) = &app.confirm {
+        let launch = match key.code {
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => app.answer_confirm(false),
+            KeyCode::Char('y') => app.answer_confirm(true),
+            KeyCode::Enter => app.answer_confirm(confirm.yes),
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Tab
+            | KeyCode::BackTab
+            | KeyCode::Char('h' | 'l') => {
+                app.toggle_confirm();
+                None
+            }
+            _ => None,
+        };
+        return match launch {
+            Some((action, location)) => Outcome::Launch(action, location),
+            None => Outcome::Continue,
+        };
+    }
+
+    // タスクの選択中
+    if app.task_picker.is_some() {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => app.task_picker = None,
+            KeyCode::Up | KeyCode::Char('k') => app.move_task(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_task(true),
+            KeyCode::Enter => {
+                if let Some((action, location)) = app.choose_task() {
+                    return Outcome::Launch(action, location);
+                }
+            }
+            _ => {}
+        }
+        return Outcome::Continue;
+    }
+
+    // 種類・タグの選択中
+    if app.type_picker.is_some() {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => app.type_picker = None,
+            KeyCode::Up | KeyCode::Char('k') => app.move_type(false),
+            KeyCode::Down | KeyCode::Char('j') => app.move_type(true),
+            KeyCode::Enter => app.choose_type(),
+            _ => {}
+        }
+        return Outcome::Continue;
+    }
+
+    // 移動先や検索語の入力中は文字をそのまま受け取る
+    if app.prompt.is_some() {
+        match key.code {
+            KeyCode::Esc => app.prompt = None,
+            KeyCode::Tab => app.complete_prompt(),
+            KeyCode::Enter => app.finish_prompt(),
+            _ => app.edit_prompt(key),
+        }
+        return Outcome::Continue;
+    }
+
+    // 絞り込み中は文字を検索語として受け取る
+    if app.filter.is_some() {
+        match key.code {
+            KeyCode::Esc => app.clear_filter(),
+            KeyCode::Up => app.move_up(),
+            KeyCode::Down => app.move_down(),
+            KeyCode::Enter => {
+                if let Some((action, location)) = app.launch_target(Action::Open) {
+                    return Outcome::Launch(action, location);
+                }
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.toggle_regex()
+            }
+            _ => app.edit_filter(key),
+        }
+        return Outcome::Continue;
+    }
+
+    // リーダーキーの後は、割り当てのあるキーの続きを待つ
+    if let Some(pressed) = app.chord.take() {
+        return chord(app, pressed, key);
+    }
+    if key.code == KeyCode::Char(app.keymap.leader)
+        && !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    {
+        app.chord = Some(String::new());
+        return Outcome::Continue;
+    }
+
+    match key.code {
+        KeyCode::Esc if app.finding() => app.stop_find(),
+        KeyCode::Esc if app.type_filter.is_some() => app.set_type_filter(None),
+        KeyCode::Char('q') | KeyCode::Esc => return Outcome::Quit,
+        KeyCode::Up | KeyCode::Char('k') => app.move_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.move_down(),
+        // H / L または Alt+←→: 履歴を戻る・進む
+        KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) => app.history_back(),
+        KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) => app.history_forward(),
+        KeyCode::Char('H') => app.history_back(),
+        KeyCode::Char('L') => app.history_forward(),
+        // スペースまたは→: ディレクトリに入る（プロジェクトの中には > で入る）
+        KeyCode::Char(' ') | KeyCode::Right => app.enter(false),
+        KeyCode::Char('>') => app.enter(true),
+        // Backspaceまたは←: 親ディレクトリに戻る
+        KeyCode::Backspace | KeyCode::Left => app.back(),
+        KeyCode::Enter
+            if key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            // Ctrl+Enter / Alt+Enter: 今いるディレクトリを開く
+            if let Some((action, location)) = app.launch_current() {
+                return Outcome::Launch(action, location);
+            }
+        }
+        code => {
+            if let Some(command) = app.keymap.key(code) {
+                return run(app, command);
+            }
+        }
+    }
+    Outcome::Continue
+}
+
+/// リーダーキーの後のキーを処理する。まとまりのキーなら続きを待ち、割り当ての無いキーや Esc でやめる
+fn chord(app: &mut App, mut pressed: String, key: &KeyEvent) -> Outcome {
+    let KeyCode::Char(c) = key.code else {
+        return Outcome::Continue;
+    };
+    pressed.push(c);
+    match app.keymap.lookup(&pressed) {
+        Some(Binding::Command(command)) => run(app, command),
+        Some(Binding::Group(_)) => {
+            app.chord = Some(pressed);
+            Outcome::Continue
+        }
+        None => Outcome::Continue,
+    }
+}
+
+/// キーに割り当てられる操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// tmux で開く（Enter）
+    Open,
+    /// git の UI を並べて開く（g）
+    Git,
+    /// 今の tmux セッションに新しいウィンドウで開く（O）
+    Window,
+    /// ウィンドウマネージャーのプロジェクトのワークスペースに端末を開く（w）
+    Workspace,
+    /// ファイルマネージャーで中を見る（e）
+    Files,
+    /// devcontainer 内で開く（d）
+    Devcontainer,
+    /// docker compose up してから開く（c）
+    Compose,
+    /// 端末アプリの新しいウィンドウで開く（n、macOS）
+    NewWindow,
+    /// タスクを選んで実行する（t）
+    Tasks,
+    /// 1つ前に開いたプロジェクトをもう一度開く（-）
+    Previous,
+    /// 全ルートのプロジェクトから探す（f）
+    Find,
+    /// プロジェクトの内容から探す（s）
+    Search,
+    /// 絞り込む（/）
+    Filter,
+    /// パスを入力して移動する（:）
+    Jump,
+    /// 種類・タグで絞り込む（T）
+    Types,
+    /// 並び順を切り替える（o）
+    Sort,
+    /// 隠しディレクトリの表示を切り替える（.）
+    Hidden,
+    /// 詳細を計算し直す（r）
+    Refresh,
+    /// 設定を読み込み直す（R）
+    Reload,
+    /// 名前を変える（m）
+    Rename,
+    /// ディレクトリを作る（N）
+    NewDir,
+    /// アーカイブに移す（a）
+    Archive,
+    /// ゴミ箱に移す（x）
+    Trash,
+    /// tmux セッションを終了する（K）
+    KillSession,
+    /// 直前の操作を取り消す（u）
+    Undo,
+    /// 終了する（q）
+    Quit,
+}
+
+/// 設定で使う操作の名前
+const COMMANDS: &[(Command, &str)] = &[
+    (Command::Open, "open"),
+    (Command::Git, "git"),
+    (Command::Window, "window"),
+    (Command::Workspace, "workspace"),
+    (Command::Files, "files"),
+    (Command::Devcontainer, "devcontainer"),
+    (Command::Compose, "compose"),
+    (Command::NewWindow, "new_window"),
+    (Command::Tasks, "tasks"),
+    (Command::Previous, "previous"),
+    (Command::Find, "find"),
+    (Command::Search, "search"),
+    (Command::Filter, "filter"),
+    (Command::Jump, "jump"),
+    (Command::Types, "types"),
+    (Command::Sort, "sort"),
+    (Command::Hidden, "hidden"),
+    (Command::Refresh, "refresh"),
+    (Command::Reload, "reload"),
+    (Command::Rename, "rename"),
+    (Command::NewDir, "new_dir"),
+    (Command::Archive, "archive"),
+    (Command::Trash, "trash"),
+    (Command::KillSession, "kill_session"),
+    (Command::Undo, "undo"),
+    (Command::Quit, "quit"),
+];
+
+impl Command {
+    /// 設定で使う名前
+    pub fn name(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(command, _)| *command == self)
+            .map(|(_, name)| *name)
+            .expect("every command has a name")
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        COMMANDS
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(command, _)| *command)
+    }
+}
+
+/// 1つのキーで行う操作。ヘッダーの操作説明もこの順に並べる
+const DEFAULT_KEYS: &[(KeyCode, Command)] = &[
+    (KeyCode::Enter, Command::Open),
+    (KeyCode::Char('g'), Command::Git),
+    (KeyCode::Char('O'), Command::Window),
+    (KeyCode::Char('e'), Command::Files),
+    (KeyCode::Char('t'), Command::Tasks),
+    (KeyCode::Char('-'), Command::Previous),
+    (KeyCode::Char('/'), Command::Filter),
+    (KeyCode::Char('T'), Command::Types),
+    (KeyCode::Char('o'), Command::Sort),
+    (KeyCode::Char('.'), Command::Hidden),
+    (KeyCode::Char('m'), Command::Rename),
+    (KeyCode::Char('N'), Command::NewDir),
+    (KeyCode::Char('a'), Command::Archive),
+    (KeyCode::Char('x'), Command::Trash),
+    (KeyCode::Char('K'), Command::KillSession),
+    (KeyCode::Char('u'), Command::Undo),
+    (KeyCode::Char('f'), Command::Find),
+    (KeyCode::Char('s'), Command::Search),
+    (KeyCode::Char(':'), Command::Jump),
+    (KeyCode::Char('r'), Command::Refresh),
+    (KeyCode::Char('R'), Command::Reload),
+    (KeyCode::Char('d'), Command::Devcontainer),
+    (KeyCode::Char('c'), Command::Compose),
+    (KeyCode::Char('n'), Command::NewWindow),
+    (KeyCode::Char('w'), Command::Workspace),
+    (KeyCode::Char('q'), Command::Quit),
+];
+
+/// リーダーキーの後の既定の割り当て。1つ目のキーでまとまりを選ぶ
+const DEFAULT_CHORDS: &[(&str, Command)] = &[
+    ("oo", Command::Open),
+    ("og", Command::Git),
+    ("ow", Command::Window),
+    ("oW", Command::Workspace),
+    ("oe", Command::Files),
+    ("od", Command::Devcontainer),
+    ("oc", Command::Compose),
+    ("on", Command::NewWindow),
+    ("ot", Command::Tasks),
+    ("o-", Command::Previous),
+    ("vf", Command::Find),
+    ("vs", Command::Search),
+    ("v/", Command::Filter),
+    ("v:", Command::Jump),
+    ("vt", Command::Types),
+    ("vo", Command::Sort),
+    ("v.", Command::Hidden),
+    ("vr", Command::Refresh),
+    ("pm", Command::Rename),
+    ("pn", Command::NewDir),
+    ("pa", Command::Archive),
+    ("px", Command::Trash),
+    ("pk", Command::KillSession),
+    ("pu", Command::Undo),
+    ("R", Command::Reload),
+    ("q", Command::Quit),
+];
+
+/// 既定のまとまりのキーと名前
+const GROUPS: &[(&str, &str)] = &[("o", "open"), ("v", "view"), ("p", "project")];
+
+/// 押したキーの割り当て
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+    /// 操作
+    Command(Command),
+    /// 続きのキーがあるまとまり（名前が分からなければ空）
+    Group(String),
+}
+
+/// 1つのキーの割り当てと、リーダーキーの後に続けて押すキーの割り当て
+pub struct Keymap {
+    /// 続けて押すキーの操作を始めるキー
+    pub leader: char,
+    /// 1つのキーと操作
+    keys: Vec<(KeyCode, Command)>,
+    /// リーダーキーの後に押すキーと操作
+    chords: Vec<(String, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(&KeysConfig::default())
+    }
+}
+
+impl Keymap {
+    /// 既定の割り当てに設定の割り当てを重ねる。読めない割り当ては警告して無視する
+    pub fn new(config: &KeysConfig) -> Self {
+        let mut chords: Vec<(String, Command)> = DEFAULT_CHORDS
+            .iter()
+            .map(|(keys, command)| (keys.to_string(), *command))
+            .collect();
+        for (keys, name) in &config.chords {
+            let (Some(sequence), Some(command)) = (parse_keys(keys), Command::parse(name)) else {
+                warn!(keys, command = name, "ignoring invalid key chord");
+                continue;
+            };
+            // 操作の割り当てとまとまりが重ならないよう、前後にあたる割り当ては外す
+            chords.retain(|(other, _)| {
+                !other.starts_with(sequence.as_str()) && !sequence.starts_with(other.as_str())
+            });
+            chords.push((sequence, command));
+        }
+        // リーダーキーに取られたキーは1つのキーの操作に使わない
+        let keys = DEFAULT_KEYS
+            .iter()
+            .filter(|(code, _)| *code != KeyCode::Char(config.leader))
+            .copied()
+            .collect();
+        Keymap {
+            leader: config.leader,
+            keys,
+            chords,
+        }
+    }
+
+    /// 1つのキーの操作
+    pub fn key(&self, code: KeyCode) -> Option<Command> {
+        self.keys
+            .iter()
+            .find(|(key, _)| *key == code)
+            .map(|(_, command)| *command)
+    }
+
+    /// 1つのキーの割り当て（操作説明に並べる順）
+    pub fn keys(&self) -> &[(KeyCode, Command)] {
+        &self.keys
+    }
+
+    /// リーダーキーの後に押したキーの割り当て
+    pub fn lookup(&self, pressed: &str) -> Option<Binding> {
+        if let Some((_, command)) = self.chords.iter().find(|(keys, _)| keys == pressed) {
+            return Some(Binding::Command(*command));
+        }
+        self.chords
+            .iter()
+            .any(|(keys, _)| keys.starts_with(pressed))
+            .then(|| Binding::Group(group_name(pressed)))
+    }
+
+    /// `pressed` の後に押せるキーと割り当て（キーの順）
+    pub fn next_keys(&self, pressed: &str) -> Vec<(char, Binding)> {
+        let mut keys: Vec<char> = self
+            .chords
+            .iter()
+            .filter_map(|(keys, _)| keys.strip_prefix(pressed)?.chars().next())
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys.into_iter()
+            .filter_map(|key| {
+                let mut next = pressed.to_string();
+                next.push(key);
+                Some((key, self.lookup(&next)?))
+            })
+            .collect()
+    }
+}
+
+/// 操作説明に出すキーの名前
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        code => code.to_string(),
+    }
+}
+
+/// 設定のキーの並び（`o g`、`space f`）。1文字でないキーは `space` だけ読める
+fn parse_keys(keys: &str) -> Option<String> {
+    let sequence: Option<String> = keys
+        .split_whitespace()
+        .map(|key| match key {
+            "space" => Some(' '),
+            _ => {
+                let mut chars = key.chars();
+                let c = chars.next()?;
+                chars.next().is_none().then_some(c)
+            }
+        })
+        .collect();
+    sequence.filter(|sequence| !sequence.is_empty())
+}
+
+fn group_name(pressed: &str) -> String {
+    GROUPS
+        .iter()
+        .find(|(keys, _)| *keys == pressed)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_default()
+}
+
+/// 操作を実行する
+fn run(app: &mut App, command: Command) -> Outcome {
+    match command {
+        Command::Open => return launch(app, Action::Open),
+        Command::Git => return launch(app, Action::Git),
+        Command::Window => return launch(app, Action::Window),
+        Command::Workspace => return launch(app, Action::Workspace),
+        Command::Files => return launch(app, Action::Files),
+        Command::Devcontainer => return launch(app, Action::Devcontainer),
+        Command::Compose => return launch(app, Action::Compose),
+        Command::NewWindow => return launch(app, Action::NewWindow),
+        Command::Previous => {
+            if let Some(location) = State::load().previous() {
+                return Outcome::Launch(Action::Open, location);
+            }
+        }
+        Command::Reload => return Outcome::Reload,
+        Command::Quit => return Outcome::Quit,
+        Command::Tasks => app.open_tasks(),
+        Command::Find => app.start_find(),
+        Command::Search => app.start_prompt(PromptKind::Search),
+        Command::Filter => app.start_filter(),
+        Command::Jump => app.start_prompt(PromptKind::Jump),
+        Command::Types => app.open_type_picker(),
+        Command::Sort => app.cycle_sort(),
+        Command::Hidden => app.toggle_hidden(),
+        Command::Refresh => app.refresh_details(),
+        Command::Rename => app.start_rename(),
+        Command::NewDir => app.start_prompt(PromptKind::NewDir),
+        Command::Archive => app.archive_selected(),
+        Command::Trash => app.trash_selected(),
+        Command::KillSession => app.kill_session_selected(),
+        Command::Undo => app.undo(),
+    }
+    Outcome::Continue
+}
+
+fn launch(app: &mut App, action: Action) -> Outcome {
+    match app.launch_target(action) {
+        Some((action, location)) => Outcome::Launch(action, location),
+        None => Outcome::Continue,
+    }
+}
@@ -1,269 +1,1321 @@
-use std::fs;
-use std::io::{self, Write};
-use std::path::PathBuf;
-use std::process::{Command, exit};
+mod app;
+mod cli;
+mod doctor;
+mod export;
+mod frame;
+mod input;
+mod keys;
+mod serve;
+mod stats;
+#[cfg(test)]
+mod tests;
+mod ui;
+mod wizard;
+mod worker;
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyEventKind},
     execute,
-    style::{Color, Print, ResetColor, SetForegroundColor},
-    terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
-fn get_developer_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|home| home.join("Developer"))
-}
+use projector_core::bundle::{Bundle, Merged};
+use projector_core::config::{self, Backend, Config, Picker};
+use projector_core::i18n::{self, Lang, Msg};
+use projector_core::location::{self, Entry, Location};
+use projector_core::paths;
+use projector_core::profile;
+use projector_core::runner::{Cmd, CommandRunner, DryRunRunner, SystemRunner};
+use projector_core::session::{self, SessionSpec};
+use projector_core::state::{BrowsePosition, State, ViewPrefs};
+use projector_core::tasks::Task;
+use projector_core::template::Vars;
+use projector_core::wm::WindowManager;
+use projector_core::{
+    cache, compose, daemon, devcontainer, fallback, fzf, ghostty, history, index, iterm, macos,
+    menu, nix, shell, timelog, tmux_resurrect, wt, Error, ExitCode, Result,
+};
 
-fn get_directories(path: &PathBuf) -> Vec<String> {
-    let mut dirs = Vec::new();
+use serde_json::json;
+use tracing::level_filters::LevelFilter;
+use tracing::{error, info, warn};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
 
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let entry_path = entry.path();
-            if entry_path.is_dir() {
-                if let Some(name) = entry_path.file_name() {
-                    if let Some(name_str) = name.to_str() {
-                        // 隠しディレクトリをスキップ
-                        if !name_str.starts_with('.') {
-                            dirs.push(name_str.to_string());
-                        }
-                    }
-                }
+use app::{Action, App};
+use cli::{Cli, Command, Format};
+use keys::Outcome;
+
+// ワーカーの結果を拾うためのイベント待ちタイムアウト
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 設定の仕組みでセッションを開く。リモートのプロジェクトは ssh 先の tmux を使う
+fn start_session(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    host: Option<&str>,
+    spec: &SessionSpec,
+) -> Result<()> {
+    match (config.backend.resolve(), host) {
+        (Backend::WindowsTerminal, host) => wt::open(runner, host, spec),
+        (Backend::Iterm, host) => iterm::open(runner, host, spec),
+        (Backend::Ghostty, host) => ghostty::open(runner, host, spec),
+        (_, Some(host)) => session::start_remote(runner, host, spec),
+        (Backend::Shell, None) => {
+            if config.backend != Backend::Shell {
+                eprintln!("{}", Msg::NoMultiplexer);
             }
+            fallback::open(runner, &config.fallback.shell(), spec)
+        }
+        (_, None) => {
+            remember_session(runner, spec);
+            let result = session::start_local(runner, spec);
+            // アタッチしていた間の作業時間を、デタッチしたところで記録する
+            timelog::poll(runner);
+            result
         }
     }
+}
 
-    dirs.sort();
-    dirs
+/// 端末で y/N を尋ねる。端末でなければ尋ねずに No
+fn confirm(question: &Msg) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
 }
 
-fn start_tmux_session(session_name: &str, project_path: &PathBuf) -> Result<(), String> {
-    let session_name = session_name.to_lowercase();
-    let path_str = project_path.to_string_lossy();
+/// `open` や `kill` の名前に当てはまるプロジェクト。エイリアスを優先し、
+/// ランチャーなどから渡されるパスも受け付ける
+fn find_projects(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    config: &Config,
+    name: &str,
+) -> Vec<Location> {
+    let options = config.scan_options();
+    let alias = config
+        .aliases
+        .get(name)
+        .and_then(|target| location::resolve(runner, roots, &options, target));
+    match alias {
+        Some(location) => vec![location],
+        None if location::is_path(name) => location::resolve(runner, roots, &options, name)
+            .into_iter()
+            .collect(),
+        None => {
+            let depth = config.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+            location::find_all(runner, roots, &options, depth, name)
+        }
+    }
+}
 
-    // セッションが既に存在するかチェック
-    let check = Command::new("tmux")
-        .args(["has-session", "-t", &session_name])
-        .output();
+/// シェルの履歴で cd したプロジェクトを数え、開いた回数として覚える。`files` が空なら既定の履歴ファイル
+///
+/// 既に回数のあるプロジェクトは、projector で開いた回数のほうが確かなので変えない。
+fn import_history(
+    roots: &[Location],
+    config: &Config,
+    files: &[PathBuf],
+    dry_run: bool,
+) -> Result<()> {
+    let files = if files.is_empty() {
+        history::default_files()
+    } else {
+        files.to_vec()
+    };
+    if files.is_empty() {
+        println!("{}", Msg::NoHistoryFiles);
+        return Ok(());
+    }
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .filter_map(|root| root.local_path().map(Path::to_path_buf))
+        .collect();
+    let visits = history::import(&files, &roots, &config.scan_options());
+
+    let mut state = State::load();
+    let (mut imported, mut skipped) = (0, 0);
+    for (path, visits) in visits {
+        let location = Location::Local(path);
+        if state.visits.contains_key(&location.spec()) {
+            skipped += 1;
+            continue;
+        }
+        println!(
+            "{}",
+            Msg::HistoryVisits {
+                name: &location.display(),
+                count: visits.count,
+            }
+        );
+        state.visits.insert(location.spec(), visits);
+        imported += 1;
+    }
+    if !dry_run {
+        state.save();
+    }
+    println!("{}", Msg::HistoryImported { imported, skipped });
+    Ok(())
+}
 
-    if let Ok(output) = check {
-        if output.status.success() {
-            println!("セッション '{}' は既に存在します。アタッチします...", session_name);
-            let status = Command::new("tmux")
-                .args(["attach-session", "-t", &session_name])
-                .status()
-                .map_err(|e| format!("tmux attach failed: {}", e))?;
+/// エイリアス・タグ・開いた履歴を JSON で書き出す。`file` が None なら標準出力
+fn export_state(config: &Config, file: Option<&Path>) -> Result<()> {
+    let bundle = Bundle::collect(config, &State::load());
+    let content = serde_json::to_string_pretty(&bundle).expect("the state always serializes");
+    let Some(file) = file else {
+        println!("{}", content);
+        return Ok(());
+    };
+    fs::write(file, content + "\n")?;
+    println!("{}", Msg::StateExported(&paths::shorten_path(file)));
+    Ok(())
+}
+
+/// `export_state` で書き出したものを、上書きせずに設定ファイルと状態に合わせる。`-` なら標準入力から読む
+fn import_state(file: &Path, dry_run: bool) -> Result<()> {
+    let content = if file == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(file)?
+    };
+    let bundle = Bundle::parse(&content)?;
+    let config_path = paths::config_file().ok_or(Error::NoHome)?;
+    let mut merged = Merged::default();
+    bundle.merge_config(&config_path, &mut merged, dry_run)?;
+    let mut state = State::load();
+    bundle.merge_state(&mut state, &mut merged);
+    if !dry_run {
+        state.save();
+    }
+    for (name, local, imported) in &merged.conflicts {
+        println!(
+            "{}",
+            Msg::AliasConflict {
+                name,
+                local,
+                imported,
+            }
+        );
+    }
+    println!(
+        "{}",
+        Msg::StateImported {
+            aliases: merged.aliases,
+            tags: merged.tags,
+            visits: merged.visits,
+            spans: merged.spans,
+        }
+    );
+    Ok(())
+}
 
-            if !status.success() {
-                return Err("tmux attach-session に失敗しました".to_string());
+/// projector が作ったセッションを終了する。`name` が None なら覚えているセッションを全部
+///
+/// プロジェクトの名前なら、そのプロジェクトを開く操作で作られるセッションをすべて終了する。
+/// プロジェクトが見つからなければ、tmux のセッション名として扱う。
+fn kill_sessions(
+    runner: &dyn CommandRunner,
+    roots: &[Location],
+    config: &Config,
+    name: Option<&str>,
+) -> Result<()> {
+    let mut names: Vec<String> = match name {
+        None => State::load()
+            .sessions
+            .into_iter()
+            .map(|spec| spec.name)
+            .collect(),
+        Some(name) => {
+            let locations = find_projects(runner, roots, config, name);
+            if locations.is_empty() {
+                vec![session::session_name(name)]
+            } else {
+                locations
+                    .iter()
+                    .flat_map(|location| {
+                        [Action::Open, Action::Git, Action::Devcontainer]
+                            .map(|action| action.session_name(location))
+                    })
+                    .collect()
             }
-            return Ok(());
         }
+    };
+    names.sort();
+    names.dedup();
+    let running: Vec<String> = names
+        .into_iter()
+        .filter(|name| session::exists(runner, name))
+        .collect();
+    if running.is_empty() {
+        match name {
+            Some(name) => println!("{}", Msg::NoSession(&session::session_name(name))),
+            None => println!("{}", Msg::NoRunningSessions),
+        }
+        return Ok(());
     }
+    for name in &running {
+        session::kill(runner, name)?;
+        if !runner.dry_run() {
+            println!("{}", Msg::SessionKilled(name));
+        }
+    }
+    Ok(())
+}
 
-    // 新規セッションをバックグラウンドで作成
-    let status = Command::new("tmux")
-        .args(["new-session", "-d", "-s", &session_name, "-c", &path_str])
-        .status()
-        .map_err(|e| format!("tmux new-session failed: {}", e))?;
+/// 名前に当てはまったプロジェクトから1つ選ぶ。複数あれば端末で番号を尋ね、端末でなければエラーにする。
+/// 番号を選ばなければ None
+fn choose_match(name: &str, mut matches: Vec<Location>, first: bool) -> Result<Option<Location>> {
+    if matches.is_empty() {
+        return Err(Error::NoMatch(name.to_string()));
+    }
+    if matches.len() == 1 || first {
+        return Ok(Some(matches.swap_remove(0)));
+    }
+    // --print-attach などで標準出力を読まれていても尋ねられるよう、標準エラー出力に出す
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Err(Error::Ambiguous {
+            name: name.to_string(),
+            matches: matches.iter().map(Location::display).collect(),
+        });
+    }
+    eprintln!("{}", Msg::AmbiguousName(name));
+    for (i, location) in matches.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, location.display());
+    }
+    eprint!("{}", Msg::ChooseMatch(matches.len()));
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let chosen = answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|i| (1..=matches.len()).contains(i));
+    Ok(chosen.map(|i| matches.swap_remove(i - 1)))
+}
+
+/// 開いたセッションにどうつなぐか
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Attach {
+    /// この端末でアタッチする
+    Here,
+    /// セッションを作るだけ（`--detach`、ランチャーなど端末の無いところから開くとき）
+    Detach,
+    /// セッションを作り、アタッチするコマンドを出力する（`--print-attach`）
+    Print,
+}
 
-    if !status.success() {
-        return Err("tmux new-session に失敗しました".to_string());
+/// アタッチせずにセッションを作る。`Attach::Print` なら標準出力にアタッチするコマンドだけを出す。
+/// tmux 以外はアタッチという段階が無いので、普段どおり開く
+fn create_detached(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    location: &Location,
+    spec: &SessionSpec,
+    attach: Attach,
+) -> Result<()> {
+    if location.host().is_some() {
+        return Err(Error::RemoteUnsupported(match attach {
+            Attach::Print => "--print-attach",
+            _ => "--detach",
+        }));
+    }
+    if config.backend.resolve() != Backend::Tmux {
+        return start_session(runner, config, None, spec);
     }
+    remember_session(runner, spec);
+    let created = session::ensure(runner, spec)?;
+    if attach == Attach::Print {
+        println!("{}", session::attach_command(&spec.name));
+    } else if !created && !runner.dry_run() {
+        println!("{}", Msg::AlreadyRunning(&spec.name));
+    }
+    Ok(())
+}
 
-    // 垂直分割
-    let status = Command::new("tmux")
-        .args(["split-window", "-h", "-t", &session_name, "-c", &path_str])
-        .status()
-        .map_err(|e| format!("tmux split-window failed: {}", e))?;
+/// ローカルの tmux に作るセッションを `projector resurrect` のために覚え、作業時間の記録を始める
+fn remember_session(runner: &dyn CommandRunner, spec: &SessionSpec) {
+    if runner.dry_run() {
+        return;
+    }
+    let mut state = State::load();
+    state.record_session(spec);
+    if let Some(activity) = timelog::activity(runner) {
+        state.update_spans(&activity, cache::now());
+    }
+    state.record_session_start(&Location::Local(spec.dir.clone()), &spec.name);
+    state.save();
+}
+
+/// 覚えているセッションのうち無くなっているものをバックグラウンドで作り直す。
+/// `names` が空なら全部
+fn resurrect(runner: &dyn CommandRunner, names: &[String]) -> Result<()> {
+    let state = State::load();
+    if let Some(name) = names
+        .iter()
+        .find(|name| !state.sessions.iter().any(|spec| spec.name == **name))
+    {
+        return Err(Error::NoMatch(name.clone()));
+    }
+    if state.sessions.is_empty() {
+        println!("{}", Msg::NoSavedSessions);
+        return Ok(());
+    }
+    let quiet = runner.dry_run();
+    let specs: Vec<&SessionSpec> = state
+        .sessions
+        .iter()
+        .filter(|spec| names.is_empty() || names.contains(&spec.name))
+        .collect();
+
+    // tmux-resurrect / continuum が復元するセッションを二重に作らない
+    let saved = tmux_resurrect::last_save(runner)
+        .map(|save| (tmux_resurrect::saved_sessions(&save), save))
+        .filter(|(sessions, _)| specs.iter().any(|spec| sessions.contains(&spec.name)));
+    if let Some((_, save)) = &saved {
+        if let Some(script) = tmux_resurrect::restore_script() {
+            if confirm(&Msg::TmuxResurrectPrompt(&paths::shorten_path(save)))? {
+                if !quiet {
+                    println!("{}", Msg::RestoringTmuxResurrect);
+                }
+                tmux_resurrect::restore(runner, &script)?;
+            }
+        }
+    }
 
-    if !status.success() {
-        return Err("tmux split-window に失敗しました".to_string());
+    for spec in specs {
+        if session::exists(runner, &spec.name) {
+            if !quiet {
+                println!("{}", Msg::AlreadyRunning(&spec.name));
+            }
+            continue;
+        }
+        if saved
+            .as_ref()
+            .is_some_and(|(sessions, _)| sessions.contains(&spec.name))
+        {
+            println!("{}", Msg::LeftToTmuxResurrect(&spec.name));
+            continue;
+        }
+        if !spec.dir.is_dir() {
+            eprintln!(
+                "{}",
+                Msg::ResurrectMissing {
+                    name: &spec.name,
+                    dir: &paths::shorten_path(&spec.dir),
+                }
+            );
+            continue;
+        }
+        session::create(runner, spec)?;
+        if !quiet {
+            println!("{}", Msg::Resurrected(&spec.name));
+        }
     }
+    Ok(())
+}
+
+fn start_devcontainer(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    location: &Location,
+) -> Result<()> {
+    let Some(path) = location.local_path() else {
+        return Err(Error::RemoteUnsupported("devcontainer"));
+    };
+
+    let container = devcontainer::up(runner, path)?;
 
-    // セッションにアタッチ
-    let status = Command::new("tmux")
-        .args(["attach-session", "-t", &session_name])
-        .status()
-        .map_err(|e| format!("tmux attach failed: {}", e))?;
+    let mut spec = SessionSpec::new(location);
+    spec.name = Action::Devcontainer.session_name(location);
+    spec.set_command(container.shell_command());
+    start_session(runner, config, None, &spec)
+}
 
-    if !status.success() {
-        return Err("tmux attach-session に失敗しました".to_string());
+/// 設定（`.projector/config.toml` があればそちら）のペイン構成とオプションでセッションを組み立てる。
+/// コマンドを指定していないペインは、ローカルのプロジェクトに Nix の開発環境があればその中で起動する
+fn session_spec(location: &Location, config: &Config, vars: &Vars) -> SessionSpec {
+    let mut spec = SessionSpec::new(location);
+    let layout = config.layout_for(location);
+    if !layout.panes.is_empty() {
+        spec.panes = layout
+            .panes
+            .iter()
+            .map(|pane| (!pane.trim().is_empty()).then(|| vars.render(pane)))
+            .collect();
+    }
+    spec.options = layout
+        .options
+        .iter()
+        .map(|(option, value)| (option.clone(), value.to_tmux()))
+        .collect();
+    spec.synchronize = layout.synchronize;
+    let shell = location
+        .local_path()
+        .and_then(|path| nix::pane_command(path, &config.nix))
+        .map(|command| vars.render(&command));
+    for pane in spec.panes.iter_mut().filter(|pane| pane.is_none()) {
+        pane.clone_from(&shell);
     }
+    spec
+}
+
+/// 設定した open コマンドを展開したもの。ローカルのプロジェクトにだけ使う
+fn open_command(location: &Location, config: &Config, vars: &Vars) -> Option<String> {
+    location.local_path()?;
+    let template = config.open.command.as_ref()?;
+    Some(vars.render(template))
+}
 
+fn run_open_command(runner: &dyn CommandRunner, location: &Location, command: &str) -> Result<()> {
+    let mut cmd = Cmd::new("sh").args(["-c", command]);
+    if let Some(path) = location.local_path() {
+        cmd = cmd.current_dir(path);
+    }
+    let success = runner
+        .interactive(&cmd)
+        .map_err(|e| Error::spawn("sh", e))?;
+    if !success {
+        return Err(Error::CommandFailed(command.to_string()));
+    }
     Ok(())
 }
 
-fn shorten_path(path: &PathBuf) -> String {
-    if let Some(home) = dirs::home_dir() {
-        if let Ok(relative) = path.strip_prefix(&home) {
-            return format!("~/{}", relative.display());
-        }
+/// プロジェクトのセッションの新しいウィンドウでタスクを実行する。
+/// tmux を使わない設定では、この端末で実行して終了を待つ
+fn run_task(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    location: &Location,
+    vars: &Vars,
+    task: &Task,
+) -> Result<()> {
+    if location.local_path().is_none() {
+        return Err(Error::RemoteUnsupported("tasks"));
     }
-    path.display().to_string()
+    if config.backend.resolve() != Backend::Tmux {
+        return run_open_command(runner, location, &task.command);
+    }
+    let spec = session_spec(location, config, vars);
+    remember_session(runner, &spec);
+    session::run_in_window(runner, &spec, &task.name, &task.command)
 }
 
-fn render(
-    stdout: &mut io::Stdout,
-    current_path: &PathBuf,
-    items: &[String],
-    selected: usize,
-) -> io::Result<()> {
-    execute!(stdout, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+/// ウィンドウマネージャーのワークスペース名。ペイン構成に workspace があればそれを使う
+fn workspace_name(config: &Config, location: &Location) -> String {
+    config
+        .layout_for(location)
+        .workspace
+        .unwrap_or_else(|| location.name())
+}
 
-    // ヘッダー
-    execute!(
-        stdout,
-        SetForegroundColor(Color::Cyan),
-        Print(format!(" {}\r\n", shorten_path(current_path))),
-        ResetColor,
-        Print(" ─────────────────────────────────────\r\n"),
-        SetForegroundColor(Color::DarkGrey),
-        Print(" [↑↓] 移動  [Space] 入る  [Enter] TMUX  [←/BS] 戻る  [q] 終了\r\n"),
-        ResetColor,
-        Print("\r\n")
-    )?;
+/// プロジェクトのワークスペースに、このプロジェクトを `projector open` する端末を起動する
+fn open_on_workspace(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    location: &Location,
+) -> Result<()> {
+    let wm = WindowManager::detect().ok_or(Error::NoWindowManager)?;
+    let command = format!("{} {}", config.wm.terminal(), reopen_command(location)?);
+    wm.open_terminal(runner, &workspace_name(config, location), &command)
+}
 
-    if items.is_empty() {
-        execute!(
-            stdout,
-            SetForegroundColor(Color::DarkGrey),
-            Print("   (サブディレクトリなし)\r\n"),
-            ResetColor
-        )?;
+/// 別の端末でこのプロジェクトを開き直すコマンド（`projector open <場所>`）
+fn reopen_command(location: &Location) -> Result<String> {
+    let exe = std::env::current_exe()?;
+    Ok(format!(
+        "{} open {}",
+        shell::quote(&exe.to_string_lossy()),
+        shell::quote(&location.spec())
+    ))
+}
+
+/// ファイルマネージャーでプロジェクトを開く。tmux の中なら今のウィンドウを分割して開き、
+/// それ以外はこの端末で実行して終了を待つ
+fn open_file_manager(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    location: &Location,
+    vars: &Vars,
+) -> Result<()> {
+    let Some(path) = location.local_path() else {
+        return Err(Error::RemoteUnsupported("file manager"));
+    };
+    let command = vars.render(&config.tools.file_manager());
+    if session::inside_tmux() {
+        session::split(runner, path, &command)
     } else {
-        for (i, item) in items.iter().enumerate() {
-            if i == selected {
-                execute!(
-                    stdout,
-                    SetForegroundColor(Color::Green),
-                    Print(format!(" ❯ {}/\r\n", item)),
-                    ResetColor
-                )?;
-            } else {
-                execute!(stdout, Print(format!("   {}/\r\n", item)))?;
-            }
-        }
+        run_open_command(runner, location, &command)
     }
+}
+
+fn start_compose(
+    runner: &dyn CommandRunner,
+    location: &Location,
+    config: &Config,
+    vars: &Vars,
+) -> Result<()> {
+    let Some(path) = location.local_path() else {
+        return Err(Error::RemoteUnsupported("docker compose"));
+    };
+
+    compose::up(runner, path)?;
 
-    stdout.flush()?;
+    let spec = session_spec(location, config, vars);
+    start_session(runner, config, None, &spec)?;
+
+    // アタッチが終わった時点でセッションが無ければ kill されたとみなす。
+    // tmux 以外はセッションの終了を確かめられない
+    if config.backend.resolve() == Backend::Tmux
+        && config.compose.down_on_exit
+        && !session::exists(runner, &spec.name)
+    {
+        println!("{}", Msg::ComposeDown);
+        compose::down(runner, path)?;
+    }
     Ok(())
 }
 
-fn run() -> io::Result<()> {
-    let developer_path = match get_developer_path() {
-        Some(path) => path,
-        None => {
-            eprintln!("ホームディレクトリを取得できませんでした");
-            exit(1);
-        }
+/// ログファイルへの書き出しを始める。ファイルを開けなければ何も記録しない
+fn init_logging(verbose: u8) {
+    let Some(path) = paths::log_file() else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let Ok(file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
     };
 
-    if !developer_path.exists() {
-        eprintln!("~/Developer ディレクトリが存在しません");
-        exit(1);
+    let level = match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    // 依存クレートのログは混ぜない
+    let targets = Targets::new()
+        .with_target("projector", level)
+        .with_target("projector_core", level);
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false),
+        )
+        .with(targets)
+        .init();
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse(std::env::args().skip(1))?;
+    if cli.help {
+        println!("{}", Msg::Usage);
+        return Ok(());
+    }
+    init_logging(cli.verbose);
+    info!(version = env!("CARGO_PKG_VERSION"), "starting");
+    if cli.profile {
+        profile::enable();
     }
+    ui::set_styled(cli.color.enabled());
 
-    let mut current_path = developer_path.clone();
-    let mut path_stack: Vec<PathBuf> = vec![];
-    let mut items = get_directories(&current_path);
-    let mut selected: usize = 0;
+    if dirs::home_dir().is_none() {
+        return Err(Error::NoHome);
+    }
+
+    // 設定ファイルが壊れていても調べられるよう、読み込む前に分ける
+    if let Command::Doctor = cli.command {
+        if let Ok(config) = config::load() {
+            i18n::set_lang(Lang::detect(config.language.as_deref()));
+        }
+        if !doctor::run(&SystemRunner) {
+            exit(ExitCode::Failure as i32);
+        }
+        return Ok(());
+    }
 
-    let mut stdout = io::stdout();
-    terminal::enable_raw_mode()?;
-    execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
+    let mut config = profile::time("config", config::load)?;
+    ui::set_ascii(cli.ascii || config.ascii);
+    i18n::set_lang(Lang::detect(config.language.as_deref()));
 
-    let result = (|| -> io::Result<Option<PathBuf>> {
-        loop {
-            render(&mut stdout, &current_path, &items, selected)?;
+    // 記録を読むだけなので、ルートが無くても表示する
+    if let Command::Stats { weeks } = cli.command {
+        stats::run(&SystemRunner, weeks, cli.format);
+        return Ok(());
+    }
+    // 別のマシンから持ってくるときは、まだルートが無いこともある
+    match &cli.command {
+        Command::StateExport { file } => return export_state(&config, file.as_deref()),
+        Command::StateImport { file } => return import_state(file, cli.dry_run),
+        _ => {}
+    }
 
-            if let Event::Key(key_event) = event::read()? {
-                if key_event.kind != KeyEventKind::Press {
-                    continue;
+    // --stdin ではルートを使わない
+    let candidates = cli
+        .stdin
+        .then(|| index::read_candidates(io::stdin().lock()));
+    let mut roots = match candidates {
+        Some(_) => vec![],
+        None => match existing_roots(&config) {
+            // 初めて使うときはエラーにせず設定ファイルを作る
+            Err(e @ Error::NoRoots(_)) if wizard::should_run() => match wizard::run()? {
+                Some(new_config) => {
+                    config = new_config;
+                    existing_roots(&config)?
                 }
+                None => return Err(e),
+            },
+            roots => roots?,
+        },
+    };
 
-                match key_event.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        return Ok(None);
-                    }
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if !items.is_empty() && selected > 0 {
-                            selected -= 1;
-                        }
-                    }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if !items.is_empty() && selected < items.len() - 1 {
-                            selected += 1;
-                        }
-                    }
-                    KeyCode::Char(' ') | KeyCode::Right => {
-                        // スペースまたは→: ディレクトリに入る
-                        if !items.is_empty() {
-                            let new_path = current_path.join(&items[selected]);
-                            let new_items = get_directories(&new_path);
-                            if !new_items.is_empty() {
-                                path_stack.push(current_path.clone());
-                                current_path = new_path;
-                                items = new_items;
-                                selected = 0;
-                            }
-                        }
-                    }
-                    KeyCode::Backspace | KeyCode::Left => {
-                        // Backspaceまたは←: 親ディレクトリに戻る
-                        if let Some(prev_path) = path_stack.pop() {
-                            current_path = prev_path;
-                            items = get_directories(&current_path);
-                            selected = 0;
-                        }
-                    }
-                    KeyCode::Enter => {
-                        // Enter: TMUXを起動
-                        if !items.is_empty() {
-                            let project_path = current_path.join(&items[selected]);
-                            return Ok(Some(project_path));
-                        }
-                    }
-                    _ => {}
+    let runner: Arc<dyn CommandRunner> = if cli.dry_run {
+        Arc::new(DryRunRunner)
+    } else {
+        Arc::new(SystemRunner)
+    };
+
+    // JSON で出力するときは標準出力を結果用に空け、画面は標準エラー出力に描く
+    let json = cli.format == Format::Json;
+    let tty = if json {
+        io::stderr().is_terminal()
+    } else {
+        io::stdout().is_terminal()
+    };
+    let picker = cli.picker.unwrap_or(config.picker);
+    // rofi などでウィンドウマネージャーのキーから選んだときは、アタッチする端末が無い
+    let attach = if cli.print_attach {
+        Attach::Print
+    } else if cli.detach || cli.all || (!tty && picker.is_graphical()) {
+        Attach::Detach
+    } else {
+        Attach::Here
+    };
+    // パイプや CI ではエスケープシーケンスを出さない
+    if !tty {
+        match cli.command {
+            Command::Browse if !picker.is_graphical() => {
+                let entries =
+                    candidates.unwrap_or_else(|| list_projects(runner.as_ref(), &roots, &config));
+                return output_projects(&entries, cli.format);
+            }
+            // Windows Terminal などは自分でタブやウィンドウを開くので端末が無くてもよい
+            Command::Open { .. } | Command::Last
+                if !cli.dry_run
+                    && !json
+                    && attach == Attach::Here
+                    && !matches!(
+                        config.backend.resolve(),
+                        Backend::WindowsTerminal | Backend::Iterm | Backend::Ghostty
+                    ) =>
+            {
+                return Err(Error::NotATerminal)
+            }
+            Command::Browse
+            | Command::List
+            | Command::Open { .. }
+            | Command::Last
+            | Command::Resurrect { .. }
+            | Command::Kill { .. }
+            | Command::Doctor
+            | Command::ImportHistory { .. }
+            | Command::Export { .. }
+            | Command::StateExport { .. }
+            | Command::StateImport { .. }
+            | Command::Stats { .. }
+            | Command::Daemon
+            | Command::Serve => {}
+        }
+    }
+
+    // 起動に失敗したら TUI に戻って選び直す
+    let mut browser: Option<Browser> = None;
+    let mut target = match cli.command {
+        Command::Browse => match picker {
+            Picker::Tui => {
+                let out: Box<dyn Write> = if json {
+                    Box::new(io::stderr())
+                } else {
+                    Box::new(io::stdout())
+                };
+                let browser =
+                    browser.insert(Browser::new(&roots, candidates, &config, &runner, out));
+                browser.run(&mut roots, &mut config)?
+            }
+            Picker::Fzf => {
+                let entries =
+                    candidates.unwrap_or_else(|| list_projects(runner.as_ref(), &roots, &config));
+                fzf::pick(runner.as_ref(), &entries)?
+                    .map(|i| (Action::Open, entries[i].location.clone()))
+            }
+            Picker::Rofi | Picker::Dmenu => {
+                let entries =
+                    candidates.unwrap_or_else(|| list_projects(runner.as_ref(), &roots, &config));
+                let command = match picker {
+                    Picker::Rofi => menu::ROFI,
+                    _ => &config.tools.dmenu,
+                };
+                menu::pick(runner.as_ref(), command, &entries)?
+                    .map(|i| (Action::Open, entries[i].location.clone()))
+            }
+        },
+        Command::List => {
+            let entries =
+                candidates.unwrap_or_else(|| list_projects(runner.as_ref(), &roots, &config));
+            return output_projects(&entries, cli.format);
+        }
+        Command::Open { name } => {
+            let matches = find_projects(runner.as_ref(), &roots, &config, &name);
+            if cli.all {
+                if matches.is_empty() {
+                    return Err(Error::NoMatch(name));
+                }
+                for location in matches {
+                    launch(
+                        runner.as_ref(),
+                        &config,
+                        &roots,
+                        cli.dry_run,
+                        attach,
+                        Action::Open,
+                        location,
+                    )?;
+                }
+                return Ok(());
+            }
+            choose_match(&name, matches, cli.first)?.map(|location| (Action::Open, location))
+        }
+        Command::Last => {
+            let location = State::load().previous().ok_or(Error::NoRecentProject)?;
+            Some((Action::Open, location))
+        }
+        // 端末を使わずにセッションを作るだけ
+        Command::Resurrect { names } => return resurrect(runner.as_ref(), &names),
+        Command::Kill { name } => {
+            return kill_sessions(runner.as_ref(), &roots, &config, name.as_deref())
+        }
+        // 設定を読み込む前に済ませている
+        Command::Doctor => return Ok(()),
+        Command::ImportHistory { files } => {
+            return import_history(&roots, &config, &files, cli.dry_run)
+        }
+        Command::Export { format } => return export::run(runner.as_ref(), &roots, &config, format),
+        // ルートを調べる前に済ませている
+        Command::StateExport { .. } | Command::StateImport { .. } | Command::Stats { .. } => {
+            return Ok(())
+        }
+        Command::Daemon => {
+            let depth = config.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+            return daemon::serve(&roots, config.scan_options(), depth, |socket| {
+                println!("{}", Msg::DaemonListening(&paths::shorten_path(socket)));
+            });
+        }
+        // Neovim の jobstart の rpc は標準入出力を使うので --stdio は省略できる
+        Command::Serve if cli.msgpack => return serve::run_msgpack(&config, &roots),
+        Command::Serve if cli.stdio => return serve::run(&config, &roots),
+        Command::Serve => return Err(Error::MissingArgument("--stdio")),
+    };
+
+    // 起動した tmux などが終わるまで待たずに、選ぶまでの計測結果を出す
+    print_profile();
+
+    loop {
+        match target {
+            Some((action, location)) if json => {
+                println!("{}", selection_json(&action, &location));
+                break;
+            }
+            Some((action, location)) => {
+                let Err(e) = launch(
+                    runner.as_ref(),
+                    &config,
+                    &roots,
+                    cli.dry_run,
+                    attach,
+                    action,
+                    location,
+                ) else {
+                    break;
+                };
+                // TUI から選んだなら、一覧と選択をそのままにして選び直せるようにする
+                let Some(browser) = browser.as_mut() else {
+                    return Err(e);
+                };
+                warn!(error = %e, "launch failed, back to the picker");
+                browser.app.show_error(&e);
+                target = browser.run(&mut roots, &mut config)?;
+            }
+            None => {
+                if json {
+                    eprintln!("{}", Msg::Cancelled);
+                } else {
+                    println!("{}", Msg::Cancelled);
+                }
+                exit(ExitCode::Cancelled as i32);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 選んだ操作でプロジェクトを開く
+fn launch(
+    runner: &dyn CommandRunner,
+    config: &Config,
+    roots: &[Location],
+    dry_run: bool,
+    attach: Attach,
+    action: Action,
+    location: Location,
+) -> Result<()> {
+    info!(location = %location.display(), "launching");
+    if !dry_run {
+        let mut state = State::load();
+        state.record_open(&location);
+        state.save();
+    }
+    let vars = Vars::project(runner, &location, roots, &action.session_name(&location));
+    let command = match action {
+        Action::Open => open_command(&location, config, &vars),
+        // open の設定は Enter で開くときだけ使う
+        _ => None,
+    };
+    // dry-run と --print-attach の出力はコマンドだけにする
+    if !dry_run && attach != Attach::Print {
+        match &command {
+            Some(command) => println!(
+                "{}",
+                Msg::OpeningWith {
+                    name: &location.display(),
+                    command,
                 }
+            ),
+            None => println!("{}", action.message(&location)),
+        }
+    }
+    // ファイルマネージャーは今の画面を分割するだけなので、ワークスペースはそのまま。
+    // 別の端末で開くときは、その端末の projector が用意する
+    if let (Some(mode), Some(wm), false) = (
+        config.wm.workspace,
+        WindowManager::detect(),
+        attach != Attach::Here
+            || matches!(
+                action,
+                Action::Files | Action::Workspace | Action::NewWindow | Action::Window
+            ),
+    ) {
+        let workspace = workspace_name(config, &location);
+        if let Err(e) = wm.prepare_workspace(runner, mode, &workspace) {
+            warn!(error = %e, "could not prepare the workspace");
+        }
+    }
+    match (action, command) {
+        (Action::Open, Some(command)) => run_open_command(runner, &location, &command)?,
+        (Action::Open, None) if attach != Attach::Here => {
+            let spec = session_spec(&location, config, &vars);
+            create_detached(runner, config, &location, &spec, attach)?
+        }
+        (Action::Open, None) => {
+            let spec = session_spec(&location, config, &vars);
+            start_session(runner, config, location.host(), &spec)?
+        }
+        (Action::Git, _) => {
+            let mut spec = session_spec(&location, config, &vars);
+            spec.name = Action::Git.session_name(&location);
+            spec.set_pane(1, vars.render(&config.tools.git));
+            start_session(runner, config, location.host(), &spec)?
+        }
+        (Action::Task(task), _) => run_task(runner, config, &location, &vars, &task)?,
+        (Action::Files, _) => open_file_manager(runner, config, &location, &vars)?,
+        (Action::Window, _) => {
+            let Some(path) = location.local_path() else {
+                return Err(Error::RemoteUnsupported("tmux windows"));
+            };
+            session::new_window(runner, &session::session_name(&location.name()), path)?
+        }
+        (Action::Devcontainer, _) => start_devcontainer(runner, config, &location)?,
+        (Action::Compose, _) => start_compose(runner, &location, config, &vars)?,
+        (Action::Workspace, _) => open_on_workspace(runner, config, &location)?,
+        (Action::NewWindow, _) => {
+            macos::open_window(runner, config.macos.terminal, &reopen_command(&location)?)?
+        }
+    }
+    Ok(())
+}
+
+/// ルート直下のプロジェクト。デーモンが動いていればその一覧を使う
+fn list_projects(runner: &dyn CommandRunner, roots: &[Location], config: &Config) -> Vec<Entry> {
+    let options = config.scan_options();
+    daemon::projects(roots, &options)
+        .unwrap_or_else(|| profile::time("scan", || index::projects(runner, roots, &options)))
+}
+
+/// `--profile` の計測結果を標準エラー出力に表示する
+fn print_profile() {
+    for timing in profile::take() {
+        eprintln!("{}", Msg::Profile(&timing));
+    }
+}
+
+/// 設定されたルートのうち存在するもの（リモートは接続してみるまで分からないので残す）
+fn existing_roots(config: &Config) -> Result<Vec<Location>> {
+    let (roots, missing): (Vec<Location>, Vec<Location>) = config
+        .roots()
+        .iter()
+        .map(|root| Location::parse(root))
+        .partition(|root| root.local_path().is_none_or(Path::exists));
+    if roots.is_empty() {
+        return Err(Error::NoRoots(
+            missing.iter().map(Location::display).collect(),
+        ));
+    }
+    Ok(roots)
+}
+
+/// 一覧を出力する。`| head` などで途中で閉じられても失敗扱いにしない
+fn output_projects(entries: &[Entry], format: Format) -> Result<()> {
+    match print_projects(entries, format) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// プロジェクトを1行ずつ出力する。ランチャー向けの形式は全体で1つの JSON
+fn print_projects(entries: &[Entry], format: Format) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    if matches!(format, Format::Raycast | Format::Alfred) {
+        let items: Vec<_> = entries
+            .iter()
+            .map(|entry| launcher_item(entry, format))
+            .collect();
+        return writeln!(out, "{}", json!({ "items": items }));
+    }
+    for entry in entries {
+        match &entry.location {
+            location if format == Format::Json => writeln!(out, "{}", location_json(location))?,
+            // UTF-8 でないパスもそのまま渡せるよう生のバイト列で書く
+            Location::Local(path) => {
+                out.write_all(path.as_os_str().as_encoded_bytes())?;
+                writeln!(out)?
             }
+            Location::Remote { host, path } => writeln!(out, "{}:{}", host, path)?,
+        }
+    }
+    Ok(())
+}
+
+/// Raycast / Alfred に渡す項目。`arg` は `projector open --detach` にそのまま渡せるパス
+fn launcher_item(entry: &Entry, format: Format) -> serde_json::Value {
+    let spec = entry.location.spec();
+    let path = entry.location.local_path();
+    if format == Format::Raycast {
+        let mut item = json!({
+            "id": spec,
+            "title": entry.name,
+            "subtitle": entry.location.display(),
+            "arg": spec,
+        });
+        match (path, entry.location.host()) {
+            (Some(path), _) => item["icon"] = json!({ "fileIcon": path }),
+            (None, Some(host)) => item["accessories"] = json!([{ "text": host }]),
+            (None, None) => {}
         }
-    })();
+        return item;
+    }
+    let mut item = json!({
+        "uid": spec,
+        "title": entry.name,
+        "subtitle": entry.location.display(),
+        "arg": spec,
+        "autocomplete": entry.name,
+    });
+    // ローカルならファイルとして扱わせ、Finder のアイコンや操作を使えるようにする
+    if let Some(path) = path {
+        item["type"] = json!("file");
+        item["icon"] = json!({ "type": "fileicon", "path": path });
+    }
+    item
+}
 
-    // クリーンアップ
-    execute!(stdout, cursor::Show, LeaveAlternateScreen)?;
-    terminal::disable_raw_mode()?;
+fn location_json(location: &Location) -> serde_json::Value {
+    json!({
+        "name": location.name(),
+        "path": location.path_str(),
+        "host": location.host(),
+    })
+}
 
-    match result {
-        Ok(Some(project_path)) => {
-            let session_name = project_path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("default");
+/// `--format json` で出力する選択結果
+fn selection_json(action: &Action, location: &Location) -> serde_json::Value {
+    let mut value = location_json(location);
+    value["session"] = json!(action.session_name(location));
+    value["action"] = json!(action.as_str());
+    value
+}
 
-            println!("選択: {} -> TMUXを起動します...", shorten_path(&project_path));
+/// TUI のループが最後に描いたときの、まだ保存していない表示の設定と位置
+type UnsavedView = Arc<Mutex<Option<(ViewPrefs, BrowsePosition)>>>;
 
-            if let Err(e) = start_tmux_session(session_name, &project_path) {
-                eprintln!("エラー: {}", e);
-                exit(1);
+/// SIGHUP を受けたら `hangup` を立てる。端末を閉じたときの SIGHUP なら、表示の状態を保存して終了する。
+/// 閉じた端末では crossterm が読み込みのループから戻らず TUI のループに制御が戻らないので、ここで判断する
+#[cfg(unix)]
+fn watch_hangup(hangup: Arc<AtomicBool>, unsaved: UnsavedView) {
+    use signal_hook::consts::SIGHUP;
+    let mut signals = match signal_hook::iterator::Signals::new([SIGHUP]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            warn!(error = %e, "failed to register SIGHUP handler");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            if fs::File::open("/dev/tty").is_err() {
+                if let Some(view) = unsaved.lock().unwrap_or_else(|e| e.into_inner()).take() {
+                    save_view(view);
+                }
+                info!("terminal closed, exiting");
+                exit(128 + SIGHUP);
             }
+            hangup.store(true, Ordering::Relaxed);
         }
-        Ok(None) => {
-            println!("キャンセルされました");
+    });
+}
+
+/// 次の起動に引き継ぐ表示の設定と位置。保存済みのものから変わっていなければ None
+fn unsaved_view(
+    app: &App,
+    state: &State,
+    resume: bool,
+    stdin: bool,
+) -> Option<(ViewPrefs, BrowsePosition)> {
+    if stdin {
+        return None;
+    }
+    let prefs = app.prefs();
+    let position = if resume {
+        app.position()
+    } else {
+        state.browse.clone()
+    };
+    (prefs != state.view || position != state.browse).then_some((prefs, position))
+}
+
+/// 表示の設定と位置を保存する。開いたプロジェクトの記録は起動のたびに書き込まれるので、読み込み直したものに重ねる
+fn save_view((view, browse): (ViewPrefs, BrowsePosition)) {
+    let mut saved = State::load();
+    saved.view = view;
+    saved.browse = browse;
+    saved.save();
+}
+
+/// 設定ファイルを読み込み直して TUI に反映する。読めなければ今の設定のまま
+fn reload_config(app: &mut App, roots: &mut Vec<Location>, config: &mut Config, stdin: bool) {
+    let reloaded = config::load().and_then(|new_config| {
+        let new_roots = if stdin {
+            vec![]
+        } else {
+            existing_roots(&new_config)?
+        };
+        Ok((new_config, new_roots))
+    });
+    match reloaded {
+        Ok((new_config, new_roots)) => {
+            info!("config reloaded");
+            i18n::set_lang(Lang::detect(new_config.language.as_deref()));
+            app.apply_config(new_roots.clone(), &new_config);
+            *config = new_config;
+            *roots = new_roots;
+            app.notify(Msg::ConfigReloaded);
         }
         Err(e) => {
-            eprintln!("エラー: {}", e);
-            exit(1);
+            warn!(error = %e, "config reload failed");
+            app.show_error(&e);
         }
     }
+}
 
-    Ok(())
+/// TUI の状態。起動に失敗したら同じ一覧と選択のまま選び直せるよう、起動の間も持っておく
+struct Browser {
+    app: App,
+    out: Box<dyn Write>,
+    /// 保存済みの表示の設定と位置
+    state: State,
+    resume: bool,
+    stdin: bool,
+    /// SIGHUP で立つ。設定を読み込み直す
+    hangup: Arc<AtomicBool>,
+    /// 端末が閉じられたときに保存する表示の状態
+    unsaved: UnsavedView,
+}
+
+impl Browser {
+    fn new(
+        roots: &[Location],
+        candidates: Option<Vec<Entry>>,
+        config: &Config,
+        runner: &Arc<dyn CommandRunner>,
+        out: Box<dyn Write>,
+    ) -> Self {
+        let stdin = candidates.is_some();
+        let state = State::load();
+        let mut app = App::new(
+            roots.to_vec(),
+            candidates,
+            config,
+            state.view.clone(),
+            paths::cache_dir(),
+            Arc::clone(runner),
+        );
+        let resume = config.resume && !stdin;
+        if resume {
+            app.resume(&state.browse);
+        }
+        app.set_frecency(state.frecency());
+        if state.view.flat {
+            app.start_find();
+        }
+
+        // SIGHUP でも設定を読み込み直す
+        let hangup = Arc::new(AtomicBool::new(false));
+        let unsaved = UnsavedView::default();
+        #[cfg(unix)]
+        watch_hangup(Arc::clone(&hangup), Arc::clone(&unsaved));
+
+        Browser {
+            app,
+            out,
+            state,
+            resume,
+            stdin,
+            hangup,
+            unsaved,
+        }
+    }
+
+    /// TUI でプロジェクトと操作を選ぶ。キャンセルされたら None
+    ///
+    /// `R` か SIGHUP で設定を読み込み直すと、`roots` と `config` も新しいものに置き換わる。
+    fn run(
+        &mut self,
+        roots: &mut Vec<Location>,
+        config: &mut Config,
+    ) -> Result<Option<(Action, Location)>> {
+        let Browser {
+            app,
+            out,
+            state,
+            resume,
+            stdin,
+            hangup,
+            unsaved,
+        } = self;
+        let (resume, stdin) = (*resume, *stdin);
+
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            EnterAlternateScreen,
+            cursor::Hide,
+            EnableBracketedPaste
+        )?;
+
+        let result = (|| -> io::Result<Option<(Action, Location)>> {
+            let mut dirty = true;
+            loop {
+                if dirty {
+                    app.request_details();
+                    app.request_ci_status();
+                    app.request_stash_counts();
+                    app.request_upstream();
+                    ui::render(out, app)?;
+                    profile::mark("first render");
+                    *unsaved.lock().unwrap_or_else(|e| e.into_inner()) =
+                        unsaved_view(app, state, resume, stdin);
+                    dirty = false;
+                }
+
+                if hangup.swap(false, Ordering::Relaxed) {
+                    reload_config(app, roots, config, stdin);
+                    dirty = true;
+                }
+
+                if !event::poll(POLL_INTERVAL)? {
+                    // 全ルートを辿っている間はスピナーを回す
+                    dirty |= app.poll_updates() || app.scan_progress().is_some();
+                    continue;
+                }
+
+                let event = event::read()?;
+                if let Event::Paste(text) = &event {
+                    app.paste(text);
+                    dirty = true;
+                    continue;
+                }
+                if let Event::Key(key_event) = event {
+                    if key_event.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    dirty = true;
+
+                    match keys::handle(app, &key_event) {
+                        Outcome::Continue => {}
+                        Outcome::Quit => return Ok(None),
+                        Outcome::Reload => reload_config(app, roots, config, stdin),
+                        Outcome::Launch(action, location) => return Ok(Some((action, location))),
+                    }
+                } else {
+                    // リサイズなど
+                    dirty = true;
+                }
+            }
+        })();
+
+        // クリーンアップ
+        execute!(
+            out,
+            DisableBracketedPaste,
+            cursor::Show,
+            LeaveAlternateScreen
+        )?;
+        terminal::disable_raw_mode()?;
+
+        // 次の起動も同じ表示から始める
+        unsaved.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some((view, browse)) = unsaved_view(app, state, resume, stdin) {
+            state.view = view.clone();
+            state.browse = browse.clone();
+            save_view((view, browse));
+        }
+
+        Ok(result?)
+    }
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("エラー: {}", e);
-        exit(1);
+    let result = run();
+    print_profile();
+    if let Err(e) = result {
+        error!(error = %e, "exiting");
+        eprintln!("{}", Msg::Error(&e));
+        exit(e.exit_code() as i32);
     }
 }
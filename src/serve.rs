@@ -0,0 +1,614 @@
+use std::io::{self, BufRead, Read, Write};
+use std::sync::Mutex;
+use std::thread;
+
+use rmpv::Value as MsgValue;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, info};
+
+use projector_core::config::{Backend, Config};
+use projector_core::location::{Entry, Location};
+use projector_core::matcher::FuzzyMatcher;
+use projector_core::runner::{CommandRunner, SystemRunner};
+use projector_core::session;
+use projector_core::state::State;
+use projector_core::template::Vars;
+use projector_core::{daemon, git, index, nix, tasks, Error, Result};
+
+use crate::app::Action;
+
+// JSON-RPC 2.0 のエラーコード
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// `query` で返す候補の既定の数
+const DEFAULT_LIMIT: usize = 50;
+
+/// メッセージの区切り方。問い合わせと同じ形で返す
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Framing {
+    /// 1行に1つの JSON
+    Line,
+    /// LSP と同じ `Content-Length` ヘッダー付き
+    Header,
+}
+
+/// 失敗した呼び出しの JSON-RPC のエラー
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        RpcError::new(SERVER_ERROR, e.to_string())
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProjectsParams {
+    /// ルート直下だけでなく、深い階層のリポジトリも含める
+    all: bool,
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    query: String,
+    #[serde(default)]
+    all: bool,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct OpenParams {
+    path: String,
+    #[serde(default)]
+    action: Option<String>,
+    /// tmux の中から呼ばれたら、作ったセッションにクライアントを切り替える
+    #[serde(default)]
+    switch: bool,
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: String,
+}
+
+/// 標準入出力で JSON-RPC 2.0 のリクエストに答える。入力が終わったら戻る
+///
+/// 標準出力は応答に使うので、コマンドは dry-run にせず実行し、tmux にはアタッチしない。
+pub fn run(config: &Config, roots: &[Location]) -> Result<()> {
+    info!("serving JSON-RPC on stdio");
+    Server::new(config, roots).serve(io::stdin().lock(), io::stdout().lock())
+}
+
+/// 標準入出力で Neovim 向けの MessagePack-RPC に答える。入力が終わったら戻る
+///
+/// メソッドは JSON-RPC と同じで、引数の先頭のテーブルを params として扱う。
+/// `open` を通知（`vim.rpcnotify`）で送ると、セッションを作り終えたところで
+/// `User ProjectorOpened` の自動コマンドを `data` に結果を入れて実行させる。
+pub fn run_msgpack(config: &Config, roots: &[Location]) -> Result<()> {
+    info!("serving MessagePack-RPC on stdio");
+    Server::new(config, roots).serve_msgpack(io::stdin().lock(), io::stdout())
+}
+
+struct Server<'a> {
+    config: &'a Config,
+    roots: &'a [Location],
+    runner: &'a dyn CommandRunner,
+    matcher: Mutex<FuzzyMatcher>,
+}
+
+impl<'a> Server<'a> {
+    fn new(config: &'a Config, roots: &'a [Location]) -> Self {
+        let mut matcher = FuzzyMatcher::new();
+        matcher.set_case(config.filter.case);
+        Server {
+            config,
+            roots,
+            runner: &SystemRunner,
+            matcher: Mutex::new(matcher),
+        }
+    }
+
+    fn serve(&self, mut input: impl BufRead, mut output: impl Write) -> Result<()> {
+        while let Some((framing, message)) = read_message(&mut input)? {
+            let response = match serde_json::from_str::<Value>(&message) {
+                Ok(request) => self.handle(request),
+                Err(e) => Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                )),
+            };
+            if let Some(response) = response {
+                write_message(&mut output, framing, &response)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn serve_msgpack(&self, mut input: impl Read, output: impl Write + Send) -> Result<()> {
+        let output = Mutex::new(output);
+        // 非同期の open は別スレッドで処理し、終わったら Neovim に通知する
+        thread::scope(|scope| loop {
+            let message = match rmpv::decode::read_value(&mut input) {
+                Ok(message) => message,
+                Err(rmpv::decode::Error::InvalidMarkerRead(e))
+                    if e.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(());
+                }
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e).into()),
+            };
+            let fields = message.as_array().map(Vec::as_slice).unwrap_or_default();
+            match fields {
+                // リクエスト: [0, msgid, method, params]
+                [kind, id, method, params] if kind.as_u64() == Some(0) => {
+                    let method = method.as_str().unwrap_or_default();
+                    debug!(method, "msgpack-rpc request");
+                    let (error, result) = match self.call(method, msgpack_params(params)) {
+                        Ok(result) => (MsgValue::Nil, to_msgpack(&result)),
+                        // Neovim は [種類, メッセージ] の形のエラーを読める
+                        Err(e) => (
+                            MsgValue::Array(vec![0.into(), e.message.into()]),
+                            MsgValue::Nil,
+                        ),
+                    };
+                    let response = MsgValue::Array(vec![1.into(), id.clone(), error, result]);
+                    send(&output, &response)?;
+                }
+                // 通知: [2, method, params]
+                [kind, method, params] if kind.as_u64() == Some(2) => {
+                    if method.as_str() != Some("open") {
+                        continue;
+                    }
+                    let params = msgpack_params(params);
+                    let output = &output;
+                    scope.spawn(move || {
+                        let notification = match self.call("open", params) {
+                            Ok(result) => nvim_call(
+                                "nvim_exec_autocmds",
+                                vec![
+                                    "User".into(),
+                                    to_msgpack(
+                                        &json!({ "pattern": "ProjectorOpened", "data": result }),
+                                    ),
+                                ],
+                            ),
+                            Err(e) => nvim_call(
+                                "nvim_echo",
+                                vec![
+                                    to_msgpack(&json!([[e.message, "ErrorMsg"]])),
+                                    true.into(),
+                                    MsgValue::Map(vec![]),
+                                ],
+                            ),
+                        };
+                        if let Err(e) = send(output, &notification) {
+                            debug!(error = %e, "failed to notify neovim");
+                        }
+                    });
+                }
+                _ => debug!("ignoring a msgpack-rpc message"),
+            }
+        })
+    }
+
+    /// 1つのリクエストに答える。通知（id が無いもの）には何も返さない
+    fn handle(&self, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            let error = RpcError::new(INVALID_REQUEST, "missing method");
+            return Some(error_response(id.unwrap_or(Value::Null), error));
+        };
+        debug!(method, "rpc request");
+        // params を省略したら既定値だけのオブジェクトとして扱う
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+        let result = self.call(method, params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn call(&self, method: &str, params: Value) -> RpcResult {
+        match method {
+            "projects" => {
+                let params: ProjectsParams = parse_params(params)?;
+                let entries = self.entries(params.all);
+                Ok(entries
+                    .iter()
+                    .map(|e| crate::location_json(&e.location))
+                    .collect())
+            }
+            "query" => {
+                let params: QueryParams = parse_params(params)?;
+                let entries = self.entries(params.all);
+                let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+                let mut matcher = self.matcher.lock().unwrap_or_else(|e| e.into_inner());
+                let matches = matcher
+                    .rank(&params.query, &names)
+                    .into_iter()
+                    .take(params.limit.unwrap_or(DEFAULT_LIMIT))
+                    .map(|i| {
+                        let mut value = crate::location_json(&entries[i].location);
+                        value["label"] = json!(entries[i].name);
+                        value["indices"] = json!(matcher.indices(&params.query, names[i]));
+                        value
+                    })
+                    .collect();
+                Ok(Value::Array(matches))
+            }
+            "open" => {
+                let params: OpenParams = parse_params(params)?;
+                let action = match params.action.as_deref() {
+                    None | Some("open") => Action::Open,
+                    Some("git") => Action::Git,
+                    Some(other) => {
+                        let message = format!("unsupported action: {}", other);
+                        return Err(RpcError::new(INVALID_PARAMS, message));
+                    }
+                };
+                self.open(&Location::parse(&params.path), action, params.switch)
+            }
+            "metadata" => {
+                let params: PathParams = parse_params(params)?;
+                Ok(self.metadata(&Location::parse(&params.path)))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method: {}", method),
+            )),
+        }
+    }
+
+    fn entries(&self, all: bool) -> Vec<Entry> {
+        if all {
+            let depth = self.config.max_depth.unwrap_or(index::DEFAULT_DEPTH);
+            let options = self.config.scan_options();
+            daemon::walk(self.roots, &options, depth)
+                .unwrap_or_else(|| index::walk(self.roots, &options, depth))
+        } else {
+            crate::list_projects(self.runner, self.roots, self.config)
+        }
+    }
+
+    /// セッションをバックグラウンドで作る。`switch` のときだけ、tmux の中から呼んだクライアントを切り替える
+    fn open(&self, location: &Location, action: Action, switch: bool) -> RpcResult {
+        if location.local_path().is_none() {
+            return Err(Error::RemoteUnsupported("serve").into());
+        }
+        if self.config.backend.resolve() != Backend::Tmux {
+            return Err(RpcError::new(
+                SERVER_ERROR,
+                "open requires the tmux backend",
+            ));
+        }
+        let name = action.session_name(location);
+        let vars = Vars::project(self.runner, location, self.roots, &name);
+        let mut spec = crate::session_spec(location, self.config, &vars);
+        spec.name = name;
+        if let Action::Git = action {
+            spec.set_pane(1, vars.render(&self.config.tools.git));
+        }
+
+        let created = session::ensure(self.runner, &spec)?;
+        crate::remember_session(self.runner, &spec);
+        let mut state = State::load();
+        state.record_open(location);
+        state.save();
+        let switched = switch && session::inside_tmux();
+        if switched {
+            session::switch_client(self.runner, &spec.name)?;
+        }
+
+        let mut value = crate::location_json(location);
+        value["session"] = json!(spec.name);
+        value["created"] = json!(created);
+        value["switched"] = json!(switched);
+        Ok(value)
+    }
+
+    /// プロジェクトについてすぐに分かること（ブランチ、リモート、Nix、タスクなど）
+    fn metadata(&self, location: &Location) -> Value {
+        let mut value = crate::location_json(location);
+        let session_name = Action::Open.session_name(location);
+        value["session"] = json!(session_name);
+        if let Some(path) = location.local_path() {
+            value["running"] = json!(session::exists(self.runner, &session_name));
+            value["branch"] = json!(git::current_branch(self.runner, path));
+            value["remote"] = json!(git::remote_url(self.runner, path));
+            value["nix"] = json!(nix::detect(path).map(|kind| kind.label()));
+            value["tasks"] = tasks::detect(path)
+                .iter()
+                .map(|task| {
+                    json!({
+                        "name": task.name,
+                        "command": task.command,
+                        "source": task.source.label(),
+                    })
+                })
+                .collect();
+        }
+        value
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// 次のメッセージを読む。入力の終わりなら None
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<(Framing, String)>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Some(length) = trimmed.strip_prefix("Content-Length:") else {
+            return Ok(Some((Framing::Line, trimmed.to_string())));
+        };
+        let length: usize = length
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid Content-Length"))?;
+        // 残りのヘッダーは空行まで読み飛ばす
+        loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        input.read_exact(&mut body)?;
+        return Ok(Some((
+            Framing::Header,
+            String::from_utf8_lossy(&body).to_string(),
+        )));
+    }
+}
+
+fn write_message(output: &mut impl Write, framing: Framing, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    match framing {
+        Framing::Line => writeln!(output, "{}", body)?,
+        Framing::Header => write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?,
+    }
+    output.flush()
+}
+
+/// Neovim の API を呼ぶ通知
+fn nvim_call(function: &str, args: Vec<MsgValue>) -> MsgValue {
+    MsgValue::Array(vec![2.into(), function.into(), MsgValue::Array(args)])
+}
+
+fn send(output: &Mutex<impl Write>, message: &MsgValue) -> io::Result<()> {
+    let mut output = output.lock().unwrap_or_else(|e| e.into_inner());
+    rmpv::encode::write_value(&mut *output, message)?;
+    output.flush()
+}
+
+/// MessagePack-RPC の引数の先頭のテーブルを JSON-RPC の params にする
+fn msgpack_params(params: &MsgValue) -> Value {
+    match params.as_array().and_then(|args| args.first()) {
+        Some(first @ MsgValue::Map(_)) => to_json(first),
+        _ => json!({}),
+    }
+}
+
+fn to_json(value: &MsgValue) -> Value {
+    match value {
+        MsgValue::Nil | MsgValue::Ext(..) => Value::Null,
+        MsgValue::Boolean(b) => json!(b),
+        MsgValue::Integer(i) => match i.as_i64() {
+            Some(i) => json!(i),
+            None => json!(i.as_u64()),
+        },
+        MsgValue::F32(f) => json!(f),
+        MsgValue::F64(f) => json!(f),
+        MsgValue::String(s) => json!(s.as_str().map_or_else(
+            || String::from_utf8_lossy(s.as_bytes()).to_string(),
+            str::to_string
+        )),
+        MsgValue::Binary(bytes) => json!(String::from_utf8_lossy(bytes)),
+        MsgValue::Array(values) => values.iter().map(to_json).collect(),
+        MsgValue::Map(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key.as_str() {
+                        Some(key) => key.to_string(),
+                        None => key.to_string(),
+                    };
+                    (key, to_json(value))
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn to_msgpack(value: &Value) -> MsgValue {
+    match value {
+        Value::Null => MsgValue::Nil,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into(),
+            (None, Some(u)) => u.into(),
+            _ => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(values) => MsgValue::Array(values.iter().map(to_msgpack).collect()),
+        Value::Object(map) => MsgValue::Map(
+            map.iter()
+                .map(|(key, value)| (key.as_str().into(), to_msgpack(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use projector_core::runner::MockRunner;
+    use tempfile::TempDir;
+
+    /// `projects` と `query` で返るプロジェクトを2つ置いたルート
+    fn root() -> TempDir {
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(root.path().join("api/.git")).unwrap();
+        fs::create_dir_all(root.path().join("web/.git")).unwrap();
+        root
+    }
+
+    fn server<'a>(config: &'a Config, roots: &'a [Location], runner: &'a MockRunner) -> Server<'a> {
+        Server {
+            config,
+            roots,
+            runner,
+            matcher: Mutex::new(FuzzyMatcher::new()),
+        }
+    }
+
+    /// 出力を読み直したメッセージ
+    fn messages(output: &[u8]) -> Vec<Value> {
+        let mut input = output;
+        let mut messages = vec![];
+        while let Some((_, message)) = read_message(&mut input).unwrap() {
+            messages.push(serde_json::from_str(&message).unwrap());
+        }
+        messages
+    }
+
+    fn error_code(result: RpcResult) -> i64 {
+        match result {
+            Ok(value) => panic!("expected an error, got {}", value),
+            Err(e) => e.code,
+        }
+    }
+
+    #[test]
+    fn reads_line_and_header_framed_messages() {
+        let mut input =
+            &b"\n{\"id\":1}\r\nContent-Length: 8\r\nContent-Type: x\r\n\r\n{\"id\":2}{\"id\":3}\n"
+                [..];
+        let (framing, message) = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(framing, Framing::Line);
+        assert_eq!(message, r#"{"id":1}"#);
+        let (framing, message) = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(framing, Framing::Header);
+        assert_eq!(message, r#"{"id":2}"#);
+        let (_, message) = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(message, r#"{"id":3}"#);
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_an_invalid_content_length() {
+        let mut input = &b"Content-Length: many\r\n\r\n{}"[..];
+        let error = read_message(&mut input).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn answers_in_the_framing_of_the_request() {
+        let message = json!({ "id": 1 });
+        let mut line = vec![];
+        write_message(&mut line, Framing::Line, &message).unwrap();
+        assert_eq!(line, b"{\"id\":1}\n");
+        let mut header = vec![];
+        write_message(&mut header, Framing::Header, &message).unwrap();
+        assert_eq!(header, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+    }
+
+    #[test]
+    fn serves_requests_until_the_input_ends() {
+        let root = root();
+        let roots = [Location::Local(root.path().to_path_buf())];
+        let config = Config::default();
+        let runner = MockRunner::new();
+        let input = [
+            // params を省略しても既定値で答える
+            r#"{"jsonrpc":"2.0","id":1,"method":"projects"}"#,
+            // id の無い通知には答えない
+            r#"{"jsonrpc":"2.0","method":"projects"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"query","params":{"query":"we"}}"#,
+            "{not json",
+            r#"{"jsonrpc":"2.0","id":3}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"rename"}"#,
+        ]
+        .join("\n");
+        let mut output = vec![];
+        server(&config, &roots, &runner)
+            .serve(input.as_bytes(), &mut output)
+            .unwrap();
+
+        let messages = messages(&output);
+        let names: Vec<&str> = messages[0]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|project| project["name"].as_str())
+            .collect();
+        assert_eq!(names, ["api", "web"]);
+        assert_eq!(messages[1]["id"], 2);
+        assert_eq!(messages[1]["result"][0]["label"], "web");
+        assert_eq!(messages[2]["id"], Value::Null);
+        assert_eq!(messages[2]["error"]["code"], PARSE_ERROR);
+        assert_eq!(messages[3]["id"], 3);
+        assert_eq!(messages[3]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(messages[4]["id"], 4);
+        assert_eq!(messages[4]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(messages.len(), 5);
+    }
+
+    #[test]
+    fn reports_invalid_params_and_server_errors() {
+        let config = Config::default();
+        let runner = MockRunner::new();
+        let server = server(&config, &[], &runner);
+        assert_eq!(error_code(server.call("query", json!({}))), INVALID_PARAMS);
+        assert_eq!(
+            error_code(server.call("open", json!({ "path": "/srv/app", "action": "zip" }))),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(server.call("open", json!({ "path": "ssh://devbox/~/app" }))),
+            SERVER_ERROR
+        );
+        assert!(runner.calls().is_empty());
+    }
+}
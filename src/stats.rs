@@ -0,0 +1,61 @@
+//! `projector stats`: 週ごとにプロジェクトのセッションで作業した時間を表示する
+
+use projector_core::cache;
+use projector_core::i18n::Msg;
+use projector_core::location::Location;
+use projector_core::runner::CommandRunner;
+use projector_core::state::State;
+use projector_core::timelog;
+use serde_json::json;
+use unicode_width::UnicodeWidthStr;
+
+use crate::cli::Format;
+
+/// 直近 `weeks` 週の作業時間を表示する。JSON なら週・プロジェクトごとに1要素の配列にする
+pub fn run(runner: &dyn CommandRunner, weeks: usize, format: Format) {
+    // 最後に実行してから終わったセッションの記録を閉じておく
+    timelog::poll(runner);
+    let weeks = timelog::weekly(&State::load().spans, cache::now(), weeks);
+
+    if format == Format::Json {
+        let rows: Vec<serde_json::Value> = weeks
+            .iter()
+            .flat_map(|week| {
+                week.projects.iter().map(|(project, seconds)| {
+                    json!({
+                        "week": timelog::format_date(week.start),
+                        "project": Location::parse(project).display(),
+                        "seconds": seconds,
+                    })
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(rows));
+        return;
+    }
+    if weeks.is_empty() {
+        println!("{}", Msg::NoTimeTracked);
+        return;
+    }
+    for (i, week) in weeks.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", Msg::StatsWeek(&timelog::format_date(week.start)));
+        let mut rows: Vec<(String, u64)> = week
+            .projects
+            .iter()
+            .map(|(project, seconds)| (Location::parse(project).display(), *seconds))
+            .collect();
+        rows.push((Msg::StatsTotal.to_string(), week.total()));
+        let width = rows.iter().map(|(name, _)| name.width()).max().unwrap_or(0);
+        for (name, seconds) in rows {
+            println!(
+                "  {}{}  {:>8}",
+                name,
+                " ".repeat(width - name.width()),
+                timelog::format_duration(seconds)
+            );
+        }
+    }
+}
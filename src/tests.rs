@@ -0,0 +1,447 @@
+//! TUI を端末なしで動かして確かめるテスト
+//!
+//! 一時ディレクトリにプロジェクトの木を作ってルートにし、キー操作を [`keys::handle`] に順に渡す。
+//! 外部コマンドは [`MockRunner`] が記録するので、組み立てた tmux の引数をそのまま確かめられる。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use projector_core::config::{Backend, Config, LayoutConfig, OptionValue};
+use projector_core::i18n::{self, Lang};
+use projector_core::location::Location;
+use projector_core::runner::MockRunner;
+use projector_core::session;
+use projector_core::state::ViewPrefs;
+use projector_core::template::Vars;
+use projector_core::Error;
+use tempfile::TempDir;
+
+use crate::app::{Action, App};
+use crate::keys::{self, Outcome};
+use crate::{session_spec, ui};
+
+/// 全ルートの一覧ができるのを待つ時間の上限
+const INDEX_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 一時ディレクトリをルートにした App と、そのコマンドを記録する実行器
+struct Harness {
+    root: TempDir,
+    /// ユーザーのキャッシュの代わりに API のレスポンスを置くディレクトリ（消えないよう持っておく）
+    _cache: TempDir,
+    config: Config,
+    runner: Arc<MockRunner>,
+    app: App,
+}
+
+impl Harness {
+    /// `tree` の各パスをルートの下に作る。`/` で終わるものはディレクトリ、それ以外は空のファイル
+    fn new(tree: &[&str]) -> Self {
+        Harness::with_config(tree, Config::default())
+    }
+
+    fn with_config(tree: &[&str], mut config: Config) -> Self {
+        i18n::set_lang(Lang::En);
+        let root = TempDir::new().unwrap();
+        for path in tree {
+            let path = root.path().join(path);
+            if path.to_string_lossy().ends_with('/') {
+                fs::create_dir_all(&path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, "").unwrap();
+            }
+        }
+        config.roots = vec![root.path().to_string_lossy().to_string()];
+        // tmux の有無でお知らせが変わらないようにする
+        config.backend = Backend::Shell;
+        let runner = Arc::new(MockRunner::new());
+        let roots = config.roots().iter().map(|r| Location::parse(r)).collect();
+        let cache = TempDir::new().unwrap();
+        let app = App::new(
+            roots,
+            None,
+            &config,
+            ViewPrefs::default(),
+            Some(cache.path().to_path_buf()),
+            runner.clone(),
+        );
+        Harness {
+            root,
+            _cache: cache,
+            config,
+            runner,
+            app,
+        }
+    }
+
+    /// ルートの下のパス
+    fn path(&self, relative: &str) -> PathBuf {
+        self.root.path().join(relative)
+    }
+
+    /// キーを順に押す。`<Enter>` のような名前のキー以外は1文字ずつ押す。
+    /// プロジェクトを開くか終了したら、そこで止めて結果を返す
+    fn press(&mut self, script: &str) -> Outcome {
+        for key in parse_keys(script) {
+            match keys::handle(&mut self.app, &key) {
+                Outcome::Continue => {}
+                outcome => return outcome,
+            }
+        }
+        Outcome::Continue
+    }
+
+    /// 全ルートの一覧ができるまで、バックグラウンドの結果を取り込む
+    fn wait_for_index(&mut self) {
+        let started = Instant::now();
+        while self.app.indexing() {
+            assert!(started.elapsed() < INDEX_TIMEOUT, "indexing timed out");
+            self.app.poll_updates();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// 絞り込み後に並んでいる名前
+    fn names(&self) -> Vec<&str> {
+        self.app
+            .visible_entries()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&str> {
+        self.app.selected_entry().map(|entry| entry.name.as_str())
+    }
+
+    /// 画面の内容。一時ディレクトリのパスは `<root>` に置き換える
+    fn screen(&self, width: u16, height: u16) -> String {
+        ui::draw(&self.app, width, height)
+            .text()
+            .replace(&self.root.path().to_string_lossy().to_string(), "<root>")
+    }
+
+    /// 開くプロジェクトのセッションを組み立てて作り、実行した tmux のコマンドを返す
+    fn create_session(&self, location: &Location) -> Result<Vec<String>, Error> {
+        let roots: Vec<Location> = self
+            .config
+            .roots()
+            .iter()
+            .map(|r| Location::parse(r))
+            .collect();
+        let name = Action::Open.session_name(location);
+        let vars = Vars::project(self.runner.as_ref(), location, &roots, &name);
+        let spec = session_spec(location, &self.config, &vars);
+        session::create(self.runner.as_ref(), &spec)?;
+        Ok(self.tmux_commands())
+    }
+
+    /// 記録した tmux のコマンド。一時ディレクトリのパスは `<root>` に置き換える
+    fn tmux_commands(&self) -> Vec<String> {
+        let root = self.root.path().to_string_lossy().to_string();
+        self.runner
+            .commands("tmux")
+            .into_iter()
+            .map(|command| command.replace(&root, "<root>"))
+            .collect()
+    }
+}
+
+/// `j<Down><Enter>` のような操作の列をキーにする
+fn parse_keys(script: &str) -> Vec<KeyEvent> {
+    let mut keys = vec![];
+    let mut rest = script;
+    while let Some(c) = rest.chars().next() {
+        let named = rest
+            .strip_prefix('<')
+            .and_then(|after| after.split_once('>'))
+            .filter(|(name, _)| name.len() > 1);
+        if let Some((name, after)) = named {
+            let (code, modifiers) = match name {
+                "Enter" => (KeyCode::Enter, KeyModifiers::NONE),
+                "M-Enter" => (KeyCode::Enter, KeyModifiers::ALT),
+                "Esc" => (KeyCode::Esc, KeyModifiers::NONE),
+                "Up" => (KeyCode::Up, KeyModifiers::NONE),
+                "Down" => (KeyCode::Down, KeyModifiers::NONE),
+                "Left" => (KeyCode::Left, KeyModifiers::NONE),
+                "Right" => (KeyCode::Right, KeyModifiers::NONE),
+                "BS" => (KeyCode::Backspace, KeyModifiers::NONE),
+                "Tab" => (KeyCode::Tab, KeyModifiers::NONE),
+                "Space" => (KeyCode::Char(' '), KeyModifiers::NONE),
+                "C-r" => (KeyCode::Char('r'), KeyModifiers::CONTROL),
+                other => panic!("unknown key <{}>", other),
+            };
+            keys.push(KeyEvent::new(code, modifiers));
+            rest = after;
+        } else {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    keys
+}
+
+/// クライアントごとのディレクトリの下にプロジェクトがある、よくある並び
+const TREE: &[&str] = &[
+    "clients/acme/.git/",
+    "clients/globex/Cargo.toml",
+    "notes/2024/",
+    "tools/.git/",
+];
+
+fn launched(outcome: Outcome) -> Location {
+    match outcome {
+        Outcome::Launch(Action::Open, location) => location,
+        other => panic!("expected a project to open, got {:?}", other),
+    }
+}
+
+fn local(location: &Location) -> &Path {
+    location.local_path().expect("a local project")
+}
+
+#[test]
+fn browses_into_directories_and_back() {
+    let mut h = Harness::new(TREE);
+    assert_eq!(h.names(), ["clients", "notes", "tools"]);
+
+    h.press("j<Space>");
+    assert_eq!(h.names(), ["2024"]);
+
+    // 戻ると、入ったディレクトリを選んだ状態になる
+    h.press("<BS>");
+    assert_eq!(h.names(), ["clients", "notes", "tools"]);
+    assert_eq!(h.selected(), Some("notes"));
+
+    h.press("k<Space>");
+    assert_eq!(h.names(), ["acme", "globex"]);
+    h.press("j");
+    assert_eq!(h.selected(), Some("globex"));
+
+    // H / L で履歴を行き来する
+    h.press("H");
+    assert_eq!(h.names(), ["clients", "notes", "tools"]);
+    h.press("L");
+    assert_eq!(h.names(), ["acme", "globex"]);
+}
+
+#[test]
+fn does_not_enter_projects_without_force() {
+    let mut h = Harness::new(&["tools/.git/", "tools/src/"]);
+    h.press("<Space>");
+    assert_eq!(h.names(), ["tools"]);
+    assert!(h.screen(80, 8).contains("tools is a project"));
+
+    h.press(">");
+    assert_eq!(h.names(), ["src"]);
+}
+
+#[test]
+fn stops_at_the_configured_depth() {
+    let config = Config {
+        max_depth: Some(2),
+        ..Config::default()
+    };
+    let mut h = Harness::with_config(&["notes/2024/q1/"], config);
+    h.press("<Space>");
+    assert_eq!(h.names(), ["2024"]);
+
+    h.press("<Space>");
+    assert_eq!(h.names(), ["2024"]);
+    assert!(h
+        .screen(80, 8)
+        .contains("cannot go any deeper (max_depth = 2)"));
+}
+
+#[test]
+fn filters_the_listing() {
+    let mut h = Harness::new(TREE);
+    h.press("/to");
+    assert_eq!(h.names(), ["tools"]);
+    // 詳細はバックグラウンドで調べるので、詳細欄の出ない幅で比べる
+    insta::assert_snapshot!(h.screen(50, 6));
+
+    // 絞り込みをやめると元に戻る
+    h.press("<Esc>");
+    assert_eq!(h.names(), ["clients", "notes", "tools"]);
+    assert!(h.app.filter.is_none());
+
+    let location = launched(h.press("/tls<Enter>"));
+    assert_eq!(local(&location), h.path("tools"));
+}
+
+#[test]
+fn regex_filter() {
+    let mut h = Harness::new(TREE);
+    h.press("/<C-r>^n");
+    assert_eq!(h.names(), ["notes"]);
+
+    h.press("(");
+    assert!(h.app.filter_invalid);
+    assert!(h.names().is_empty());
+}
+
+#[test]
+fn finds_projects_in_all_roots() {
+    let mut h = Harness::new(TREE);
+    h.press("f");
+    h.wait_for_index();
+    assert_eq!(
+        h.names(),
+        [
+            "clients",
+            "clients/acme",
+            "clients/globex",
+            "notes",
+            "tools"
+        ]
+    );
+
+    let location = launched(h.press("/glob<Enter>"));
+    assert_eq!(local(&location), h.path("clients/globex"));
+}
+
+#[test]
+fn lists_remote_directories_in_the_background() {
+    let mut h = Harness::new(TREE);
+    h.runner.respond(&["ssh"], true, "d api\nd web\n");
+    h.press(":ssh://devbox/srv<Enter>");
+    // ssh の結果を取り込むまでは空の一覧に入っている
+    assert!(h.app.indexing());
+    assert!(h.names().is_empty());
+    assert!(h.screen(80, 10).contains("looking for projects"));
+
+    h.wait_for_index();
+    assert_eq!(h.names(), ["api", "web"]);
+}
+
+#[test]
+fn quits_without_opening() {
+    let mut h = Harness::new(TREE);
+    assert_eq!(h.press("jq"), Outcome::Quit);
+    assert_eq!(h.press("<Esc>"), Outcome::Quit);
+    assert_eq!(h.press("R"), Outcome::Reload);
+}
+
+#[test]
+fn opens_the_directory_being_browsed() {
+    let mut h = Harness::new(TREE);
+    // ルート一覧ではまだ開くディレクトリが無い
+    assert_eq!(h.press("<M-Enter>"), Outcome::Continue);
+    let location = launched(h.press("<Space><M-Enter>"));
+    assert_eq!(local(&location), h.path("clients"));
+}
+
+#[test]
+fn builds_the_session_for_the_selected_project() {
+    let mut h = Harness::new(TREE);
+    let location = launched(h.press("<Space>j<Enter>"));
+    assert_eq!(local(&location), h.path("clients/globex"));
+
+    h.runner.respond(&["tmux", "-V"], true, "tmux 3.4\n");
+    let commands = h.create_session(&location).unwrap();
+    assert_eq!(
+        commands,
+        [
+            "tmux -V",
+            "tmux new-session -d -s globex -c <root>/clients/globex",
+            "tmux split-window -h -t globex -c <root>/clients/globex",
+        ]
+    );
+}
+
+#[test]
+fn builds_the_configured_layout() {
+    let mut layout = LayoutConfig {
+        panes: vec![String::new(), "lazygit".to_string()],
+        synchronize: true,
+        ..LayoutConfig::default()
+    };
+    layout
+        .options
+        .insert("history-limit".to_string(), OptionValue::Number(50000));
+    let config = Config {
+        layout,
+        ..Config::default()
+    };
+    let mut h = Harness::with_config(TREE, config);
+    let location = launched(h.press("/tools<Enter>"));
+
+    h.runner.respond(&["tmux", "-V"], true, "tmux 3.4\n");
+    let commands = h.create_session(&location).unwrap();
+    assert_eq!(
+        commands,
+        [
+            "tmux -V",
+            "tmux new-session -d -s tools -c <root>/tools",
+            "tmux set-option -t tools history-limit 50000",
+            "tmux split-window -h -t tools -c <root>/tools lazygit",
+            "tmux set-window-option -t tools synchronize-panes on",
+        ]
+    );
+}
+
+#[test]
+fn refuses_layouts_an_old_tmux_cannot_build() {
+    let mut h = Harness::new(TREE);
+    let location = launched(h.press("/tools<Enter>"));
+
+    h.runner.respond(&["tmux", "-V"], true, "tmux 1.8\n");
+    let error = h.create_session(&location).unwrap_err();
+    assert!(matches!(error, Error::TmuxTooOld { .. }));
+    // セッションを作りかけたままにしない
+    assert_eq!(h.tmux_commands(), ["tmux -V"]);
+}
+
+#[test]
+fn runs_leader_key_chords() {
+    let mut config = Config::default();
+    config
+        .keys
+        .chords
+        .insert("space o".to_string(), "open".to_string());
+    let mut h = Harness::with_config(TREE, config);
+
+    // まとまりのキーの後は一覧を出して続きを待つ
+    h.press(",v");
+    assert!(h.screen(60, 12).contains("filter"));
+    h.press("<Esc>");
+    assert_eq!(h.app.chord, None);
+
+    let location = launched(h.press("<Space>,<Space>o"));
+    assert_eq!(local(&location), h.path("clients/acme"));
+    assert_eq!(h.press(",q"), Outcome::Quit);
+}
+
+#[test]
+fn describes_the_configured_leader_key_in_the_hint() {
+    let mut config = Config::default();
+    config.keys.leader = 'g';
+    let h = Harness::with_config(TREE, config);
+
+    // リーダーキーに取られたキーは1つのキーの操作から外れる
+    let screen = h.screen(400, 6);
+    assert!(screen.contains("[g] more keys"), "{}", screen);
+    assert!(!screen.contains("git UI"), "{}", screen);
+    assert!(screen.contains("[Enter] open in tmux"), "{}", screen);
+}
+
+#[test]
+fn confirms_before_opening_plain_directories() {
+    let mut config = Config::default();
+    config.open.confirm_plain_dirs = true;
+    let mut h = Harness::with_config(TREE, config);
+
+    // clients にはプロジェクトの目印が無い
+    assert_eq!(h.press("<Enter>"), Outcome::Continue);
+    assert!(h.app.confirm.is_some());
+    assert_eq!(h.press("n"), Outcome::Continue);
+    let location = launched(h.press("<Enter>y"));
+    assert_eq!(local(&location), h.path("clients"));
+
+    let location = launched(h.press("/tools<Enter>"));
+    assert_eq!(local(&location), h.path("tools"));
+}
@@ -0,0 +1,917 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::{
+    event::KeyCode,
+    style::{self, Color},
+    terminal,
+};
+
+use projector_core::cache;
+use projector_core::config::HintConfig;
+use projector_core::forge::CiStatus;
+use projector_core::i18n::Msg;
+use projector_core::stats::format_size;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::app::{App, Confirm, Details, PromptKind, Toast, View};
+use crate::frame::{Frame, Style};
+use crate::keys::{self, Binding, Keymap};
+
+// ヘッダー（パス・区切り線・ヒント・空行）の行数。ヒントを出さなければ1行減る
+const HEADER_HEIGHT: u16 = 4;
+// これより狭い端末では詳細ペインを表示しない
+const MIN_WIDTH_FOR_DETAILS: u16 = 60;
+// エラーの表示を折り返す幅
+const MAX_ERROR_WIDTH: usize = 72;
+// 詳細ペインに表示する言語の数
+const TOP_LANGUAGES: usize = 5;
+
+/// 印や罫線に使う文字
+struct Glyphs {
+    /// 選択中の行の印
+    selected: &'static str,
+    /// ヘッダーの区切り線
+    rule: &'static str,
+    /// 詳細ペインの左の縦線
+    divider: &'static str,
+    /// 切り詰めた末尾（表示幅 1）
+    ellipsis: char,
+    symlink: &'static str,
+    alias: &'static str,
+    stash: &'static str,
+    /// upstream より進んでいる・遅れている
+    upstream: [&'static str; 2],
+    /// CI の成功・失敗・実行中
+    ci: [char; 3],
+    /// 全ルートを辿っている間に回すスピナーのコマ
+    spinner: &'static [char],
+    /// 確認ダイアログの枠（左上・右上・左下・右下・横・縦）
+    frame: [char; 6],
+}
+
+const UNICODE: Glyphs = Glyphs {
+    selected: "❯",
+    rule: "─────────────────────────────────────",
+    divider: "│",
+    ellipsis: '…',
+    symlink: "➜",
+    alias: "→",
+    stash: "≡",
+    upstream: ["↑", "↓"],
+    ci: ['✓', '✗', '●'],
+    spinner: &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+    frame: ['┌', '┐', '└', '┘', '─', '│'],
+};
+
+const ASCII: Glyphs = Glyphs {
+    selected: ">",
+    rule: "-------------------------------------",
+    divider: "|",
+    ellipsis: '~',
+    symlink: "@",
+    alias: "->",
+    stash: "$",
+    upstream: ["^", "v"],
+    ci: ['+', 'x', '*'],
+    spinner: &['|', '/', '-', '\\'],
+    frame: ['+', '+', '+', '+', '-', '|'],
+};
+
+// 色や太字を使うか（--color と NO_COLOR など）
+static STYLED: AtomicBool = AtomicBool::new(true);
+
+// 印や罫線を ASCII だけで描くか（--ascii と設定の ascii）
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// 印や罫線を ASCII だけで描くかを決める
+pub fn set_ascii(enabled: bool) {
+    ASCII_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+fn glyphs() -> &'static Glyphs {
+    if ASCII_ONLY.load(Ordering::Relaxed) {
+        &ASCII
+    } else {
+        &UNICODE
+    }
+}
+
+/// 色や太字を使うかを決める。使わなければ文字だけを書き出す
+pub fn set_styled(enabled: bool) {
+    STYLED.store(enabled, Ordering::Relaxed);
+    style::force_color_output(enabled);
+}
+
+fn styled() -> bool {
+    STYLED.load(Ordering::Relaxed)
+}
+
+/// 表示幅が `width` を超えるなら末尾を `…`（ASCII なら `~`）にして切り詰める
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    if width > 0 {
+        out.push(glyphs().ellipsis);
+    }
+    out
+}
+
+/// 表示幅を数えながら1行を書く。はみ出す部分は切り詰めて折り返さない
+struct Line<'a> {
+    frame: &'a mut Frame,
+    remaining: usize,
+}
+
+impl<'a> Line<'a> {
+    fn new(frame: &'a mut Frame, width: u16) -> Self {
+        Line {
+            frame,
+            remaining: width as usize,
+        }
+    }
+
+    fn print(&mut self, text: &str, color: Option<Color>) {
+        self.put(text, Style::color(color.filter(|_| styled())));
+    }
+
+    /// 太字で書く
+    fn print_bold(&mut self, text: &str, color: Color) {
+        if !styled() {
+            return self.print(text, None);
+        }
+        self.put(text, Style::bold(color));
+    }
+
+    fn put(&mut self, text: &str, style: Style) {
+        if self.remaining == 0 {
+            return;
+        }
+        let text = truncate(text, self.remaining);
+        self.remaining -= text.width();
+        self.frame.print(&text, style);
+    }
+
+    /// 名前を書き、`highlight` の位置の文字（検索語にマッチしたもの）を強調する
+    fn print_name(&mut self, name: &str, highlight: &[usize], color: Option<Color>) {
+        let mut chars = name.char_indices().enumerate().peekable();
+        while let Some((i, (start, _))) = chars.next() {
+            let matched = highlight.binary_search(&i).is_ok();
+            // 強調の有無が同じ文字はまとめて書く
+            let mut end = name.len();
+            while let Some(&(j, (next, _))) = chars.peek() {
+                if highlight.binary_search(&j).is_ok() != matched {
+                    end = next;
+                    break;
+                }
+                chars.next();
+            }
+            if matched {
+                self.print_bold(&name[start..end], Color::Yellow);
+            } else {
+                self.print(&name[start..end], color);
+            }
+        }
+    }
+
+    fn end(self) {
+        self.frame.next_line();
+    }
+}
+
+/// 画面を描いて端末に書き出す
+pub fn render(out: &mut impl Write, app: &App) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    draw(app, width, height).write(out)
+}
+
+/// `width` × `height` の画面を描く。端末には書き出さない
+pub fn draw(app: &App, width: u16, height: u16) -> Frame {
+    let mut frame = Frame::new(width, height);
+    // 詳細ペインと重ならないよう一覧の幅を抑える
+    let list_width = if width < MIN_WIDTH_FOR_DETAILS {
+        width
+    } else {
+        width / 2 - 1
+    };
+
+    // ヘッダー
+    let mut line = Line::new(&mut frame, width);
+    line.print(&format!(" {}", app.current_display()), Some(Color::Cyan));
+    if let Some(name) = &app.type_filter {
+        line.print(&format!(" [{}]", name), Some(Color::Yellow));
+    }
+    line.end();
+    let glyphs = glyphs();
+    let mut line = Line::new(&mut frame, width);
+    line.print(&format!(" {}", glyphs.rule), None);
+    line.end();
+    let hint = match &app.hint {
+        HintConfig::Show(true) => Some(key_hint(&app.keymap)),
+        HintConfig::Show(false) => None,
+        HintConfig::Text(text) => Some(text.clone()),
+    };
+    if let Some(hint) = &hint {
+        let mut line = Line::new(&mut frame, width);
+        line.print(&format!(" {}", hint), Some(Color::DarkGrey));
+        line.end();
+    }
+    let header_height = HEADER_HEIGHT - u16::from(hint.is_none());
+    // 入力中ならその行でカーソルを出す位置
+    let mut cursor_column = None;
+    let mut line = Line::new(&mut frame, width);
+    if let Some(picker) = &app.task_picker {
+        line.print(
+            &format!(" {}", Msg::TasksTitle(&picker.location.display())),
+            Some(Color::Cyan),
+        );
+        line.print(&format!("  {}", Msg::TasksHint), Some(Color::DarkGrey));
+    } else if app.type_picker.is_some() {
+        line.print(&format!(" {}", Msg::TypesTitle), Some(Color::Cyan));
+        line.print(&format!("  {}", Msg::TypesHint), Some(Color::DarkGrey));
+    } else if let Some((kind, input)) = &app.prompt {
+        let (prefix, hint) = match kind {
+            PromptKind::Jump => (":", Msg::JumpHint),
+            PromptKind::Search => ("rg: ", Msg::SearchHint),
+            PromptKind::Rename => ("mv: ", Msg::RenameHint),
+            PromptKind::NewDir => ("mkdir: ", Msg::NewDirHint),
+        };
+        cursor_column = Some(1 + prefix.width() + input.cursor_width());
+        line.print(&format!(" {}{}", prefix, input.text()), Some(Color::Cyan));
+        line.print(&format!("  {}", hint), Some(Color::DarkGrey));
+    } else if let Some(filter) = &app.filter {
+        cursor_column = Some(2 + filter.cursor_width());
+        line.print(&format!(" /{}", filter.text()), Some(Color::Yellow));
+        if app.filter_invalid {
+            line.print(&format!("  {}", Msg::InvalidRegex), Some(Color::Red));
+        } else {
+            line.print(&format!("  {}", Msg::FilterHint), Some(Color::DarkGrey));
+        }
+    } else if let Some((elapsed, scanned)) = app.scan_progress() {
+        // 描画は POLL_INTERVAL ごとなので、経過時間からコマを決める
+        let spinner = glyphs.spinner;
+        let tick = spinner[(elapsed.as_millis() / 100) as usize % spinner.len()];
+        line.print(
+            &format!(" {} {}", tick, Msg::Scanning(scanned)),
+            Some(Color::DarkGrey),
+        );
+    }
+    line.end();
+
+    if let Some(picker) = &app.task_picker {
+        for (i, task) in picker.tasks.iter().enumerate() {
+            let mut line = Line::new(&mut frame, list_width);
+            if i == picker.selected {
+                line.print(
+                    &format!(" {} {}", glyphs.selected, task.name),
+                    Some(Color::Green),
+                );
+            } else {
+                line.print(&format!("   {}", task.name), None);
+            }
+            line.print(
+                &format!("  [{}] {}", task.source.label(), task.command),
+                Some(Color::DarkGrey),
+            );
+            line.end();
+        }
+    } else if let Some(picker) = &app.type_picker {
+        for (i, option) in picker.options.iter().enumerate() {
+            let mut line = Line::new(&mut frame, list_width);
+            let name = match option {
+                Some(name) => name.clone(),
+                None => Msg::AllTypes.to_string(),
+            };
+            if i == picker.selected {
+                line.print(
+                    &format!(" {} {}", glyphs.selected, name),
+                    Some(Color::Green),
+                );
+            } else {
+                line.print(&format!("   {}", name), None);
+            }
+            line.end();
+        }
+    } else if app.visible.is_empty() {
+        let message = if app.indexing() {
+            match app.view {
+                View::Search(_) => Msg::Searching,
+                _ => Msg::Indexing,
+            }
+        } else if app.items.is_empty() {
+            Msg::NoSubdirectories
+        } else {
+            Msg::NoMatches
+        };
+        let mut line = Line::new(&mut frame, width);
+        line.print(&format!("   {}", message), Some(Color::DarkGrey));
+        line.end();
+    } else {
+        for (i, entry) in app.visible_entries().enumerate() {
+            let mut line = Line::new(&mut frame, list_width);
+            let highlight = app.highlights.get(i).map(Vec::as_slice).unwrap_or_default();
+            let (marker, color) = if i == app.selected {
+                (glyphs.selected, Some(Color::Green))
+            } else {
+                (" ", None)
+            };
+            line.print(&format!(" {} ", marker), color);
+            line.print_name(&entry.name, highlight, color);
+            line.print("/", color);
+
+            if entry.location.has_lossy_name() {
+                line.print(" [!utf8]", Some(Color::Yellow));
+            }
+            if entry.symlink {
+                line.print(&format!(" {}", glyphs.symlink), Some(Color::DarkGrey));
+            }
+            if entry.alias {
+                line.print(
+                    &format!(" {} {}", glyphs.alias, entry.location.display()),
+                    Some(Color::DarkGrey),
+                );
+            } else if let Some(host) = entry.location.host() {
+                line.print(&format!(" [{}]", host), Some(Color::DarkGrey));
+            }
+            let ci = entry
+                .location
+                .local_path()
+                .and_then(|path| app.ci.get(path));
+            if let Some(Some(status)) = ci {
+                let (mark, color) = ci_mark(glyphs, *status);
+                line.print(&format!(" {}", mark), Some(color));
+            }
+            let stashes = entry
+                .location
+                .local_path()
+                .and_then(|path| app.stashes.get(path))
+                .copied()
+                .unwrap_or(0);
+            if stashes > 0 {
+                line.print(
+                    &format!(" {}{}", glyphs.stash, stashes),
+                    Some(Color::Magenta),
+                );
+            }
+            let upstream = entry
+                .location
+                .local_path()
+                .and_then(|path| app.upstream.get(path));
+            if let Some(&Some((ahead, behind))) = upstream {
+                let [ahead_mark, behind_mark] = glyphs.upstream;
+                let mut marks = String::new();
+                if ahead > 0 {
+                    marks.push_str(&format!("{}{}", ahead_mark, ahead));
+                }
+                if behind > 0 {
+                    marks.push_str(&format!("{}{}", behind_mark, behind));
+                }
+                if !marks.is_empty() {
+                    line.print(&format!(" {}", marks), Some(Color::Yellow));
+                }
+            }
+            line.end();
+        }
+    }
+
+    draw_details(&mut frame, app, header_height);
+    draw_toasts(&mut frame, &app.toasts);
+    if let Some(pressed) = &app.chord {
+        draw_chords(&mut frame, app, pressed);
+    }
+    match (&app.error, &app.confirm, cursor_column) {
+        (Some(error), _, _) => draw_error(&mut frame, error),
+        (None, Some(confirm), _) => draw_confirm(&mut frame, confirm),
+        (None, None, Some(column)) => {
+            let column = column.min(width.saturating_sub(1) as usize) as u16;
+            frame.show_cursor(column, header_height - 1);
+        }
+        (None, None, None) => {}
+    }
+    frame
+}
+
+fn draw_details(frame: &mut Frame, app: &App, header_height: u16) {
+    let width = frame.width();
+    if width < MIN_WIDTH_FOR_DETAILS {
+        return;
+    }
+    let Some(entry) = app.selected_entry() else {
+        return;
+    };
+    let column = width / 2;
+    let pane_width = width - column;
+    let divider = glyphs().divider;
+
+    frame.move_to(column, header_height);
+    Line::new(frame, pane_width).print(
+        &format!("{} {}", divider, Msg::DetailsTitle),
+        Some(Color::Cyan),
+    );
+    // リモートのプロジェクトは統計を取らない
+    let Some(path) = app.selected_path() else {
+        frame.move_to(column, header_height + 1);
+        Line::new(frame, pane_width).print(
+            &format!("{} {}", divider, Msg::Remote(&entry.location.display())),
+            None,
+        );
+        return;
+    };
+
+    let details = app.details.get(&path);
+    let mut top = header_height + 1;
+    if draw_health(frame, app, &path, details, column, top) {
+        top += 1;
+    }
+    let size = match details.and_then(|d| d.size) {
+        Some(bytes) => format_size(bytes),
+        None => Msg::Computing.to_string(),
+    };
+
+    let todos = match details.and_then(|d| d.todos.as_ref()) {
+        Some(count) => format!("TODO {} / FIXME {}", count.todo, count.fixme),
+        None => Msg::Computing.to_string(),
+    };
+
+    let mut lines = vec![
+        Msg::Size(&size).to_string(),
+        Msg::Markers(&todos).to_string(),
+    ];
+    if let Some(&count) = app.stashes.get(&path).filter(|&&count| count > 0) {
+        lines.push(Msg::Stashes(count).to_string());
+    }
+    if let Some(&Some((ahead, behind))) = app.upstream.get(&path) {
+        if ahead + behind > 0 {
+            lines.push(Msg::Upstream { ahead, behind }.to_string());
+        }
+    }
+    if details.is_some_and(|d| d.devcontainer) {
+        lines.push(Msg::DevcontainerAvailable.to_string());
+    }
+    if let Some(kind) = details.and_then(|d| d.nix) {
+        lines.push(format!("Nix: {}", kind.label()));
+    }
+    if details.is_some_and(|d| d.compose) {
+        let status = match details.and_then(|d| d.compose_status) {
+            None => Msg::Fetching.to_string(),
+            Some(None) => Msg::ComposeUnknown.to_string(),
+            Some(Some(s)) if s.running == 0 => Msg::ComposeStopped.to_string(),
+            Some(Some(s)) => Msg::ComposeRunning {
+                running: s.running,
+                total: s.total,
+            }
+            .to_string(),
+        };
+        lines.push(Msg::Compose(&status).to_string());
+    }
+    match details.and_then(|d| d.open_counts) {
+        Some(Some(counts)) => lines.push(
+            Msg::OpenCounts {
+                pull_requests: counts.pull_requests,
+                issues: counts.issues,
+            }
+            .to_string(),
+        ),
+        Some(None) => {}
+        None if app.fetches_open_counts() => lines.push(Msg::OpenCountsFetching.to_string()),
+        None => {}
+    }
+    match details.and_then(|d| d.languages.as_ref()) {
+        None => lines.push(Msg::LanguagesComputing.to_string()),
+        Some(languages) if languages.is_empty() => lines.push(Msg::LanguagesNone.to_string()),
+        Some(languages) => {
+            let total: usize = languages.iter().map(|l| l.lines).sum();
+            lines.push(Msg::LanguagesTotal(total).to_string());
+            for language in languages.iter().take(TOP_LANGUAGES) {
+                lines.push(
+                    Msg::LanguageRow {
+                        name: language.name,
+                        lines: language.lines,
+                        files: language.files,
+                    }
+                    .to_string(),
+                );
+            }
+        }
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        frame.move_to(column, top + i as u16);
+        Line::new(frame, pane_width).print(&format!("{} {}", divider, line), None);
+    }
+}
+
+/// 詳細ペインに健全性の行（最後のコミット・変更の有無・CI・PR・TODO）を描く。出すものが無ければ false
+///
+/// 項目は設定の `[health]` で選び、まだ計算していないものや連携していないものは出さない。
+fn draw_health(
+    frame: &mut Frame,
+    app: &App,
+    path: &Path,
+    details: Option<&Details>,
+    column: u16,
+    row: u16,
+) -> bool {
+    let health = app.health;
+    let glyphs = glyphs();
+    let mut items: Vec<(String, Option<Color>)> = vec![];
+    if let Some(time) = details
+        .and_then(|d| d.last_commit)
+        .filter(|_| health.last_commit)
+    {
+        let age = cache::now().saturating_sub(time);
+        items.push((Msg::CommitAge(age).to_string(), None));
+    }
+    match details.and_then(|d| d.dirty).filter(|_| health.dirty) {
+        Some(true) => items.push((Msg::Dirty.to_string(), Some(Color::Yellow))),
+        Some(false) => items.push((Msg::Clean.to_string(), Some(Color::Green))),
+        None => {}
+    }
+    if let Some(Some(status)) = app.ci.get(path).filter(|_| health.ci) {
+        let (mark, color) = ci_mark(glyphs, *status);
+        items.push((format!("CI {}", mark), Some(color)));
+    }
+    let counts = details.and_then(|d| d.open_counts).flatten();
+    if let Some(counts) = counts.filter(|_| health.pull_requests) {
+        items.push((
+            Msg::HealthPullRequests(counts.pull_requests).to_string(),
+            None,
+        ));
+    }
+    if let Some(todos) = details
+        .and_then(|d| d.todos.as_ref())
+        .filter(|_| health.todos)
+    {
+        items.push((format!("TODO {}", todos.todo + todos.fixme), None));
+    }
+    if items.is_empty() {
+        return false;
+    }
+
+    frame.move_to(column, row);
+    let mut line = Line::new(frame, frame.width() - column);
+    line.print(&format!("{} {}", glyphs.divider, Msg::HealthTitle), None);
+    // 項目の中にも空白があるので、項目の間は2つ空ける
+    for (text, color) in items {
+        line.print(&format!("  {}", text), color);
+    }
+    true
+}
+
+/// お知らせを画面の右下に重ねて描く（新しいものが下）
+fn draw_toasts(frame: &mut Frame, toasts: &[Toast]) {
+    let (width, height) = (frame.width(), frame.height());
+    let max_width = (width as usize).saturating_sub(2);
+    for (i, toast) in toasts.iter().rev().enumerate() {
+        let Some(row) = height.checked_sub(1 + i as u16) else {
+            break;
+        };
+        let text = truncate(&format!(" {} ", toast.text), max_width);
+        let column = (width as usize).saturating_sub(text.width() + 1) as u16;
+        let color = if toast.error {
+            Color::Red
+        } else {
+            Color::Magenta
+        };
+        frame.move_to(column, row);
+        Line::new(frame, width - column).print_bold(&text, color);
+    }
+}
+
+/// エラーを画面の中央に重ねて描く。長いメッセージは折り返す
+fn draw_error(frame: &mut Frame, message: &str) {
+    let (width, height) = (frame.width(), frame.height());
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, _] = glyphs().frame;
+    let title = Msg::ErrorTitle.to_string();
+    let hint = Msg::ErrorHint.to_string();
+    let max_inner = (width as usize).saturating_sub(2);
+    let lines = wrap(message, max_inner.saturating_sub(2).min(MAX_ERROR_WIDTH));
+    let content_width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain([title.width(), hint.width()])
+        .max()
+        .unwrap_or(0);
+    let inner = (content_width + 2).min(max_inner);
+    let left = (width as usize).saturating_sub(inner + 2) as u16 / 2;
+    let rows = lines.len() as u16 + 5;
+    let top = height.saturating_sub(rows) / 2;
+
+    let border = horizontal.to_string().repeat(inner);
+    frame.move_to(left, top);
+    frame.print(
+        &format!("{}{}{}", top_left, border, top_right),
+        Style::default(),
+    );
+    dialog_row(frame, left, top + 1, inner, |line| {
+        line.print_bold(&format!(" {}", title), Color::Red)
+    });
+    for (i, text) in lines.iter().enumerate() {
+        dialog_row(frame, left, top + 2 + i as u16, inner, |line| {
+            line.print(&format!(" {}", text), None)
+        });
+    }
+    dialog_row(frame, left, top + rows - 3, inner, |_| {});
+    dialog_row(frame, left, top + rows - 2, inner, |line| {
+        line.print(&format!(" {}", hint), Some(Color::DarkGrey))
+    });
+    frame.move_to(left, top + rows - 1);
+    frame.print(
+        &format!("{}{}{}", bottom_left, border, bottom_right),
+        Style::default(),
+    );
+}
+
+/// 表示幅 `width` に収まるよう折り返す。なるべく空白で区切り、長い語は途中で切る
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_inclusive(' ') {
+            if line.width() + word.trim_end().width() > width && !line.trim().is_empty() {
+                lines.push(line.trim_end().to_string());
+                line.clear();
+            }
+            for c in word.chars() {
+                if line.width() + c.width().unwrap_or(0) > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// 確認ダイアログを画面の中央に重ねて描く
+fn draw_confirm(frame: &mut Frame, confirm: &Confirm) {
+    let (width, height) = (frame.width(), frame.height());
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, _] = glyphs().frame;
+    let yes = Msg::Yes.to_string();
+    let no = Msg::No.to_string();
+    let hint = Msg::ConfirmHint.to_string();
+    // フォーカスのあるボタンは括弧で囲む（色が無くても分かるように）
+    let button = |label: &str, focused: bool| {
+        if focused {
+            format!("[ {} ]", label)
+        } else {
+            format!("  {}  ", label)
+        }
+    };
+    let buttons_width = yes.width() + no.width() + 10;
+    let content_width = confirm.message.width().max(hint.width()).max(buttons_width);
+    // 枠の内側の幅。狭い端末では内容を切り詰める
+    let inner = (content_width + 2).min((width as usize).saturating_sub(2));
+    let left = (width as usize).saturating_sub(inner + 2) as u16 / 2;
+    let top = height.saturating_sub(6) / 2;
+
+    let border = horizontal.to_string().repeat(inner);
+    frame.move_to(left, top);
+    frame.print(
+        &format!("{}{}{}", top_left, border, top_right),
+        Style::default(),
+    );
+    dialog_row(frame, left, top + 1, inner, |line| {
+        line.print_bold(&format!(" {}", confirm.message), Color::Yellow)
+    });
+    dialog_row(frame, left, top + 2, inner, |_| {});
+    dialog_row(frame, left, top + 3, inner, |line| {
+        line.print(&" ".repeat(inner.saturating_sub(buttons_width) / 2), None);
+        for (label, focused) in [(&yes, confirm.yes), (&no, !confirm.yes)] {
+            let text = button(label, focused);
+            if focused {
+                line.print_bold(&text, Color::Green);
+            } else {
+                line.print(&text, None);
+            }
+            line.print(" ", None);
+        }
+    });
+    dialog_row(frame, left, top + 4, inner, |line| {
+        line.print(&format!(" {}", hint), Some(Color::DarkGrey))
+    });
+    frame.move_to(left, top + 5);
+    frame.print(
+        &format!("{}{}{}", bottom_left, border, bottom_right),
+        Style::default(),
+    );
+}
+
+/// ヘッダーの操作説明。移動のキー、リーダーキー、1つのキーの操作の順に並べる
+fn key_hint(keymap: &Keymap) -> String {
+    let leader = keys::key_name(KeyCode::Char(keymap.leader));
+    let mut hint = vec![
+        Msg::NavigationHint.to_string(),
+        Msg::LeaderHint(&leader).to_string(),
+    ];
+    for (code, command) in keymap.keys() {
+        hint.push(format!(
+            "[{}] {}",
+            keys::key_name(*code),
+            Msg::CommandName(command.name())
+        ));
+    }
+    hint.join("  ")
+}
+
+/// リーダーキーの後に押せるキーの一覧を画面の下に重ねて描く
+fn draw_chords(frame: &mut Frame, app: &App, pressed: &str) {
+    let (width, height) = (frame.width(), frame.height());
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, _] = glyphs().frame;
+    let key_label = |c: char| keys::key_name(KeyCode::Char(c));
+    let rows: Vec<(String, String, bool)> = app
+        .keymap
+        .next_keys(pressed)
+        .into_iter()
+        .map(|(key, binding)| match binding {
+            Binding::Command(command) => (
+                key_label(key),
+                Msg::CommandName(command.name()).to_string(),
+                false,
+            ),
+            Binding::Group(name) => (key_label(key), Msg::ChordGroup(&name).to_string(), true),
+        })
+        .collect();
+    let title = std::iter::once(app.keymap.leader)
+        .chain(pressed.chars())
+        .map(key_label)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let key_width = rows
+        .iter()
+        .map(|(key, _, _)| key.width())
+        .max()
+        .unwrap_or(0);
+    let content_width = rows
+        .iter()
+        .map(|(_, text, _)| key_width + 2 + text.width())
+        .max()
+        .unwrap_or(0)
+        .max(title.width());
+    let inner = (content_width + 2).min((width as usize).saturating_sub(2));
+    // 画面に収まらない分は出さない
+    let shown = rows.len().min((height as usize).saturating_sub(3));
+    let left = (width as usize).saturating_sub(inner + 2) as u16 / 2;
+    let top = height.saturating_sub(shown as u16 + 3);
+
+    let border = horizontal.to_string().repeat(inner);
+    frame.move_to(left, top);
+    frame.print(
+        &format!("{}{}{}", top_left, border, top_right),
+        Style::default(),
+    );
+    dialog_row(frame, left, top + 1, inner, |line| {
+        line.print_bold(&format!(" {}", title), Color::Cyan)
+    });
+    for (i, (key, text, group)) in rows.iter().take(shown).enumerate() {
+        dialog_row(frame, left, top + 2 + i as u16, inner, |line| {
+            line.print_bold(&format!(" {:<1$}", key, key_width), Color::Yellow);
+            let color = if *group { Some(Color::Magenta) } else { None };
+            line.print(&format!("  {}", text), color);
+        });
+    }
+    frame.move_to(left, top + 2 + shown as u16);
+    frame.print(
+        &format!("{}{}{}", bottom_left, border, bottom_right),
+        Style::default(),
+    );
+}
+
+/// 確認ダイアログの枠の中の1行。`inner` に満たない分は空白で埋めて下の表示を隠す
+fn dialog_row(
+    frame: &mut Frame,
+    left: u16,
+    row: u16,
+    inner: usize,
+    draw: impl FnOnce(&mut Line<'_>),
+) {
+    let vertical = glyphs().frame[5].to_string();
+    frame.move_to(left, row);
+    frame.print(&vertical, Style::default());
+    let mut line = Line::new(frame, inner as u16);
+    draw(&mut line);
+    let rest = line.remaining;
+    frame.print(&" ".repeat(rest), Style::default());
+    frame.print(&vertical, Style::default());
+}
+
+fn ci_mark(glyphs: &Glyphs, status: CiStatus) -> (char, Color) {
+    let [success, failure, pending] = glyphs.ci;
+    match status {
+        CiStatus::Success => (success, Color::Green),
+        CiStatus::Failure => (failure, Color::Red),
+        CiStatus::Pending => (pending, Color::Yellow),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use projector_core::config::{Backend, Config};
+    use projector_core::i18n::{self, Lang};
+    use projector_core::location::{Entry, Location};
+    use projector_core::runner::DryRunRunner;
+    use projector_core::state::ViewPrefs;
+    use projector_core::Error;
+
+    use super::*;
+
+    /// `--stdin` で渡したように、`names` を候補に並べた App
+    fn app(names: &[&str]) -> App {
+        i18n::set_lang(Lang::En);
+        let candidates = names
+            .iter()
+            .map(|name| {
+                let location = Location::Local(format!("/work/{}", name).into());
+                Entry {
+                    name: name.to_string(),
+                    location,
+                    alias: false,
+                    symlink: false,
+                }
+            })
+            .collect();
+        // tmux の有無でお知らせが変わらないようにする
+        let config = Config {
+            backend: Backend::Shell,
+            ..Config::default()
+        };
+        App::new(
+            vec![],
+            Some(candidates),
+            &config,
+            ViewPrefs::default(),
+            None,
+            Arc::new(DryRunRunner),
+        )
+    }
+
+    fn type_filter(app: &mut App, text: &str) {
+        app.start_filter();
+        for c in text.chars() {
+            app.edit_filter(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn list_with_details() {
+        let mut app = app(&["api", "frontend", "infra"]);
+        app.move_down();
+        insta::assert_snapshot!(draw(&app, 80, 10).text());
+    }
+
+    #[test]
+    fn narrow_without_details() {
+        let app = app(&["api", "a-very-long-project-name-that-does-not-fit"]);
+        insta::assert_snapshot!(draw(&app, 30, 7).text());
+    }
+
+    #[test]
+    fn filtered() {
+        let mut app = app(&["api", "frontend", "infra", "api-gateway"]);
+        type_filter(&mut app, "api");
+        insta::assert_snapshot!(draw(&app, 80, 8).text());
+    }
+
+    #[test]
+    fn no_matches() {
+        let mut app = app(&["api", "frontend"]);
+        type_filter(&mut app, "zzz");
+        insta::assert_snapshot!(draw(&app, 80, 6).text());
+    }
+
+    #[test]
+    fn error_dialog() {
+        let mut app = app(&["api"]);
+        app.show_error(&Error::NoMatch("frontend".to_string()));
+        insta::assert_snapshot!(draw(&app, 70, 12).text());
+    }
+
+    #[test]
+    fn toast() {
+        let mut app = app(&["api"]);
+        app.notify("Copied /work/api");
+        insta::assert_snapshot!(draw(&app, 70, 8).text());
+    }
+
+    #[test]
+    fn wide_characters_are_clipped() {
+        let app = app(&["日本語のプロジェクト", "api"]);
+        insta::assert_snapshot!(draw(&app, 20, 6).text());
+    }
+}
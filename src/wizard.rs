@@ -0,0 +1,80 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use projector_core::config::{self, Backend, Config};
+use projector_core::i18n::Msg;
+use projector_core::location::Location;
+use projector_core::{paths, Result};
+
+const DEFAULT_ROOT: &str = "~/Developer";
+
+/// 設定ファイルがまだ無く、端末で対話できるなら初回設定を行う
+pub fn should_run() -> bool {
+    paths::config_file().is_some_and(|path| !path.exists())
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+}
+
+/// 質問して最初の設定ファイルを書き、読み込み直した設定を返す。入力が途切れたら None
+pub fn run() -> Result<Option<Config>> {
+    let Some(path) = paths::config_file() else {
+        return Ok(None);
+    };
+    println!("{}", Msg::WizardIntro(&paths::shorten_path(&path)));
+
+    let Some(answer) = ask(&Msg::WizardRoots)? else {
+        return Ok(None);
+    };
+    let mut roots: Vec<String> = answer
+        .split(',')
+        .map(str::trim)
+        .filter(|root| !root.is_empty())
+        .map(str::to_string)
+        .collect();
+    if roots.is_empty() {
+        roots.push(DEFAULT_ROOT.to_string());
+    }
+    for root in &roots {
+        let Some(dir) = Location::parse(root).local_path().map(|p| p.to_path_buf()) else {
+            continue;
+        };
+        if dir.exists() {
+            continue;
+        }
+        let Some(answer) = ask(&Msg::WizardCreate(root))? else {
+            return Ok(None);
+        };
+        if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") {
+            fs::create_dir_all(&dir)?;
+        }
+    }
+
+    let backend = loop {
+        let Some(answer) = ask(&Msg::WizardBackend)? else {
+            return Ok(None);
+        };
+        if answer.is_empty() {
+            break Backend::Auto;
+        }
+        match Backend::parse(&answer) {
+            Some(backend) => break backend,
+            None => println!("{}", Msg::WizardInvalid),
+        }
+    };
+
+    config::write_initial(&path, &roots, backend)?;
+    println!("{}", Msg::WizardSaved(&paths::shorten_path(&path)));
+    config::load().map(Some)
+}
+
+/// 質問を表示して1行読む。入力の終わりなら None
+fn ask(question: &Msg) -> Result<Option<String>> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
@@ -0,0 +1,49 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Task<T> = Box<dyn FnOnce() -> T + Send>;
+
+/// バックグラウンドでタスクを実行し、結果をチャネルで返すワーカープール
+pub struct Worker<T> {
+    tasks: Sender<Task<T>>,
+    results: Receiver<T>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    pub fn new(threads: usize) -> Self {
+        let (task_tx, task_rx) = mpsc::channel::<Task<T>>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let task_rx = Arc::new(Mutex::new(task_rx));
+
+        for _ in 0..threads.max(1) {
+            let task_rx = Arc::clone(&task_rx);
+            let result_tx = result_tx.clone();
+            thread::spawn(move || loop {
+                // ロックはタスクの受け取りの間だけ保持する
+                let task = match task_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => return,
+                };
+                let Ok(task) = task else { return };
+                if result_tx.send(task()).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Worker {
+            tasks: task_tx,
+            results: result_rx,
+        }
+    }
+
+    pub fn submit(&self, task: impl FnOnce() -> T + Send + 'static) {
+        // ワーカーが全て終了している場合は黙って捨てる
+        let _ = self.tasks.send(Box::new(task));
+    }
+
+    pub fn try_recv(&self) -> Option<T> {
+        self.results.try_recv().ok()
+    }
+}